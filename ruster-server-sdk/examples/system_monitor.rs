@@ -0,0 +1,173 @@
+//! A Server that watches load and memory through /proc.
+//!
+//! Run it by hand with `cargo run --example system_monitor -- --interval 5`,
+//! or let Ruster supervise it from a `[[servers]]` entry.
+
+use std::fs;
+use std::time::Duration;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use ruster_server_sdk::{reply, Emitter, Reply, ServerBuilder};
+
+/// Load per CPU above which `cpu_high` is emitted
+const CPU_HIGH: f64 = 0.9;
+/// Share of available memory below which `memory_low` is emitted
+const MEMORY_LOW: f64 = 0.1;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    let interval = args.iter().position(|a| a == "--interval")
+        .and_then(|i| args.get(i + 1))
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(5);
+
+    let server = ServerBuilder::new("system-monitor")
+        .version(env!("CARGO_PKG_VERSION"))
+        .event("cpu_high", json!({
+            "type": "object",
+            "properties": {"load": {"type": "number"}, "cpus": {"type": "integer"}, "severity": {"type": "string"}},
+            "required": ["load", "cpus"]
+        }))
+        .event("memory_low", json!({
+            "type": "object",
+            "properties": {"available_mb": {"type": "integer"}, "percent": {"type": "number"}, "severity": {"type": "string"}},
+            "required": ["available_mb", "percent"]
+        }))
+        .tool("load", "Load averages over 1, 5 and 15 minutes and the number of CPUs", json!({"type": "object"}), |_| async { load() })
+        .tool("memory", "Total and available memory in MB", json!({"type": "object"}), |_| async { memory() })
+        .tool(
+            "top_processes",
+            "Lists the processes that used the most CPU time",
+            json!({"type": "object", "properties": {"limit": {"type": "integer", "description": "How many processes (default 5)"}}}),
+            |req| async move {
+                #[derive(Deserialize)]
+                struct Args { limit: Option<usize> }
+                let args: Args = req.params()?;
+                top_processes(args.limit.unwrap_or(5))
+            },
+        )
+        .on_error(|e| eprintln!("system-monitor: {:#}", e))
+        .build();
+
+    tokio::spawn(watch(server.emitter(), Duration::from_secs(interval)));
+    eprintln!("system-monitor listening on {}", server.socket_path().display());
+    server.run().await
+}
+
+/// Emits an event when load or memory crosses its threshold, not on every sample.
+async fn watch(emitter: Emitter, interval: Duration) {
+    let (mut cpu_high, mut memory_low) = (false, false);
+    loop {
+        if let (Some(load), Some(cpus)) = (loadavg().map(|l| l[0]), cpu_count()) {
+            let high = load / cpus as f64 > CPU_HIGH;
+            if high && !cpu_high {
+                let _ = emitter.emit("cpu_high", json!({"load": load, "cpus": cpus, "severity": "warning"}));
+            }
+            cpu_high = high;
+        }
+        if let Some((total, available)) = meminfo() {
+            let percent = available as f64 / total as f64;
+            let low = percent < MEMORY_LOW;
+            if low && !memory_low {
+                let severity = if percent < MEMORY_LOW / 2.0 { "critical" } else { "warning" };
+                let _ = emitter.emit("memory_low", json!({"available_mb": available / 1024, "percent": percent * 100.0, "severity": severity}));
+            }
+            memory_low = low;
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+fn load() -> Reply {
+    match (loadavg(), cpu_count()) {
+        (Some(l), Some(cpus)) => reply::ok(json!({"load1": l[0], "load5": l[1], "load15": l[2], "cpus": cpus})),
+        _ => reply::error("unavailable", "Cannot read /proc/loadavg"),
+    }
+}
+
+fn memory() -> Reply {
+    match meminfo() {
+        Some((total, available)) => reply::ok(json!({"total_mb": total / 1024, "available_mb": available / 1024})),
+        None => reply::error("unavailable", "Cannot read /proc/meminfo"),
+    }
+}
+
+fn top_processes(limit: usize) -> Reply {
+    let mut procs: Vec<Value> = Vec::new();
+    let entries = fs::read_dir("/proc").map_err(anyhow::Error::from)?;
+    for entry in entries.flatten() {
+        let Ok(pid) = entry.file_name().to_string_lossy().parse::<u32>() else { continue };
+        // Processes may exit while we look
+        let Ok(stat) = fs::read_to_string(entry.path().join("stat")) else { continue };
+        if let Some((name, ticks)) = parse_stat(&stat) {
+            procs.push(json!({"pid": pid, "name": name, "cpu_ticks": ticks}));
+        }
+    }
+    procs.sort_by_key(|p| std::cmp::Reverse(p["cpu_ticks"].as_u64().unwrap_or(0)));
+    procs.truncate(limit);
+    reply::ok(procs)
+}
+
+/// The command name and user plus system CPU ticks from `/proc/<pid>/stat`.
+fn parse_stat(stat: &str) -> Option<(String, u64)> {
+    // The name is in parentheses and may itself contain spaces or parentheses
+    let (start, end) = (stat.find('(')?, stat.rfind(')')?);
+    let name = stat[start + 1..end].to_string();
+    let fields: Vec<&str> = stat[end + 1..].split_whitespace().collect();
+    // utime and stime are fields 14 and 15; `fields` starts at field 3
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    Some((name, utime + stime))
+}
+
+fn loadavg() -> Option<[f64; 3]> {
+    let text = fs::read_to_string("/proc/loadavg").ok()?;
+    let mut parts = text.split_whitespace().map(|s| s.parse::<f64>().ok());
+    Some([parts.next()??, parts.next()??, parts.next()??])
+}
+
+fn cpu_count() -> Option<usize> {
+    let stat = fs::read_to_string("/proc/stat").ok()?;
+    let n = stat.lines()
+        .filter(|l| l.starts_with("cpu") && l.as_bytes().get(3).is_some_and(u8::is_ascii_digit))
+        .count();
+    (n > 0).then_some(n)
+}
+
+/// `(MemTotal, MemAvailable)` in kB.
+fn meminfo() -> Option<(u64, u64)> {
+    let text = fs::read_to_string("/proc/meminfo").ok()?;
+    let field = |key: &str| {
+        text.lines()
+            .find(|l| l.starts_with(key))
+            .and_then(|l| l.split_whitespace().nth(1))
+            .and_then(|v| v.parse::<u64>().ok())
+    };
+    Some((field("MemTotal:")?, field("MemAvailable:")?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ruster_server_sdk::testing::TestClient;
+
+    #[test]
+    fn test_parse_stat_with_spaces_in_name() {
+        let stat = "1234 (Web Content (x)) S 1 1234 1234 0 -1 4194560 100 0 0 0 250 50 0 0 20 0 1 0 100 0 0";
+        assert_eq!(parse_stat(stat), Some(("Web Content (x)".to_string(), 300)));
+    }
+
+    #[tokio::test]
+    async fn test_tools_read_proc() {
+        let server = ServerBuilder::new("system-monitor")
+            .tool("load", "", json!({}), |_| async { load() })
+            .tool("top_processes", "", json!({}), |_| async { top_processes(3) })
+            .build();
+        let mut client = TestClient::connect(&server).await.unwrap();
+        let load = client.call_tool("load", json!({})).await.unwrap().unwrap();
+        assert!(load["cpus"].as_u64().unwrap() >= 1);
+        let top = client.call_tool("top_processes", json!({})).await.unwrap().unwrap();
+        assert!(!top.as_array().unwrap().is_empty());
+    }
+}
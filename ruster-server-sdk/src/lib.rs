@@ -0,0 +1,36 @@
+//! Write Servers for Ruster: processes that push events to sessions and
+//! offer tools to the LLM over the protocol described in servers.md.
+//!
+//! ```no_run
+//! use ruster_server_sdk::{reply, ServerBuilder};
+//! use serde_json::json;
+//!
+//! #[tokio::main]
+//! async fn main() -> anyhow::Result<()> {
+//!     let server = ServerBuilder::new("clock")
+//!         .event("tick", json!({"type": "object", "properties": {"unix": {"type": "integer"}}}))
+//!         .tool("now", "Current Unix time", json!({"type": "object"}), |_| async {
+//!             reply::ok(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0))
+//!         })
+//!         .build();
+//!
+//!     let emitter = server.emitter();
+//!     tokio::spawn(async move {
+//!         loop {
+//!             tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+//!             let _ = emitter.emit("tick", json!({"unix": 0}));
+//!         }
+//!     });
+//!
+//!     // Listens on /tmp/ruster-srv-clock.sock, or where Ruster's supervisor says
+//!     server.run().await
+//! }
+//! ```
+
+pub mod protocol;
+pub mod reply;
+mod server;
+pub mod testing;
+
+pub use reply::{Reply, ReplyError};
+pub use server::{Emitter, Request, Server, ServerBuilder};
@@ -0,0 +1,104 @@
+//! Wire format of the Ruster Server protocol, version 1 (see servers.md).
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Ruster discovers Servers as `<dir>/ruster-srv-<name>.sock`.
+pub const SOCKET_PREFIX: &str = "ruster-srv-";
+pub const SOCKET_SUFFIX: &str = ".sock";
+/// Directory Ruster watches by default (`server_discovery_dir`).
+pub const DEFAULT_DISCOVERY_DIR: &str = "/tmp";
+
+/// Where the Server `name` listens inside the discovery directory `dir`.
+pub fn socket_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}{}{}", SOCKET_PREFIX, name, SOCKET_SUFFIX))
+}
+
+/// Error codes carried by `error` frames.
+pub mod error_code {
+    pub const UNSUPPORTED_VERSION: &str = "unsupported_version";
+    pub const INVALID_FRAME: &str = "invalid_frame";
+    pub const UNEXPECTED_FRAME: &str = "unexpected_frame";
+    pub const UNKNOWN_EVENT: &str = "unknown_event";
+    pub const UNKNOWN_METHOD: &str = "unknown_method";
+    /// A `tool_call` named a tool the Server does not advertise
+    pub const UNKNOWN_TOOL: &str = "unknown_tool";
+    /// The params of a request do not have the expected shape
+    pub const INVALID_PARAMS: &str = "invalid_params";
+    /// A handler failed
+    pub const INTERNAL: &str = "internal";
+}
+
+/// Sent by both peers when a connection opens. Ruster speaks first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Hello {
+    pub protocol: u32,
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    /// JSON Schema of the `data` of each event type the Server emits
+    #[serde(default)]
+    pub events: BTreeMap<String, Value>,
+    /// Tools the LLM may call through `tool_call` requests
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<ToolSpec>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ToolSpec {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// JSON Schema of the arguments
+    #[serde(default)]
+    pub parameters: Value,
+}
+
+/// One line of the protocol.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Frame {
+    Hello(Hello),
+    Event {
+        event: String,
+        #[serde(default)]
+        data: Value,
+    },
+    Request {
+        id: String,
+        method: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        session_id: Option<String>,
+        #[serde(default)]
+        params: Value,
+    },
+    Reply {
+        id: String,
+        #[serde(default)]
+        result: Value,
+    },
+    Error {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        code: String,
+        message: String,
+    },
+}
+
+impl Frame {
+    pub fn error(id: Option<String>, code: &str, message: impl Into<String>) -> Self {
+        Frame::Error { id, code: code.to_string(), message: message.into() }
+    }
+
+    pub fn to_line(&self) -> String {
+        let mut line = serde_json::to_string(self).unwrap_or_default();
+        line.push('\n');
+        line
+    }
+}
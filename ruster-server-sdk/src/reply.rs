@@ -0,0 +1,55 @@
+//! Building the answers handlers return.
+
+use std::fmt;
+use serde::Serialize;
+use serde_json::Value;
+use crate::protocol::error_code;
+
+/// What a request handler returns: the `result` of a `reply` frame, or an `error` frame.
+pub type Reply = Result<Value, ReplyError>;
+
+/// Sent back as an `error` frame carrying the request's id.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplyError {
+    pub code: String,
+    pub message: String,
+}
+
+impl ReplyError {
+    pub fn new(code: &str, message: impl Into<String>) -> Self {
+        Self { code: code.to_string(), message: message.into() }
+    }
+}
+
+impl fmt::Display for ReplyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for ReplyError {}
+
+/// Handlers can use `?` on `anyhow` errors; they are reported as `internal`.
+impl From<anyhow::Error> for ReplyError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::new(error_code::INTERNAL, e.to_string())
+    }
+}
+
+/// Replies with any serializable value.
+pub fn ok(value: impl Serialize) -> Reply {
+    serde_json::to_value(value).map_err(|e| ReplyError::new(error_code::INTERNAL, e.to_string()))
+}
+
+/// Replies with text. Ruster hands a string result to the model as-is.
+pub fn text(text: impl Into<String>) -> Reply {
+    Ok(Value::String(text.into()))
+}
+
+pub fn error(code: &str, message: impl Into<String>) -> Reply {
+    Err(ReplyError::new(code, message))
+}
+
+pub fn invalid_params(message: impl Into<String>) -> Reply {
+    error(error_code::INVALID_PARAMS, message)
+}
@@ -0,0 +1,476 @@
+//! Declaring a Server and serving Ruster connections.
+
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{broadcast, mpsc};
+use anyhow::{Result, anyhow};
+use crate::protocol::{self, error_code, Frame, Hello, ToolSpec, PROTOCOL_VERSION};
+use crate::reply::{Reply, ReplyError};
+
+/// A request from Ruster. For `tool_call`, `params` holds the tool's arguments.
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub id: String,
+    pub method: String,
+    /// The session the request is made on behalf of
+    pub session_id: Option<String>,
+    pub params: Value,
+}
+
+impl Request {
+    /// Deserializes `params`, failing with `invalid_params`.
+    pub fn params<T: DeserializeOwned>(&self) -> Result<T, ReplyError> {
+        serde_json::from_value(self.params.clone())
+            .map_err(|e| ReplyError::new(error_code::INVALID_PARAMS, e.to_string()))
+    }
+}
+
+type Handler = Arc<dyn Fn(Request) -> Pin<Box<dyn Future<Output = Reply> + Send>> + Send + Sync>;
+
+fn handler<F, Fut>(f: F) -> Handler
+where
+    F: Fn(Request) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Reply> + Send + 'static,
+{
+    Arc::new(move |req| Box::pin(f(req)))
+}
+
+type ErrorHook = Arc<dyn Fn(anyhow::Error) + Send + Sync>;
+
+/// Declares the events, tools and methods of a Server.
+pub struct ServerBuilder {
+    name: String,
+    version: String,
+    events: BTreeMap<String, Value>,
+    tools: Vec<(ToolSpec, Handler)>,
+    methods: HashMap<String, Handler>,
+    on_error: Option<ErrorHook>,
+}
+
+impl ServerBuilder {
+    /// `name` is the name used in `server attach` and in the socket name.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version: "0.1.0".to_string(),
+            events: BTreeMap::new(),
+            tools: Vec::new(),
+            methods: HashMap::new(),
+            on_error: None,
+        }
+    }
+
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    /// Declares an event type with the JSON Schema of its `data`.
+    pub fn event(mut self, name: impl Into<String>, schema: Value) -> Self {
+        self.events.insert(name.into(), schema);
+        self
+    }
+
+    /// Advertises a tool the LLM may call. `parameters` is the JSON Schema of its arguments.
+    pub fn tool<F, Fut>(mut self, name: impl Into<String>, description: impl Into<String>, parameters: Value, f: F) -> Self
+    where
+        F: Fn(Request) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Reply> + Send + 'static,
+    {
+        let spec = ToolSpec { name: name.into(), description: description.into(), parameters };
+        self.tools.push((spec, handler(f)));
+        self
+    }
+
+    /// Handles requests of `method`.
+    pub fn method<F, Fut>(mut self, method: impl Into<String>, f: F) -> Self
+    where
+        F: Fn(Request) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Reply> + Send + 'static,
+    {
+        self.methods.insert(method.into(), handler(f));
+        self
+    }
+
+    /// Handles the messages sessions send with `server send`.
+    pub fn on_message<F, Fut>(self, f: F) -> Self
+    where
+        F: Fn(Request) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Reply> + Send + 'static,
+    {
+        self.method("message", f)
+    }
+
+    /// Called with errors that have no caller to return to: connections that
+    /// fail while `listen` serves them, and `error` frames Ruster sends
+    /// outside a request. They are ignored by default.
+    pub fn on_error<F>(mut self, f: F) -> Self
+    where
+        F: Fn(anyhow::Error) + Send + Sync + 'static,
+    {
+        self.on_error = Some(Arc::new(f));
+        self
+    }
+
+    pub fn build(self) -> Server {
+        let mut capabilities = Vec::new();
+        if !self.events.is_empty() {
+            capabilities.push("events".to_string());
+        }
+        if !self.methods.is_empty() {
+            capabilities.push("requests".to_string());
+        }
+        if !self.tools.is_empty() {
+            capabilities.push("tools".to_string());
+        }
+        let hello = Hello {
+            protocol: PROTOCOL_VERSION,
+            name: self.name,
+            version: self.version,
+            capabilities,
+            events: self.events,
+            tools: self.tools.iter().map(|(spec, _)| spec.clone()).collect(),
+        };
+        let (events, _) = broadcast::channel(256);
+        Server {
+            inner: Arc::new(Inner {
+                hello,
+                tools: self.tools.into_iter().map(|(spec, h)| (spec.name, h)).collect(),
+                methods: self.methods,
+                on_error: self.on_error,
+                events,
+            }),
+        }
+    }
+}
+
+struct Inner {
+    hello: Hello,
+    tools: HashMap<String, Handler>,
+    methods: HashMap<String, Handler>,
+    on_error: Option<ErrorHook>,
+    /// Event frames, fanned out to every connection
+    events: broadcast::Sender<String>,
+}
+
+/// A built Server. Cheap to clone.
+#[derive(Clone)]
+pub struct Server {
+    inner: Arc<Inner>,
+}
+
+/// Pushes events to every connected Ruster.
+#[derive(Clone)]
+pub struct Emitter {
+    inner: Arc<Inner>,
+}
+
+impl Emitter {
+    /// Emits an event. Fails if the Server declared event types and this is
+    /// not one of them. Events emitted while nobody is connected are dropped.
+    pub fn emit(&self, event: &str, data: Value) -> Result<()> {
+        let declared = &self.inner.hello.events;
+        if !declared.is_empty() && !declared.contains_key(event) {
+            return Err(anyhow!("Event type '{}' was not declared", event));
+        }
+        let _ = self.inner.events.send(Frame::Event { event: event.to_string(), data }.to_line());
+        Ok(())
+    }
+}
+
+impl Server {
+    pub fn hello(&self) -> &Hello {
+        &self.inner.hello
+    }
+
+    pub fn emitter(&self) -> Emitter {
+        Emitter { inner: self.inner.clone() }
+    }
+
+    /// The socket a supervised Server was told to use (`RUSTER_SERVER_SOCKET`),
+    /// or `/tmp/ruster-srv-<name>.sock`.
+    pub fn socket_path(&self) -> PathBuf {
+        std::env::var_os("RUSTER_SERVER_SOCKET")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| protocol::socket_path(Path::new(protocol::DEFAULT_DISCOVERY_DIR), &self.inner.hello.name))
+    }
+
+    /// Listens on `socket_path()` and serves connections until SIGINT or
+    /// SIGTERM, then removes the socket.
+    pub async fn run(&self) -> Result<()> {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut sigint = signal(SignalKind::interrupt())?;
+        let mut sigterm = signal(SignalKind::terminate())?;
+        let path = self.socket_path();
+        tokio::select! {
+            res = self.listen(&path) => res,
+            _ = sigint.recv() => Ok(()),
+            _ = sigterm.recv() => Ok(()),
+        }
+    }
+
+    /// Listens on `path` and serves each connection. A socket left behind by
+    /// a process that exited is replaced; one that still accepts connections
+    /// is an error. The socket is removed when listening stops, including
+    /// when the returned future is dropped.
+    pub async fn listen(&self, path: &Path) -> Result<()> {
+        if path.exists() {
+            match UnixStream::connect(path).await {
+                Ok(_) => return Err(anyhow!("Another process is listening on {}", path.display())),
+                Err(e) if e.kind() == std::io::ErrorKind::ConnectionRefused => tokio::fs::remove_file(path).await?,
+                Err(e) => return Err(anyhow!("Cannot check {}: {}", path.display(), e)),
+            }
+        }
+        let listener = UnixListener::bind(path)
+            .map_err(|e| anyhow!("Failed to bind {}: {}", path.display(), e))?;
+        let _socket = SocketFile(path.to_path_buf());
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let server = self.clone();
+            tokio::spawn(async move {
+                let (reader, writer) = stream.into_split();
+                if let Err(e) = server.serve(reader, writer).await {
+                    server.report(e.context("Connection failed"));
+                }
+            });
+        }
+    }
+
+    /// Serves one Ruster connection: the handshake, then requests and events
+    /// until Ruster disconnects.
+    pub async fn serve<R, W>(&self, reader: R, writer: W) -> Result<()>
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
+        let mut writer = writer;
+        let mut lines = BufReader::new(reader).lines();
+
+        // A peer checking whether the socket is alive, as `listen` does
+        let Some(first) = lines.next_line().await? else { return Ok(()) };
+        match serde_json::from_str::<Frame>(&first) {
+            Ok(Frame::Hello(hello)) if hello.protocol == PROTOCOL_VERSION => {}
+            Ok(Frame::Hello(hello)) => {
+                let msg = format!("Protocol version {} is not supported, expected {}", hello.protocol, PROTOCOL_VERSION);
+                writer.write_all(Frame::error(None, error_code::UNSUPPORTED_VERSION, msg.clone()).to_line().as_bytes()).await?;
+                return Err(anyhow!(msg));
+            }
+            _ => {
+                writer.write_all(Frame::error(None, error_code::UNEXPECTED_FRAME, "Expected hello").to_line().as_bytes()).await?;
+                return Err(anyhow!("Ruster did not start with hello"));
+            }
+        }
+        writer.write_all(Frame::Hello(self.inner.hello.clone()).to_line().as_bytes()).await?;
+        writer.flush().await?;
+        let mut events = self.inner.events.subscribe();
+
+        let (out, mut out_rx) = mpsc::channel::<String>(100);
+        let write_task = tokio::spawn(async move {
+            while let Some(line) = out_rx.recv().await {
+                if writer.write_all(line.as_bytes()).await.is_err() { break; }
+                if writer.flush().await.is_err() { break; }
+            }
+        });
+
+        loop {
+            tokio::select! {
+                line = lines.next_line() => match line? {
+                    Some(line) => self.handle_line(&line, &out).await,
+                    None => break,
+                },
+                event = events.recv() => match event {
+                    Ok(line) => { let _ = out.send(line).await; }
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+            }
+        }
+        drop(out);
+        let _ = write_task.await;
+        Ok(())
+    }
+
+    async fn handle_line(&self, line: &str, out: &mpsc::Sender<String>) {
+        if line.trim().is_empty() {
+            return;
+        }
+        let frame = match serde_json::from_str::<Frame>(line) {
+            Ok(frame) => frame,
+            Err(e) => {
+                let _ = out.send(Frame::error(None, error_code::INVALID_FRAME, e.to_string()).to_line()).await;
+                return;
+            }
+        };
+        match frame {
+            Frame::Request { id, method, session_id, params } => {
+                let (handler, params) = if method == "tool_call" {
+                    let name = params["name"].as_str().unwrap_or_default();
+                    match self.inner.tools.get(name) {
+                        Some(h) => (h.clone(), params["arguments"].clone()),
+                        None => {
+                            let msg = format!("No tool '{}'", name);
+                            let _ = out.send(Frame::error(Some(id), error_code::UNKNOWN_TOOL, msg).to_line()).await;
+                            return;
+                        }
+                    }
+                } else {
+                    match self.inner.methods.get(&method) {
+                        Some(h) => (h.clone(), params),
+                        None => {
+                            let msg = format!("No method '{}'", method);
+                            let _ = out.send(Frame::error(Some(id), error_code::UNKNOWN_METHOD, msg).to_line()).await;
+                            return;
+                        }
+                    }
+                };
+                // Handlers run concurrently; Ruster correlates replies by id
+                let out = out.clone();
+                tokio::spawn(async move {
+                    let request = Request { id: id.clone(), method, session_id, params };
+                    let frame = match handler(request).await {
+                        Ok(result) => Frame::Reply { id, result },
+                        Err(e) => Frame::error(Some(id), &e.code, e.message),
+                    };
+                    let _ = out.send(frame.to_line()).await;
+                });
+            }
+            Frame::Hello(_) => {
+                let _ = out.send(Frame::error(None, error_code::UNEXPECTED_FRAME, "Handshake already completed").to_line()).await;
+            }
+            Frame::Error { id: None, code, message } => {
+                self.report(anyhow!("Ruster reported {}: {}", code, message));
+            }
+            // Ruster sends no requests of its own that would be answered here
+            Frame::Event { .. } | Frame::Reply { .. } | Frame::Error { .. } => {}
+        }
+    }
+
+    fn report(&self, error: anyhow::Error) {
+        if let Some(hook) = &self.inner.on_error {
+            hook(error);
+        }
+    }
+}
+
+/// Removes a bound socket file when dropped.
+struct SocketFile(PathBuf);
+
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::reply;
+    use crate::testing::TestClient;
+
+    fn server() -> Server {
+        ServerBuilder::new("demo")
+            .version("2.0.0")
+            .event("ping", json!({"type": "object"}))
+            .tool("add", "Adds two numbers", json!({"type": "object"}), |req| async move {
+                #[derive(serde::Deserialize)]
+                struct Args { a: i64, b: i64 }
+                let args: Args = req.params()?;
+                reply::ok(args.a + args.b)
+            })
+            .tool("slow", "Answers late", json!({}), |_| async {
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                reply::text("done")
+            })
+            .on_message(|req| async move { reply::ok(json!({"session": req.session_id, "got": req.params})) })
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_handshake_tools_messages_and_events() {
+        let server = server();
+        let mut client = TestClient::connect(&server).await.unwrap();
+        assert_eq!(client.hello.version, "2.0.0");
+        assert_eq!(client.hello.capabilities, ["events", "requests", "tools"]);
+        assert_eq!(client.hello.tools.len(), 2);
+
+        assert_eq!(client.call_tool("add", json!({"a": 2, "b": 3})).await.unwrap(), Ok(json!(5)));
+        let bad = client.call_tool("add", json!({"a": "x"})).await.unwrap().unwrap_err();
+        assert_eq!(bad.code, error_code::INVALID_PARAMS);
+        let missing = client.call_tool("nope", json!({})).await.unwrap().unwrap_err();
+        assert_eq!(missing.code, error_code::UNKNOWN_TOOL);
+        let method = client.request("status", None, json!(null)).await.unwrap().unwrap_err();
+        assert_eq!(method.code, error_code::UNKNOWN_METHOD);
+
+        let reply = client.send_message("main", json!("hi")).await.unwrap().unwrap();
+        assert_eq!(reply, json!({"session": "main", "got": "hi"}));
+
+        let emitter = server.emitter();
+        emitter.emit("ping", json!({"n": 1})).unwrap();
+        assert!(emitter.emit("pong", json!({})).is_err());
+        assert_eq!(client.next_event().await.unwrap(), ("ping".to_string(), json!({"n": 1})));
+    }
+
+    #[tokio::test]
+    async fn test_events_arriving_while_waiting_for_a_reply_are_kept() {
+        let server = server();
+        let mut client = TestClient::connect(&server).await.unwrap();
+        let emitter = server.emitter();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            emitter.emit("ping", json!({"n": 2})).unwrap();
+        });
+        assert_eq!(client.call_tool("slow", json!({})).await.unwrap(), Ok(json!("done")));
+        assert_eq!(client.next_event().await.unwrap().1, json!({"n": 2}));
+    }
+
+    #[tokio::test]
+    async fn test_listen_replaces_only_stale_sockets() {
+        let path = std::env::temp_dir().join(format!("ruster-sdk-{}.sock", std::process::id()));
+        // Left behind by a process that exited
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+
+        let errors = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = errors.clone();
+        let server = ServerBuilder::new("demo").on_error(move |e| seen.lock().unwrap().push(e.to_string())).build();
+        let running = server.clone();
+        let listen_path = path.clone();
+        let listening = tokio::spawn(async move { running.listen(&listen_path).await });
+
+        let mut stream = loop {
+            match UnixStream::connect(&path).await {
+                Ok(stream) => break stream,
+                Err(_) => tokio::time::sleep(std::time::Duration::from_millis(10)).await,
+            }
+        };
+        let err = server.listen(&path).await.unwrap_err();
+        assert!(err.to_string().contains("Another process"), "{}", err);
+
+        // A failed handshake reaches the hook
+        stream.write_all(b"{\"type\": \"event\", \"event\": \"x\"}\n").await.unwrap();
+        let mut reply = String::new();
+        BufReader::new(&mut stream).read_line(&mut reply).await.unwrap();
+        drop(stream);
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert_eq!(errors.lock().unwrap().as_slice(), ["Connection failed"]);
+
+        // The refused second listener must not remove the first one's socket
+        assert!(path.exists());
+        listening.abort();
+        let _ = listening.await;
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_other_protocol_versions() {
+        let err = TestClient::connect_with_version(&server(), PROTOCOL_VERSION + 1).await.err().unwrap();
+        assert!(err.to_string().contains(error_code::UNSUPPORTED_VERSION), "{}", err);
+    }
+}
@@ -0,0 +1,122 @@
+//! A stand-in for Ruster, for testing Servers without a daemon.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream, Lines, ReadHalf, WriteHalf};
+use anyhow::{Result, anyhow};
+use crate::protocol::{Frame, Hello, PROTOCOL_VERSION};
+use crate::reply::{Reply, ReplyError};
+use crate::server::Server;
+
+/// How long the client waits for a frame before giving up.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Connects to a [`Server`] over an in-memory pipe and speaks the protocol
+/// the way Ruster does.
+pub struct TestClient {
+    /// The Server's hello
+    pub hello: Hello,
+    lines: Lines<BufReader<ReadHalf<DuplexStream>>>,
+    writer: WriteHalf<DuplexStream>,
+    /// Events received while waiting for a reply
+    events: VecDeque<(String, Value)>,
+    next_id: u64,
+}
+
+impl TestClient {
+    pub async fn connect(server: &Server) -> Result<Self> {
+        Self::connect_with_version(server, PROTOCOL_VERSION).await
+    }
+
+    /// Connects announcing `protocol`, to test version negotiation.
+    pub async fn connect_with_version(server: &Server, protocol: u32) -> Result<Self> {
+        let (client, remote) = tokio::io::duplex(64 * 1024);
+        let server = server.clone();
+        tokio::spawn(async move {
+            let (reader, writer) = tokio::io::split(remote);
+            let _ = server.serve(reader, writer).await;
+        });
+
+        let (reader, writer) = tokio::io::split(client);
+        let mut client = Self {
+            hello: Hello::default(),
+            lines: BufReader::new(reader).lines(),
+            writer,
+            events: VecDeque::new(),
+            next_id: 0,
+        };
+        let ours = Hello {
+            protocol,
+            name: "ruster".to_string(),
+            version: "test".to_string(),
+            capabilities: vec!["events".to_string(), "requests".to_string(), "tools".to_string()],
+            events: BTreeMap::new(),
+            tools: Vec::new(),
+        };
+        client.write(Frame::Hello(ours)).await?;
+        client.hello = match client.read().await? {
+            Frame::Hello(hello) => hello,
+            Frame::Error { code, message, .. } => return Err(anyhow!("Handshake refused ({}): {}", code, message)),
+            other => return Err(anyhow!("Expected hello, got {:?}", other)),
+        };
+        Ok(client)
+    }
+
+    /// Sends a request and waits for its answer. The outer error is a
+    /// transport failure, the inner one an `error` frame from the Server.
+    pub async fn request(&mut self, method: &str, session_id: Option<&str>, params: Value) -> Result<Reply> {
+        self.next_id += 1;
+        let id = self.next_id.to_string();
+        self.write(Frame::Request {
+            id: id.clone(),
+            method: method.to_string(),
+            session_id: session_id.map(|s| s.to_string()),
+            params,
+        }).await?;
+        loop {
+            match self.read().await? {
+                Frame::Reply { id: reply_id, result } if reply_id == id => return Ok(Ok(result)),
+                Frame::Error { id: Some(reply_id), code, message } if reply_id == id => {
+                    return Ok(Err(ReplyError { code, message }));
+                }
+                Frame::Event { event, data } => self.events.push_back((event, data)),
+                other => return Err(anyhow!("Unexpected frame {:?}", other)),
+            }
+        }
+    }
+
+    /// Calls a tool the way the LLM's tool calls reach the Server.
+    pub async fn call_tool(&mut self, name: &str, arguments: Value) -> Result<Reply> {
+        self.request("tool_call", Some("test"), json!({"name": name, "arguments": arguments})).await
+    }
+
+    /// Sends a message the way `server send` does.
+    pub async fn send_message(&mut self, session_id: &str, message: Value) -> Result<Reply> {
+        self.request("message", Some(session_id), message).await
+    }
+
+    /// The next event the Server emits, as `(type, data)`.
+    pub async fn next_event(&mut self) -> Result<(String, Value)> {
+        if let Some(event) = self.events.pop_front() {
+            return Ok(event);
+        }
+        match self.read().await? {
+            Frame::Event { event, data } => Ok((event, data)),
+            other => Err(anyhow!("Expected an event, got {:?}", other)),
+        }
+    }
+
+    async fn write(&mut self, frame: Frame) -> Result<()> {
+        self.writer.write_all(frame.to_line().as_bytes()).await?;
+        self.writer.flush().await?;
+        Ok(())
+    }
+
+    async fn read(&mut self) -> Result<Frame> {
+        let line = tokio::time::timeout(TIMEOUT, self.lines.next_line()).await
+            .map_err(|_| anyhow!("Timed out waiting for the Server"))??
+            .ok_or_else(|| anyhow!("The Server closed the connection"))?;
+        serde_json::from_str(&line).map_err(|e| anyhow!("Invalid frame from the Server: {}", e))
+    }
+}
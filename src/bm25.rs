@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+
+const K1: f32 = 1.2;
+const B: f32 = 0.75;
+
+/// Okapi BM25 index over a small, in-memory set of documents.
+pub struct Bm25Index {
+    docs: Vec<HashMap<String, usize>>,
+    doc_lens: Vec<usize>,
+    avg_len: f32,
+    doc_freq: HashMap<String, usize>,
+}
+
+/// Common English words that carry no topic and are not indexed.
+const STOPWORDS: &[&str] = &[
+    "a", "about", "all", "am", "an", "and", "any", "are", "as", "at", "be", "been", "but", "by",
+    "can", "could", "did", "do", "does", "for", "from", "had", "has", "have", "he", "her", "him",
+    "his", "how", "i", "if", "in", "into", "is", "it", "its", "me", "my", "no", "not", "of", "on",
+    "or", "our", "please", "she", "so", "some", "that", "the", "their", "them", "then", "there",
+    "these", "they", "this", "those", "to", "up", "us", "was", "we", "were", "what", "when",
+    "where", "which", "who", "why", "will", "with", "would", "you", "your",
+];
+
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_lowercase())
+        .filter(|t| !STOPWORDS.contains(&t.as_str()))
+        .collect()
+}
+
+fn query_terms(query: &str) -> Vec<String> {
+    let mut terms = tokenize(query);
+    terms.sort();
+    terms.dedup();
+    terms
+}
+
+impl Bm25Index {
+    pub fn new<S: AsRef<str>>(documents: &[S]) -> Self {
+        let mut docs = Vec::with_capacity(documents.len());
+        let mut doc_lens = Vec::with_capacity(documents.len());
+        let mut doc_freq: HashMap<String, usize> = HashMap::new();
+
+        for doc in documents {
+            let tokens = tokenize(doc.as_ref());
+            doc_lens.push(tokens.len());
+            let mut freqs: HashMap<String, usize> = HashMap::new();
+            for token in tokens {
+                *freqs.entry(token).or_default() += 1;
+            }
+            for term in freqs.keys() {
+                *doc_freq.entry(term.clone()).or_default() += 1;
+            }
+            docs.push(freqs);
+        }
+
+        let avg_len = if doc_lens.is_empty() {
+            0.0
+        } else {
+            doc_lens.iter().sum::<usize>() as f32 / doc_lens.len() as f32
+        };
+        Self { docs, doc_lens, avg_len, doc_freq }
+    }
+
+    fn idf(&self, term: &str) -> f32 {
+        let n = self.docs.len() as f32;
+        let df = self.doc_freq.get(term).copied().unwrap_or(0) as f32;
+        ((n - df + 0.5) / (df + 0.5) + 1.0).ln()
+    }
+
+    /// Returns the raw BM25 score of every document for `query`, in index order.
+    pub fn scores(&self, query: &str) -> Vec<f32> {
+        let terms = query_terms(query);
+        self.docs.iter().zip(&self.doc_lens).map(|(freqs, len)| {
+            terms.iter().map(|term| {
+                let Some(&tf) = freqs.get(term) else { return 0.0 };
+                let tf = tf as f32;
+                let norm = if self.avg_len > 0.0 { *len as f32 / self.avg_len } else { 1.0 };
+                self.idf(term) * tf * (K1 + 1.0) / (tf + K1 * (1.0 - B + B * norm))
+            }).sum()
+        }).collect()
+    }
+
+    /// Scores scaled to 0..1 against an absolute bound: the score of a document
+    /// of average length containing every query term once. A document scores
+    /// 1.0 only if it covers the whole query, whatever the other documents score.
+    pub fn normalized_scores(&self, query: &str) -> Vec<f32> {
+        let bound: f32 = query_terms(query).iter().map(|t| self.idf(t)).sum();
+        if bound <= 0.0 {
+            return vec![0.0; self.docs.len()];
+        }
+        self.scores(query).into_iter().map(|s| (s / bound).min(1.0)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bm25_ranks_matching_document_first() {
+        let index = Bm25Index::new(&[
+            "clock: Fetches current date and time using system tools.",
+            "joke-teller: Tells funny programming jokes.",
+            "weather: Reports the weather forecast.",
+        ]);
+        let scores = index.normalized_scores("what TIME is it?");
+        assert!(scores[0] > 0.8, "{:?}", scores);
+        assert_eq!(scores[1], 0.0);
+        assert_eq!(scores[2], 0.0);
+
+        assert!(index.normalized_scores("nothing relevant").iter().all(|s| *s == 0.0));
+
+        // Half of the query is unrelated to any document, so even the best match stays below 1
+        let partial = index.normalized_scores("time for kubernetes");
+        assert!(partial[0] > 0.0 && partial[0] < 0.6, "{:?}", partial);
+    }
+
+    #[test]
+    fn test_stopwords_alone_match_nothing() {
+        let index = Bm25Index::new(&[
+            "clock: Fetches the current date and time.",
+            "weather: Reports the weather for a city.",
+        ]);
+        assert!(index.normalized_scores("the a is it the").iter().all(|s| *s == 0.0));
+        assert!(index.normalized_scores("is the printer on").iter().all(|s| *s == 0.0));
+    }
+}
@@ -7,24 +7,66 @@ use home::home_dir;
 use serde_json::Value;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct Config {
     pub socket_path: String,
     pub default_model: String,
     pub rag_model: String,
     pub skills_dirs: Vec<String>,
+    // Project-local skill dirs, searched before `skills_dirs`
+    pub project_skills_dirs: Vec<String>,
     pub initial_skills: Vec<String>,
+    // RAG-selected skills are unloaded after this many turns without being relevant (0 disables)
+    pub skill_unload_after_turns: usize,
+    // How long a tool call routed to an attached server may take
+    pub server_tool_timeout_secs: u64,
+    // How long a `server send` waits for the server's reply
+    pub server_reply_timeout_secs: u64,
+    // Logged server events older than this are pruned (0 disables)
+    pub server_event_retention_secs: u64,
+    // Oldest logged server events are pruned once a session's log exceeds this size (0 disables)
+    pub server_event_max_bytes: u64,
     pub banned_skills: Vec<String>,
     pub proactive_interval_secs: u64,
     pub log_level: String,
     pub tool_run_dir: String,
     pub tool_output_lines: usize,
+    // Largest JSON tool result passed to the model unmodified
+    pub tool_json_max_bytes: usize,
+    // Tool runs older than this are pruned (0 disables)
+    pub tool_run_retention_secs: u64,
+    // Oldest tool runs are pruned once the directory exceeds this size (0 disables)
+    pub tool_run_max_bytes: u64,
+    // Size limits for the built-in file system tools
+    pub fs_max_read_bytes: u64,
+    pub fs_max_write_bytes: u64,
     pub rag_top_n: usize,
     pub rag_threshold: f32,
+    // "hybrid", "embedding" or "keyword"
+    pub rag_mode: crate::skills::RagMode,
+    // Weights of the BM25 and embedding scores in hybrid mode
+    pub rag_bm25_weight: f32,
+    pub rag_embedding_weight: f32,
+    // Also index skill instructions for keyword matching
+    pub rag_index_instructions: bool,
     // Optional extra configuration not in spec but useful
     pub proxy_url: Option<String>,
+    pub mcp_servers: Vec<crate::mcp::McpServerConfig>,
+    // Directory watched for `ruster-srv-<name>.sock` server sockets
+    pub server_discovery_dir: String,
+    // Server processes launched and supervised by Ruster
+    pub servers: Vec<crate::supervisor::ServerConfig>,
+    // Second UNIX socket exposing sessions over MCP (disabled when unset)
+    pub mcp_server_socket: Option<String>,
 }
 
 impl Config {
+    /// All skill directories in precedence order: project-local dirs first,
+    /// then `skills_dirs` as listed (user before system by default).
+    pub fn skill_search_path(&self) -> Vec<String> {
+        self.project_skills_dirs.iter().chain(&self.skills_dirs).cloned().collect()
+    }
+
     pub fn get_keys() -> Vec<String> {
         let val = serde_json::to_value(Config::default()).unwrap_or_default();
         if let Some(obj) = val.as_object() {
@@ -83,15 +125,34 @@ impl Default for Config {
                 "~/.config/ruster/skills".to_string(),
                 "/usr/share/ruster/skills".to_string(),
             ],
+            project_skills_dirs: vec![],
             initial_skills: vec![],
+            skill_unload_after_turns: 5,
+            server_tool_timeout_secs: 30,
+            server_reply_timeout_secs: 60,
+            server_event_retention_secs: 7 * 24 * 3600,
+            server_event_max_bytes: 10 * 1024 * 1024,
             banned_skills: vec![],
             proactive_interval_secs: 300,
             log_level: "info".to_string(),
             tool_run_dir: "/tmp/ruster.run".to_string(),
             tool_output_lines: 10,
+            tool_json_max_bytes: 16 * 1024,
+            tool_run_retention_secs: 7 * 24 * 3600,
+            tool_run_max_bytes: 100 * 1024 * 1024,
+            fs_max_read_bytes: 1024 * 1024,
+            fs_max_write_bytes: 1024 * 1024,
             rag_top_n: 3,
             rag_threshold: 0.4,
+            rag_mode: crate::skills::RagMode::Embedding,
+            rag_bm25_weight: 0.3,
+            rag_embedding_weight: 0.7,
+            rag_index_instructions: false,
             proxy_url: Some("http://localhost:8080".to_string()),
+            mcp_servers: vec![],
+            server_discovery_dir: ruster_server_sdk::protocol::DEFAULT_DISCOVERY_DIR.to_string(),
+            servers: vec![],
+            mcp_server_socket: None,
         }
     }
 }
@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use serde_json_path::JsonPath;
+use anyhow::{Result, anyhow};
+
+/// Severity of a server event, read from its `data.severity`.
+/// Events without a recognised severity count as `info`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Debug,
+    #[default]
+    Info,
+    Warning,
+    Error,
+    Critical,
+}
+
+impl Severity {
+    pub fn of(event: &Value) -> Self {
+        event["data"]["severity"].as_str()
+            .and_then(|s| serde_json::from_value(json!(s.to_lowercase())).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Which events of an attached server reach the session.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EventFilter {
+    // Event types to keep; empty keeps all
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<String>,
+    // JSONPath filter expressions on the event (`@`), all of which must hold
+    #[serde(default, rename = "where", skip_serializing_if = "Vec::is_empty")]
+    pub predicates: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_severity: Option<Severity>,
+}
+
+impl EventFilter {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Fails on a predicate that is not a valid JSONPath filter expression.
+    pub fn validate(&self) -> Result<()> {
+        for p in &self.predicates {
+            predicate(p)?;
+        }
+        Ok(())
+    }
+
+    /// Whether `event` (`{"event": ..., "data": ...}`) passes the filter.
+    pub fn matches(&self, event: &Value) -> bool {
+        if !self.events.is_empty() {
+            let kind = event["event"].as_str().unwrap_or_default();
+            if !self.events.iter().any(|e| e == kind) {
+                return false;
+            }
+        }
+        if let Some(min) = self.min_severity
+            && Severity::of(event) < min
+        {
+            return false;
+        }
+        let wrapped = Value::Array(vec![event.clone()]);
+        self.predicates.iter().all(|p| match predicate(p) {
+            Ok(path) => !path.query(&wrapped).is_empty(),
+            Err(_) => false,
+        })
+    }
+}
+
+/// A filter expression such as `@.data.load > 0.9` becomes `$[?...]`, which
+/// selects the event when run against `[event]`.
+fn predicate(expr: &str) -> Result<JsonPath> {
+    JsonPath::parse(&format!("$[?{}]", expr)).map_err(|e| anyhow!("Invalid filter expression '{}': {}", expr, e))
+}
+
+/// Fails on a `{...}` placeholder that is unterminated or not valid JSONPath.
+pub fn validate_template(template: &str) -> Result<()> {
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..].find('}')
+            .ok_or_else(|| anyhow!("Unterminated placeholder in template: {}", &rest[start..]))?;
+        let path = &rest[start + 1..start + end];
+        JsonPath::parse(path).map_err(|e| anyhow!("Invalid JSONPath '{}' in template: {}", path, e))?;
+        rest = &rest[start + end + 1..];
+    }
+    Ok(())
+}
+
+/// Condenses an event into a line of text. Each `{<JSONPath>}` placeholder is
+/// replaced by the first node it selects (strings without quotes), or by
+/// nothing when it selects none.
+pub fn render(template: &str, event: &Value) -> String {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else { break };
+        out.push_str(&rest[..start]);
+        let path = &rest[start + 1..start + end];
+        if let Ok(p) = JsonPath::parse(path)
+            && let Some(node) = p.query(event).first()
+        {
+            match node {
+                Value::String(s) => out.push_str(s),
+                other => out.push_str(&other.to_string()),
+            }
+        }
+        rest = &rest[start + end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_and_template() {
+        let hot = json!({"event": "cpu_high", "data": {"load": 0.97, "severity": "Warning", "top": ["make", "rustc"]}});
+        let warm = json!({"event": "cpu_high", "data": {"load": 0.6}});
+        let disk = json!({"event": "disk_full", "data": {"mount": "/", "severity": "critical"}});
+
+        let filter = EventFilter {
+            events: vec!["cpu_high".to_string()],
+            predicates: vec!["@.data.load > 0.9".to_string(), "match(@.data.top[0], 'ma.*')".to_string()],
+            min_severity: None,
+        };
+        filter.validate().unwrap();
+        assert!(filter.matches(&hot));
+        assert!(!filter.matches(&warm));
+        assert!(!filter.matches(&disk));
+
+        let severe = EventFilter { min_severity: Some(Severity::Warning), ..Default::default() };
+        assert!(severe.matches(&hot));
+        assert!(!severe.matches(&warm));
+        assert!(severe.matches(&disk));
+        assert!(EventFilter::default().matches(&warm));
+
+        let bad = EventFilter { predicates: vec!["@.data.load >".to_string()], ..Default::default() };
+        assert!(bad.validate().is_err());
+
+        let template = "{$.event}: load {$.data.load}, top {$.data.top[0]}{$.data.missing}";
+        validate_template(template).unwrap();
+        assert_eq!(render(template, &hot), "cpu_high: load 0.97, top make");
+        assert!(validate_template("{$.data").is_err());
+    }
+}
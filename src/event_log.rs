@@ -0,0 +1,218 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use chrono::{DateTime, Local};
+use anyhow::Result;
+use crate::servers::EventDeliveryMode;
+
+/// Bytes read from the end of the log to find the last sequence number.
+const TAIL_BYTES: u64 = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoggedEvent {
+    pub seq: u64,
+    pub timestamp: String,
+    pub server_name: String,
+    pub delivery: EventDeliveryMode,
+    pub content: Value,
+}
+
+/// Append-only log of the server events delivered to one session.
+/// The cursor file holds the last sequence number handed to the LLM.
+pub struct EventLog {
+    path: PathBuf,
+    cursor_path: PathBuf,
+    last_seq: u64,
+}
+
+impl EventLog {
+    pub fn open(dir: &Path) -> Result<Self> {
+        let path = dir.join("server_events.jsonl");
+        let cursor_path = dir.join("server_events.cursor");
+        let last_seq = tail_seq(&path)?;
+        Ok(Self { path, cursor_path, last_seq })
+    }
+
+    pub fn last_seq(&self) -> u64 {
+        self.last_seq
+    }
+
+    /// Appends an event and returns its sequence number.
+    pub fn append(&mut self, server_name: &str, delivery: EventDeliveryMode, content: Value) -> Result<u64> {
+        let event = LoggedEvent {
+            seq: self.last_seq + 1,
+            timestamp: Local::now().to_rfc3339(),
+            server_name: server_name.to_string(),
+            delivery,
+            content,
+        };
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(&event)?)?;
+        self.last_seq = event.seq;
+        Ok(event.seq)
+    }
+
+    /// Events with a sequence number above `seq`, oldest first.
+    pub fn since(&self, seq: u64, limit: usize) -> Result<Vec<LoggedEvent>> {
+        Ok(read(&self.path)?.filter(|e| e.seq > seq).take(limit).collect())
+    }
+
+    /// Next-turn events logged after the last turn that consumed them.
+    pub fn undelivered(&self) -> Result<Vec<LoggedEvent>> {
+        Ok(self.since(self.cursor(), usize::MAX)?
+            .into_iter()
+            .filter(|e| e.delivery == EventDeliveryMode::NextTurn)
+            .collect())
+    }
+
+    /// Records that every event logged so far has reached the LLM.
+    pub fn mark_delivered(&self) -> Result<()> {
+        fs::write(&self.cursor_path, self.last_seq.to_string())?;
+        Ok(())
+    }
+
+    /// Removes events older than `max_age`, then the oldest events once the
+    /// log exceeds `max_bytes`. The newest event, which carries the sequence
+    /// across restarts, and undelivered next-turn events are kept. Returns the
+    /// number of events removed.
+    pub fn prune(&mut self, max_age: Option<Duration>, max_bytes: Option<u64>) -> Result<usize> {
+        let events: Vec<LoggedEvent> = read(&self.path)?.collect();
+        let cursor = self.cursor();
+        let cutoff = max_age.and_then(|age| chrono::Duration::from_std(age).ok()).map(|age| Local::now() - age);
+
+        let mut kept: Vec<String> = Vec::new();
+        let mut size = 0u64;
+        for (i, event) in events.iter().enumerate().rev() {
+            let line = serde_json::to_string(event)?;
+            let protected = i + 1 == events.len()
+                || (event.seq > cursor && event.delivery == EventDeliveryMode::NextTurn);
+            let expired = cutoff.is_some_and(|cutoff| {
+                DateTime::parse_from_rfc3339(&event.timestamp).is_ok_and(|t| t < cutoff)
+            });
+            let oversized = max_bytes.is_some_and(|max| size + line.len() as u64 + 1 > max);
+            if protected || !(expired || oversized) {
+                size += line.len() as u64 + 1;
+                kept.push(line);
+            }
+        }
+
+        let removed = events.len() - kept.len();
+        if removed > 0 {
+            let tmp = self.path.with_extension("jsonl.tmp");
+            let mut file = File::create(&tmp)?;
+            for line in kept.iter().rev() {
+                writeln!(file, "{}", line)?;
+            }
+            fs::rename(&tmp, &self.path)?;
+        }
+        Ok(removed)
+    }
+
+    fn cursor(&self) -> u64 {
+        fs::read_to_string(&self.cursor_path).ok()
+            .and_then(|s| s.trim().parse().ok())
+            .unwrap_or(0)
+    }
+}
+
+/// Streams the events of a log, skipping lines that do not parse.
+fn read(path: &Path) -> Result<impl Iterator<Item = LoggedEvent>> {
+    let file = match File::open(path) {
+        Ok(file) => Some(file),
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    let path = path.to_path_buf();
+    Ok(file.into_iter()
+        .flat_map(|file| BufReader::new(file).lines().map_while(Result::ok).enumerate())
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter_map(move |(i, line)| match serde_json::from_str(&line) {
+            Ok(event) => Some(event),
+            Err(e) => {
+                tracing::warn!(path = %path.display(), line = %(i + 1), error = %e, "Skipping malformed server event.");
+                None
+            }
+        }))
+}
+
+/// The sequence number of the last event, found by reading only the end of
+/// the log unless no complete event is there.
+fn tail_seq(path: &Path) -> Result<u64> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+    let start = file.metadata()?.len().saturating_sub(TAIL_BYTES);
+    file.seek(SeekFrom::Start(start))?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail)?;
+    // The first line is usually cut off and fails to parse
+    let seq = String::from_utf8_lossy(&tail)
+        .lines()
+        .rev()
+        .find_map(|line| serde_json::from_str::<LoggedEvent>(line).ok())
+        .map(|e| e.seq);
+    match seq {
+        Some(seq) => Ok(seq),
+        None if start > 0 => Ok(read(path)?.last().map(|e| e.seq).unwrap_or(0)),
+        None => Ok(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_sequence_replay_and_undelivered() {
+        let dir = TempDir::new("events");
+
+        let mut log = EventLog::open(&dir).unwrap();
+        assert_eq!(log.append("mon", EventDeliveryMode::NextTurn, json!("a")).unwrap(), 1);
+        log.mark_delivered().unwrap();
+        log.append("mon", EventDeliveryMode::Proactive, json!("b")).unwrap();
+        log.append("mon", EventDeliveryMode::NextTurn, json!("c")).unwrap();
+
+        // A reopened log continues the sequence and still knows what is undelivered
+        let mut log = EventLog::open(&dir).unwrap();
+        assert_eq!(log.last_seq(), 3);
+        let undelivered: Vec<Value> = log.undelivered().unwrap().into_iter().map(|e| e.content).collect();
+        assert_eq!(undelivered, [json!("c")]);
+        assert_eq!(log.append("disk", EventDeliveryMode::Immediate, json!("d")).unwrap(), 4);
+
+        let seqs: Vec<u64> = log.since(1, 2).unwrap().iter().map(|e| e.seq).collect();
+        assert_eq!(seqs, [2, 3]);
+        log.mark_delivered().unwrap();
+        assert!(log.undelivered().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_prune_keeps_newest_and_undelivered() {
+        let dir = TempDir::new("events");
+
+        let mut log = EventLog::open(&dir).unwrap();
+        log.append("mon", EventDeliveryMode::Immediate, json!("a")).unwrap();
+        log.append("mon", EventDeliveryMode::NextTurn, json!("b")).unwrap();
+        log.append("mon", EventDeliveryMode::Immediate, json!("c")).unwrap();
+        log.append("mon", EventDeliveryMode::Immediate, json!("d")).unwrap();
+        // A torn write is skipped, not fatal
+        OpenOptions::new().append(true).open(dir.join("server_events.jsonl")).unwrap().write_all(b"{\"seq\": 5, \"tim\n").unwrap();
+        assert_eq!(EventLog::open(&dir).unwrap().last_seq(), 4);
+
+        assert_eq!(log.prune(Some(Duration::ZERO), None).unwrap(), 2);
+        let seqs: Vec<u64> = log.since(0, usize::MAX).unwrap().iter().map(|e| e.seq).collect();
+        assert_eq!(seqs, [2, 4]);
+        assert_eq!(EventLog::open(&dir).unwrap().last_seq(), 4);
+
+        log.mark_delivered().unwrap();
+        assert_eq!(log.prune(None, Some(1)).unwrap(), 1);
+        assert_eq!(log.since(0, usize::MAX).unwrap().len(), 1);
+        assert_eq!(log.append("mon", EventDeliveryMode::Immediate, json!("e")).unwrap(), 5);
+    }
+}
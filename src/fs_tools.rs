@@ -0,0 +1,637 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use anyhow::{Result, anyhow};
+use regex::Regex;
+use crate::config::Config;
+use crate::llm::{Tool, ToolOutput};
+
+const GREP_MAX_MATCHES: usize = 200;
+const DIFF_CONTEXT: usize = 3;
+
+/// Directories a session's file system tools are confined to.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Workspace {
+    #[serde(default)]
+    pub roots: Vec<PathBuf>,
+}
+
+impl Workspace {
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub fn add_root(&mut self, path: &str) -> Result<PathBuf> {
+        let root = crate::config::expand_path(path).canonicalize()
+            .map_err(|e| anyhow!("Invalid workspace root {}: {}", path, e))?;
+        if !root.is_dir() {
+            return Err(anyhow!("Workspace root {} is not a directory", path));
+        }
+        if !self.roots.contains(&root) {
+            self.roots.push(root.clone());
+        }
+        Ok(root)
+    }
+
+    /// Resolves `path` (relative to the first root, or absolute) and checks
+    /// that it stays inside one of the roots once symlinks are followed.
+    pub fn resolve(&self, path: &str) -> Result<PathBuf> {
+        let first = self.roots.first().ok_or_else(|| anyhow!("No workspace roots configured for this session"))?;
+        let p = Path::new(path);
+        let candidate = if p.is_absolute() { p.to_path_buf() } else { first.join(p) };
+
+        // Canonicalize the longest existing ancestor; `file_name` rejects a
+        // trailing `..` in the part that does not exist yet. A dangling
+        // symlink counts as existing, so it fails to canonicalize instead of
+        // being written through later.
+        let mut existing = candidate.as_path();
+        let mut tail = Vec::new();
+        while fs::symlink_metadata(existing).is_err() {
+            let name = existing.file_name().ok_or_else(|| anyhow!("Invalid path: {}", path))?;
+            tail.push(name.to_os_string());
+            existing = existing.parent().ok_or_else(|| anyhow!("Invalid path: {}", path))?;
+        }
+        let mut resolved = existing.canonicalize()
+            .map_err(|e| anyhow!("Cannot resolve {}: {}", path, e))?;
+        for name in tail.into_iter().rev() {
+            resolved.push(name);
+        }
+
+        let inside = self.roots.iter().any(|root| {
+            root.canonicalize().map(|r| resolved.starts_with(r)).unwrap_or(false)
+        });
+        if !inside {
+            return Err(anyhow!("Path {} is outside the session workspace", path));
+        }
+        Ok(resolved)
+    }
+}
+
+pub fn tools() -> Vec<Tool> {
+    let tool = |name: &str, description: &str, parameters: Value| Tool {
+        name: name.to_string(),
+        description: description.to_string(),
+        parameters,
+        exec: None,
+        working_dir: None,
+        mcp_server: None,
+        server: None,
+        output: ToolOutput::Text,
+    };
+    vec![
+        tool("read_file", "Reads a text file from the session workspace. Optionally returns only a range of lines.", json!({
+            "type": "object",
+            "properties": {
+                "path": { "type": "string", "description": "File path, relative to the workspace root." },
+                "offset": { "type": "integer", "description": "First line to return (0-indexed)." },
+                "limit": { "type": "integer", "description": "Number of lines to return." }
+            },
+            "required": ["path"]
+        })),
+        tool("write_file", "Creates or overwrites a file in the session workspace.", json!({
+            "type": "object",
+            "properties": {
+                "path": { "type": "string", "description": "File path, relative to the workspace root." },
+                "content": { "type": "string", "description": "The full new content of the file." }
+            },
+            "required": ["path", "content"]
+        })),
+        tool("list_dir", "Lists the entries of a directory in the session workspace.", json!({
+            "type": "object",
+            "properties": {
+                "path": { "type": "string", "description": "Directory path, relative to the workspace root (default: the root)." }
+            }
+        })),
+        tool("grep", "Searches files in the session workspace for lines matching a regular expression.", json!({
+            "type": "object",
+            "properties": {
+                "pattern": { "type": "string", "description": "Regular expression to search for." },
+                "path": { "type": "string", "description": "File or directory to search (default: the workspace root)." },
+                "glob": { "type": "string", "description": "Only search files whose name matches this glob, e.g. '*.rs'." }
+            },
+            "required": ["pattern"]
+        })),
+        tool("apply_patch", "Applies a unified diff to files in the session workspace. Use '--- /dev/null' to create and '+++ /dev/null' to delete a file.", json!({
+            "type": "object",
+            "properties": {
+                "patch": { "type": "string", "description": "The unified diff." }
+            },
+            "required": ["patch"]
+        })),
+    ]
+}
+
+/// Executes one of the file system tools, returning `None` if `name` is not one.
+/// Edits are recorded in `run_dir` so they can be audited and reverted.
+pub fn execute(name: &str, args: &Value, workspace: &Workspace, config: &Config, run_dir: &Path) -> Option<Result<String>> {
+    let res = match name {
+        "read_file" => read_file(args, workspace, config),
+        "write_file" => write_file(args, workspace, config, run_dir),
+        "list_dir" => list_dir(args, workspace),
+        "grep" => grep(args, workspace, config),
+        "apply_patch" => apply_patch(args, workspace, config, run_dir),
+        _ => return None,
+    };
+    Some(res)
+}
+
+fn read_text(path: &Path, max_bytes: u64) -> Result<String> {
+    let len = fs::metadata(path)?.len();
+    if len > max_bytes {
+        return Err(anyhow!("File {} is {} bytes, larger than the limit of {} bytes", path.display(), len, max_bytes));
+    }
+    String::from_utf8(fs::read(path)?).map_err(|_| anyhow!("File {} is not valid UTF-8 text", path.display()))
+}
+
+fn read_file(args: &Value, workspace: &Workspace, config: &Config) -> Result<String> {
+    let path = args["path"].as_str().ok_or_else(|| anyhow!("Missing path"))?;
+    let resolved = workspace.resolve(path)?;
+    let content = read_text(&resolved, config.fs_max_read_bytes)?;
+
+    if args["offset"].is_null() && args["limit"].is_null() {
+        return Ok(content);
+    }
+    let offset = args["offset"].as_u64().unwrap_or(0) as usize;
+    let limit = args["limit"].as_u64().map(|l| l as usize).unwrap_or(usize::MAX);
+    Ok(content.lines().skip(offset).take(limit).collect::<Vec<_>>().join("\n"))
+}
+
+fn write_file(args: &Value, workspace: &Workspace, config: &Config, run_dir: &Path) -> Result<String> {
+    let path = args["path"].as_str().ok_or_else(|| anyhow!("Missing path"))?;
+    let content = args["content"].as_str().ok_or_else(|| anyhow!("Missing content"))?;
+    if content.len() as u64 > config.fs_max_write_bytes {
+        return Err(anyhow!("Content is {} bytes, larger than the limit of {} bytes", content.len(), config.fs_max_write_bytes));
+    }
+    let resolved = workspace.resolve(path)?;
+    let old = if resolved.exists() { Some(read_text(&resolved, config.fs_max_read_bytes)?) } else { None };
+
+    record_change(run_dir, &resolved, old.as_deref(), Some(content))?;
+    if let Some(parent) = resolved.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&resolved, content)?;
+    Ok(format!("Wrote {} bytes to {}", content.len(), path))
+}
+
+fn list_dir(args: &Value, workspace: &Workspace) -> Result<String> {
+    let path = args["path"].as_str().unwrap_or(".");
+    let resolved = workspace.resolve(path)?;
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(&resolved)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let meta = entry.metadata()?;
+        if meta.is_dir() {
+            entries.push(format!("{}/", name));
+        } else {
+            entries.push(format!("{} ({} bytes)", name, meta.len()));
+        }
+    }
+    entries.sort();
+    if entries.is_empty() {
+        return Ok(format!("{} is empty.", path));
+    }
+    Ok(entries.join("\n"))
+}
+
+fn grep(args: &Value, workspace: &Workspace, config: &Config) -> Result<String> {
+    let pattern = args["pattern"].as_str().ok_or_else(|| anyhow!("Missing pattern"))?;
+    let re = Regex::new(pattern).map_err(|e| anyhow!("Invalid pattern: {}", e))?;
+    let name_filter = match args["glob"].as_str() {
+        Some(g) => Some(glob::Pattern::new(g).map_err(|e| anyhow!("Invalid glob: {}", e))?),
+        None => None,
+    };
+    let start = workspace.resolve(args["path"].as_str().unwrap_or("."))?;
+    let base = workspace.roots.first().cloned().unwrap_or_default();
+
+    let mut files = Vec::new();
+    collect_files(&start, &mut files)?;
+    files.sort();
+
+    let mut matches = Vec::new();
+    'files: for file in files {
+        if let Some(filter) = &name_filter {
+            let name = file.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            if !filter.matches(&name) { continue; }
+        }
+        // Skip binary and oversized files
+        let Ok(content) = read_text(&file, config.fs_max_read_bytes) else { continue };
+        let display = file.strip_prefix(&base).unwrap_or(&file).display().to_string();
+        for (i, line) in content.lines().enumerate() {
+            if re.is_match(line) {
+                matches.push(format!("{}:{}:{}", display, i + 1, line));
+                if matches.len() >= GREP_MAX_MATCHES {
+                    matches.push(format!("(Stopped after {} matches.)", GREP_MAX_MATCHES));
+                    break 'files;
+                }
+            }
+        }
+    }
+    if matches.is_empty() {
+        return Ok("No matches found.".to_string());
+    }
+    Ok(matches.join("\n"))
+}
+
+/// Collects regular files below `path`, skipping `.git` and never following symlinks.
+fn collect_files(path: &Path, out: &mut Vec<PathBuf>) -> Result<()> {
+    let meta = fs::symlink_metadata(path)?;
+    if meta.is_file() {
+        out.push(path.to_path_buf());
+    } else if meta.is_dir() {
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            if entry.file_name() == ".git" { continue; }
+            collect_files(&entry.path(), out)?;
+        }
+    }
+    Ok(())
+}
+
+// --- Patches ---
+
+struct Hunk {
+    old_start: usize,
+    lines: Vec<(char, String)>,
+}
+
+struct FilePatch {
+    old_path: Option<String>,
+    new_path: Option<String>,
+    hunks: Vec<Hunk>,
+}
+
+fn patch_path(header: &str) -> Option<String> {
+    // Drop a trailing timestamp ("path\t2024-01-01 ...") and the a/ b/ prefixes
+    let path = header.split('\t').next().unwrap_or(header).trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    Some(path.strip_prefix("a/").or_else(|| path.strip_prefix("b/")).unwrap_or(path).to_string())
+}
+
+fn parse_patch(patch: &str) -> Result<Vec<FilePatch>> {
+    let mut files: Vec<FilePatch> = Vec::new();
+    let mut lines = patch.lines().peekable();
+    while let Some(line) = lines.next() {
+        if let Some(old) = line.strip_prefix("--- ") {
+            let new = lines.next()
+                .and_then(|l| l.strip_prefix("+++ "))
+                .ok_or_else(|| anyhow!("Expected '+++' after '--- {}'", old))?;
+            files.push(FilePatch {
+                old_path: patch_path(old),
+                new_path: patch_path(new),
+                hunks: Vec::new(),
+            });
+        } else if let Some(header) = line.strip_prefix("@@ ") {
+            let file = files.last_mut().ok_or_else(|| anyhow!("Hunk before file header"))?;
+            let invalid = || anyhow!("Invalid hunk header: {}", line);
+            let mut ranges = header.split_whitespace();
+            let (old_start, mut old_left) = ranges.next().and_then(|r| r.strip_prefix('-')).and_then(hunk_range).ok_or_else(invalid)?;
+            let (_, mut new_left) = ranges.next().and_then(|r| r.strip_prefix('+')).and_then(hunk_range).ok_or_else(invalid)?;
+
+            // The line counts in the header say where the hunk ends, so a
+            // removed line starting with "-- " is not taken for a file header
+            let mut hunk = Hunk { old_start, lines: Vec::new() };
+            while old_left > 0 || new_left > 0 {
+                let next = lines.next().ok_or_else(|| anyhow!("Hunk at line {} is truncated", old_start))?;
+                let (c, text) = match next.chars().next() {
+                    Some(c @ (' ' | '-' | '+')) => (c, next[1..].to_string()),
+                    // Some tools drop the leading space of empty context lines
+                    None => (' ', String::new()),
+                    Some('\\') => continue, // "\ No newline at end of file"
+                    _ => return Err(anyhow!("Unexpected line in hunk at line {}: {}", old_start, next)),
+                };
+                let (old, new) = match c {
+                    ' ' => (1, 1),
+                    '-' => (1, 0),
+                    _ => (0, 1),
+                };
+                if old > old_left || new > new_left {
+                    return Err(anyhow!("Hunk at line {} is longer than its header says", old_start));
+                }
+                old_left -= old;
+                new_left -= new;
+                hunk.lines.push((c, text));
+            }
+            if lines.peek().is_some_and(|l| l.starts_with('\\')) {
+                lines.next();
+            }
+            file.hunks.push(hunk);
+        }
+    }
+    if files.is_empty() {
+        return Err(anyhow!("No file headers found in patch"));
+    }
+    Ok(files)
+}
+
+/// Parses `start[,count]` from a hunk header. The count defaults to 1.
+fn hunk_range(range: &str) -> Option<(usize, usize)> {
+    match range.split_once(',') {
+        Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+fn apply_hunks(original: &str, hunks: &[Hunk]) -> Result<String> {
+    let lines: Vec<&str> = original.lines().collect();
+    let mut out: Vec<String> = Vec::new();
+    let mut pos = 0;
+
+    for hunk in hunks {
+        let old: Vec<&str> = hunk.lines.iter().filter(|(c, _)| *c != '+').map(|(_, l)| l.as_str()).collect();
+        let expected = hunk.old_start.saturating_sub(1).max(pos);
+
+        // Look for the hunk's old lines near the expected position
+        let matches_at = |at: usize| at + old.len() <= lines.len() && lines[at..at + old.len()] == old[..];
+        let found = (0..=lines.len())
+            .flat_map(|d| [expected.checked_add(d), expected.checked_sub(d)])
+            .flatten()
+            .filter(|at| *at >= pos)
+            .find(|at| matches_at(*at))
+            .ok_or_else(|| anyhow!("Hunk at line {} does not apply", hunk.old_start))?;
+
+        out.extend(lines[pos..found].iter().map(|l| l.to_string()));
+        for (c, l) in &hunk.lines {
+            if *c != '-' {
+                out.push(l.clone());
+            }
+        }
+        pos = found + old.len();
+    }
+    out.extend(lines[pos..].iter().map(|l| l.to_string()));
+
+    let mut result = out.join("\n");
+    if !result.is_empty() && (original.is_empty() || original.ends_with('\n')) {
+        result.push('\n');
+    }
+    Ok(result)
+}
+
+fn apply_patch(args: &Value, workspace: &Workspace, config: &Config, run_dir: &Path) -> Result<String> {
+    let patch = args["patch"].as_str().ok_or_else(|| anyhow!("Missing patch"))?;
+
+    // Compute every result before touching the disk so a bad hunk changes nothing
+    let mut edits = Vec::new();
+    for file in parse_patch(patch)? {
+        let target = file.new_path.clone().or(file.old_path.clone()).ok_or_else(|| anyhow!("Patch has no file path"))?;
+        let resolved = workspace.resolve(&target)?;
+        let old = match &file.old_path {
+            Some(_) => Some(read_text(&resolved, config.fs_max_read_bytes)?),
+            None => None,
+        };
+        let new = match &file.new_path {
+            Some(_) => {
+                let content = apply_hunks(old.as_deref().unwrap_or_default(), &file.hunks)?;
+                if content.len() as u64 > config.fs_max_write_bytes {
+                    return Err(anyhow!("Patched {} would be {} bytes, larger than the limit of {} bytes", target, content.len(), config.fs_max_write_bytes));
+                }
+                Some(content)
+            },
+            None => None,
+        };
+        edits.push((target, resolved, old, new));
+    }
+
+    let mut summary = Vec::new();
+    for (target, resolved, old, new) in edits {
+        record_change(run_dir, &resolved, old.as_deref(), new.as_deref())?;
+        match new {
+            Some(content) => {
+                if let Some(parent) = resolved.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&resolved, &content)?;
+                summary.push(format!("{} {}", if old.is_some() { "Patched" } else { "Created" }, target));
+            },
+            None => {
+                fs::remove_file(&resolved)?;
+                summary.push(format!("Deleted {}", target));
+            }
+        }
+    }
+    Ok(summary.join("\n"))
+}
+
+// --- Change records ---
+
+/// A file edit made by a tool run. `backup` holds the previous content and is
+/// `None` when the file did not exist before.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileChange {
+    pub path: PathBuf,
+    pub backup: Option<PathBuf>,
+}
+
+fn load_changes(run_dir: &Path) -> Vec<FileChange> {
+    fs::read_to_string(run_dir.join("changes.json"))
+        .ok()
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default()
+}
+
+fn record_change(run_dir: &Path, path: &Path, old: Option<&str>, new: Option<&str>) -> Result<()> {
+    let mut changes = load_changes(run_dir);
+    let backup = match old {
+        Some(content) => {
+            let backups_dir = run_dir.join("backups");
+            fs::create_dir_all(&backups_dir)?;
+            let backup = backups_dir.join(changes.len().to_string());
+            fs::write(&backup, content)?;
+            Some(backup)
+        },
+        None => None,
+    };
+    changes.push(FileChange { path: path.to_path_buf(), backup });
+    fs::write(run_dir.join("changes.json"), serde_json::to_string_pretty(&changes)?)?;
+
+    let display = path.display().to_string();
+    let diff = unified_diff(
+        if old.is_some() { &display } else { "/dev/null" },
+        if new.is_some() { &display } else { "/dev/null" },
+        old.unwrap_or_default(),
+        new.unwrap_or_default(),
+    );
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(run_dir.join("diff"))?;
+    std::io::Write::write_all(&mut file, diff.as_bytes())?;
+    Ok(())
+}
+
+/// Restores every file changed by a tool run to its previous state.
+pub fn revert(run_dir: &Path) -> Result<Vec<String>> {
+    let changes = load_changes(run_dir);
+    if changes.is_empty() {
+        return Err(anyhow!("Tool run made no file changes"));
+    }
+    let mut reverted = Vec::new();
+    for change in changes.iter().rev() {
+        match &change.backup {
+            Some(backup) => {
+                if let Some(parent) = change.path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::copy(backup, &change.path)?;
+            },
+            None => {
+                if change.path.exists() {
+                    fs::remove_file(&change.path)?;
+                }
+            }
+        }
+        reverted.push(change.path.display().to_string());
+    }
+    Ok(reverted)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Op {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+fn diff_lines(a: &[&str], b: &[&str]) -> Vec<Op> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..].iter().rev().zip(b[prefix..].iter().rev()).take_while(|(x, y)| x == y).count();
+    let (am, bm) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut ops: Vec<Op> = (0..prefix).map(|i| Op::Equal(i, i)).collect();
+    if am.len().saturating_mul(bm.len()) > 4_000_000 {
+        // Too large for LCS: replace the whole changed region
+        ops.extend((0..am.len()).map(|i| Op::Delete(prefix + i)));
+        ops.extend((0..bm.len()).map(|j| Op::Insert(prefix + j)));
+    } else {
+        // lcs[i][j] = LCS length of am[i..] and bm[j..]
+        let mut lcs = vec![vec![0usize; bm.len() + 1]; am.len() + 1];
+        for i in (0..am.len()).rev() {
+            for j in (0..bm.len()).rev() {
+                lcs[i][j] = if am[i] == bm[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < am.len() || j < bm.len() {
+            if i < am.len() && j < bm.len() && am[i] == bm[j] {
+                ops.push(Op::Equal(prefix + i, prefix + j));
+                i += 1;
+                j += 1;
+            } else if j < bm.len() && (i == am.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+                ops.push(Op::Insert(prefix + j));
+                j += 1;
+            } else {
+                ops.push(Op::Delete(prefix + i));
+                i += 1;
+            }
+        }
+    }
+    ops.extend((0..suffix).map(|k| Op::Equal(a.len() - suffix + k, b.len() - suffix + k)));
+    ops
+}
+
+pub fn unified_diff(old_name: &str, new_name: &str, old: &str, new: &str) -> String {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    let ops = diff_lines(&a, &b);
+
+    let changes: Vec<usize> = ops.iter().enumerate()
+        .filter(|(_, op)| !matches!(op, Op::Equal(..)))
+        .map(|(k, _)| k)
+        .collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    // Group changes whose context windows overlap
+    let mut groups: Vec<(usize, usize)> = Vec::new();
+    for &k in &changes {
+        let start = k.saturating_sub(DIFF_CONTEXT);
+        let end = (k + DIFF_CONTEXT + 1).min(ops.len());
+        match groups.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => groups.push((start, end)),
+        }
+    }
+
+    let mut out = format!("--- {}\n+++ {}\n", old_name, new_name);
+    for (start, end) in groups {
+        let old_before = ops[..start].iter().filter(|op| !matches!(op, Op::Insert(_))).count();
+        let new_before = ops[..start].iter().filter(|op| !matches!(op, Op::Delete(_))).count();
+        let mut body = String::new();
+        let (mut old_count, mut new_count) = (0, 0);
+        for op in &ops[start..end] {
+            match *op {
+                Op::Equal(i, _) => { body.push_str(&format!(" {}\n", a[i])); old_count += 1; new_count += 1; },
+                Op::Delete(i) => { body.push_str(&format!("-{}\n", a[i])); old_count += 1; },
+                Op::Insert(j) => { body.push_str(&format!("+{}\n", b[j])); new_count += 1; },
+            }
+        }
+        let old_start = if old_count > 0 { old_before + 1 } else { old_before };
+        let new_start = if new_count > 0 { new_before + 1 } else { new_before };
+        out.push_str(&format!("@@ -{},{} +{},{} @@\n", old_start, old_count, new_start, new_count));
+        out.push_str(&body);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_then_patch_roundtrip() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\nj\nk\n";
+        let diff = unified_diff("f.txt", "f.txt", old, new);
+        let files = parse_patch(&diff).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(apply_hunks(old, &files[0].hunks).unwrap(), new);
+    }
+
+    #[test]
+    fn test_removed_line_looking_like_a_file_header() {
+        // Removing "-- x" gives the hunk line "--- x", which is still part of the hunk
+        let old = "keep\n-- x\n++ y\nend\n";
+        let new = "keep\nend\n";
+        let patch = "--- a/f.sql\n+++ b/f.sql\n@@ -1,4 +1,2 @@\n keep\n--- x\n-++ y\n end\n--- a/g.txt\n+++ b/g.txt\n@@ -1 +1 @@\n-a\n+b\n";
+        let files = parse_patch(patch).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(apply_hunks(old, &files[0].hunks).unwrap(), new);
+        assert_eq!(files[1].new_path.as_deref(), Some("g.txt"));
+
+        assert!(parse_patch("--- a/f\n+++ b/f\n@@ -1,3 +1,3 @@\n a\n").is_err());
+    }
+
+    #[test]
+    fn test_resolve_stays_inside_workspace() {
+        let root = crate::test_util::TempDir::new("ws");
+        fs::create_dir_all(root.join("src")).unwrap();
+        let mut ws = Workspace::default();
+        ws.add_root(root.to_str().unwrap()).unwrap();
+
+        assert!(ws.resolve("src/new.rs").is_ok());
+        assert!(ws.resolve("../outside.txt").is_err());
+        assert!(ws.resolve("/etc/passwd").is_err());
+    }
+
+    #[test]
+    fn test_resolve_rejects_dangling_symlink_to_outside() {
+        let root = crate::test_util::TempDir::new("ws");
+        let outside = crate::test_util::TempDir::new("outside");
+        std::os::unix::fs::symlink(outside.join("new"), root.join("out")).unwrap();
+        let mut ws = Workspace::default();
+        ws.add_root(root.to_str().unwrap()).unwrap();
+
+        assert!(ws.resolve("out").is_err());
+        assert!(ws.resolve("out/child").is_err());
+        assert!(!outside.join("new").exists());
+    }
+}
@@ -0,0 +1,84 @@
+use std::ffi::{CString, OsString};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::Path;
+use tokio::io::unix::AsyncFd;
+use anyhow::{Result, anyhow};
+
+pub use libc::{IN_CLOSE_WRITE, IN_CREATE, IN_DELETE, IN_DELETE_SELF, IN_IGNORED, IN_MOVE_SELF, IN_MOVED_FROM, IN_MOVED_TO, IN_Q_OVERFLOW};
+
+/// Minimal async wrapper around a Linux inotify instance.
+pub struct Inotify {
+    fd: AsyncFd<OwnedFd>,
+}
+
+#[derive(Debug)]
+pub struct Event {
+    pub wd: i32,
+    pub mask: u32,
+    pub name: Option<OsString>,
+}
+
+impl Inotify {
+    pub fn new() -> Result<Self> {
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(anyhow!("inotify_init1 failed: {}", std::io::Error::last_os_error()));
+        }
+        // SAFETY: `fd` is a freshly created descriptor we exclusively own.
+        let owned = unsafe { OwnedFd::from_raw_fd(fd) };
+        Ok(Self { fd: AsyncFd::new(owned)? })
+    }
+
+    pub fn add_watch(&self, path: &Path, mask: u32) -> Result<i32> {
+        let c_path = CString::new(path.as_os_str().as_bytes())?;
+        let wd = unsafe { libc::inotify_add_watch(self.fd.as_raw_fd(), c_path.as_ptr(), mask) };
+        if wd < 0 {
+            return Err(anyhow!("Failed to watch {}: {}", path.display(), std::io::Error::last_os_error()));
+        }
+        Ok(wd)
+    }
+
+    /// Waits for and returns the next batch of events. Cancel safe.
+    pub async fn read_events(&self) -> Result<Vec<Event>> {
+        let mut buf = [0u8; 4096];
+        loop {
+            let mut guard = self.fd.readable().await?;
+            let res = guard.try_io(|inner| {
+                let n = unsafe { libc::read(inner.as_raw_fd(), buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+                if n < 0 {
+                    Err(std::io::Error::last_os_error())
+                } else {
+                    Ok(n as usize)
+                }
+            });
+            match res {
+                Ok(Ok(n)) => return Ok(parse_events(&buf[..n])),
+                Ok(Err(e)) => return Err(e.into()),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
+
+fn parse_events(buf: &[u8]) -> Vec<Event> {
+    let header = std::mem::size_of::<libc::inotify_event>();
+    let mut events = Vec::new();
+    let mut offset = 0;
+    while offset + header <= buf.len() {
+        // SAFETY: the kernel writes whole `inotify_event` records; the buffer may be unaligned.
+        let raw: libc::inotify_event = unsafe { std::ptr::read_unaligned(buf[offset..].as_ptr() as *const libc::inotify_event) };
+        let name_start = offset + header;
+        let name_end = (name_start + raw.len as usize).min(buf.len());
+        let name = if raw.len > 0 {
+            // The name is NUL padded
+            let bytes = buf[name_start..name_end].split(|b| *b == 0).next().unwrap_or_default();
+            Some(OsString::from_vec(bytes.to_vec()))
+        } else {
+            None
+        };
+        events.push(Event { wd: raw.wd, mask: raw.mask, name });
+        offset = name_end;
+    }
+    events
+}
@@ -15,6 +15,33 @@ pub struct Tool {
     pub exec: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub working_dir: Option<String>,
+    // Set for tools imported from an MCP server; calls are routed there.
+    #[serde(skip)]
+    pub mcp_server: Option<String>,
+    // Set for tools advertised by an attached Server; calls go over its socket.
+    #[serde(skip)]
+    pub server: Option<String>,
+    #[serde(default, skip_serializing_if = "ToolOutput::is_text")]
+    pub output: ToolOutput,
+}
+
+/// How the stdout of an `exec` tool is handed back to the model.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolOutput {
+    // First `tool_output_lines` lines, with pagination for the rest
+    #[default]
+    Text,
+    // Validated JSON, passed intact up to `tool_json_max_bytes`
+    Json,
+    // Raw bytes stored in the tool run directory and referenced by ID
+    Artifact,
+}
+
+impl ToolOutput {
+    pub fn is_text(&self) -> bool {
+        *self == ToolOutput::Text
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
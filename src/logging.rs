@@ -1,19 +1,27 @@
 use tracing_appender::non_blocking::WorkerGuard;
 use tracing_appender::rolling::{RollingFileAppender, Rotation};
-use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
+use tracing_subscriber::{fmt, fmt::writer::BoxMakeWriter, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
 use std::path::PathBuf;
 use home::home_dir;
 use anyhow::Result;
 
-pub fn init_logging(log_level: &str) -> Result<WorkerGuard> {
+/// `console_stderr` sends console logs to stderr, keeping stdout free for
+/// protocols such as MCP over stdio.
+pub fn init_logging(log_level: &str, console_stderr: bool) -> Result<WorkerGuard> {
     let log_dir = get_log_dir()?;
     std::fs::create_dir_all(&log_dir)?;
 
     let file_appender = RollingFileAppender::new(Rotation::DAILY, &log_dir, "ruster.log");
     let (non_blocking, guard) = tracing_appender::non_blocking(file_appender);
 
+    let console_writer = if console_stderr {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    };
+
     let stdout_layer = fmt::layer()
-        .with_writer(std::io::stdout)
+        .with_writer(console_writer)
         .with_filter(EnvFilter::new(log_level));
 
     let file_layer = fmt::layer()
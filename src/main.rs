@@ -5,7 +5,22 @@ mod llm;
 mod skills;
 mod server;
 mod proactive;
+mod mcp;
+mod mcp_server;
 mod servers;
+mod tool_runs;
+mod fs_tools;
+mod bm25;
+mod skill_resources;
+mod skill_discovery;
+mod event_filter;
+mod event_log;
+mod skill_install;
+mod skill_lint;
+mod inotify;
+mod supervisor;
+#[cfg(test)]
+mod test_util;
 
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -16,10 +31,20 @@ use crate::session::SessionManager;
 use crate::skills::SkillsManager;
 use crate::llm::LlmClient;
 use crate::servers::ServerRegistry;
+use crate::mcp::McpManager;
 use anyhow::Result;
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    // `ruster skill lint [dir...]` checks skills and exits without starting the daemon
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() >= 3 && args[1] == "skill" && args[2] == "lint" {
+        return skill_lint::run_cli(&args[3..]);
+    }
+
+    // `--mcp-stdio` serves MCP on stdin/stdout instead of binding the control socket
+    let mcp_stdio = std::env::args().any(|a| a == "--mcp-stdio");
+
     // 1. Load config
     let config = load_config()?;
     let config_arc = Arc::new(RwLock::new(config));
@@ -27,7 +52,7 @@ async fn main() -> Result<()> {
     // 2. Init logging
     let _guard = {
         let cfg = config_arc.read().await;
-        init_logging(&cfg.log_level)?
+        init_logging(&cfg.log_level, mcp_stdio)?
     };
     tracing::info!("Ruster starting up...");
 
@@ -35,7 +60,8 @@ async fn main() -> Result<()> {
     let server_registry = {
         let log_dir = crate::logging::get_log_dir()?;
         let config_dir = log_dir.parent().unwrap().join("config");
-        Arc::new(ServerRegistry::new(&config_dir))
+        let discovery_dir = crate::config::expand_path(&config_arc.read().await.server_discovery_dir);
+        Arc::new(ServerRegistry::new(&config_dir, discovery_dir))
     };
 
     // 4. Init Skills
@@ -43,10 +69,22 @@ async fn main() -> Result<()> {
     let _ = skills_manager.ensure_default_skills();
     {
         let cfg = config_arc.read().await;
-        skills_manager.load_from_dirs(&cfg.skills_dirs)?;
+        skills_manager.load_from_dirs(&cfg.skill_search_path())?;
+    }
+    match SkillsManager::embedding_cache_path() {
+        Ok(path) => {
+            if let Err(e) = skills_manager.load_embedding_cache(path) {
+                tracing::warn!(error = %e, "Failed to load skill embedding cache.");
+            }
+        },
+        Err(e) => tracing::warn!(error = %e, "No location for skill embedding cache."),
     }
     let skills_arc = Arc::new(RwLock::new(skills_manager));
 
+    // 4b. Init MCP Clients (connected in the background)
+    let mcp_manager = Arc::new(McpManager::new());
+    mcp_manager.start(&config_arc.read().await.mcp_servers);
+
     // 5. Init LLM Client
     let llm_client = {
         let cfg = config_arc.read().await;
@@ -59,17 +97,23 @@ async fn main() -> Result<()> {
         skills_arc.clone(),
         llm_client.clone(),
         server_registry.clone(),
+        mcp_manager.clone(),
     ));
 
-    // 7. Start Discovery Loop
-    let sr_clone = server_registry.clone();
-    tokio::spawn(async move {
-        loop {
-            if let Err(e) = sr_clone.scan_and_update().await {
-                tracing::error!("Server discovery error: {}", e);
+    // 6b. Launch configured Servers
+    {
+        let cfg = config_arc.read().await;
+        for server in cfg.servers.iter().filter(|s| s.autostart) {
+            if let Err(e) = session_manager.supervisor.start(server).await {
+                tracing::error!(server = %server.name, error = %e, "Failed to launch server.");
             }
-            tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
         }
+    }
+
+    // 7. Start Discovery Loop
+    let sm_discovery = session_manager.clone();
+    tokio::spawn(async move {
+        servers::start_discovery_loop(sm_discovery).await;
     });
 
     // 8. Start Proactive Loop
@@ -79,21 +123,70 @@ async fn main() -> Result<()> {
         proactive::start_proactive_loop(sm_clone, config_clone).await;
     });
 
-    // 9. Start Server
-    let socket_path = {
+    // 9. Start Tool Run Retention Loop
+    let config_clone2 = config_arc.clone();
+    tokio::spawn(async move {
+        tool_runs::start_retention_loop(config_clone2).await;
+    });
+
+    // 9a. Start Event Counter Save Loop
+    let sm_counters = session_manager.clone();
+    tokio::spawn(async move {
+        session::start_counter_save_loop(sm_counters).await;
+    });
+
+    // 9b. Start Server Event Retention Loop
+    let sm_retention = session_manager.clone();
+    tokio::spawn(async move {
+        session::start_event_retention_loop(sm_retention).await;
+    });
+
+    // 9c. Start Skills Watcher
+    let skills_dirs = config_arc.read().await.skill_search_path();
+    let skills_clone = skills_arc.clone();
+    let skill_events = session_manager.event_sender.clone();
+    tokio::spawn(async move {
+        skills::start_watch_loop(skills_clone, skills_dirs, skill_events).await;
+    });
+
+    // 10. Start Server
+    let (socket_path, mcp_server_socket) = {
         let cfg = config_arc.read().await;
-        cfg.socket_path.clone()
+        (cfg.socket_path.clone(), cfg.mcp_server_socket.clone())
     };
     let sm_clone2 = session_manager.clone();
     
     // Spawn server task so we can handle signals
-    let _server_handle = tokio::spawn(async move {
-        if let Err(e) = server::start_server(&socket_path, sm_clone2).await {
-            tracing::error!("Server error: {}", e);
+    if !mcp_stdio {
+        tokio::spawn(async move {
+            if let Err(e) = server::start_server(&socket_path, sm_clone2).await {
+                tracing::error!("Server error: {}", e);
+            }
+        });
+    }
+
+    // 10b. Start MCP Server socket
+    if let Some(path) = mcp_server_socket.clone() {
+        let sm_clone3 = session_manager.clone();
+        tokio::spawn(async move {
+            if let Err(e) = mcp_server::start_socket_server(&path, sm_clone3).await {
+                tracing::error!("MCP server error: {}", e);
+            }
+        });
+    }
+
+    let sm_clone4 = session_manager.clone();
+    let stdio_done = async move {
+        if mcp_stdio {
+            if let Err(e) = mcp_server::serve_stdio(sm_clone4).await {
+                tracing::error!("MCP stdio error: {}", e);
+            }
+        } else {
+            std::future::pending::<()>().await;
         }
-    });
+    };
 
-    // 10. Wait for signals
+    // 11. Wait for signals
     let mut sigterm = signal::unix::signal(signal::unix::SignalKind::terminate())?;
     
     tokio::select! {
@@ -103,22 +196,28 @@ async fn main() -> Result<()> {
         _ = sigterm.recv() => {
             tracing::info!("Received SIGTERM, shutting down...");
         }
+        _ = stdio_done => {
+            tracing::info!("MCP stdio closed, shutting down...");
+        }
     }
 
     // Graceful shutdown logic
+    tracing::info!("Stopping supervised servers...");
+    session_manager.supervisor.stop_all().await;
+    tracing::info!("Saving event counters...");
+    session_manager.save_event_counters().await;
     tracing::info!("Saving server registry...");
     let _ = server_registry.save().await;
     
     {
         let cfg = config_arc.read().await;
-        let _ = std::fs::remove_file(&cfg.socket_path);
-        
-        // Remove tool_run_dir
-        let tool_run_path = crate::config::expand_path(&cfg.tool_run_dir);
-        if tool_run_path.exists() {
-            tracing::info!("Removing tool run directory: {:?}", tool_run_path);
-            let _ = std::fs::remove_dir_all(&tool_run_path);
+        if !mcp_stdio {
+            let _ = std::fs::remove_file(&cfg.socket_path);
+        }
+        if let Some(path) = &mcp_server_socket {
+            let _ = std::fs::remove_file(path);
         }
+        // Tool runs are kept for auditing and pruned by the retention loop.
     }
     tracing::info!("Shutdown complete.");
 
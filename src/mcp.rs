@@ -0,0 +1,617 @@
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::UnixStream;
+use tokio::process::{Child, Command};
+use tokio::sync::{mpsc, oneshot, watch, Mutex, RwLock};
+use tokio::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use anyhow::{Result, anyhow};
+use crate::llm::{Tool, ToolOutput};
+
+pub const PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// Separator between the server name and the tool name in the names exposed
+/// to the LLM, e.g. `github__create_issue`.
+pub const TOOL_SEPARATOR: &str = "__";
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpServerConfig {
+    pub name: String,
+    // Launch the server as a child process speaking MCP over stdio...
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    // ...or connect to an already running server on a UNIX socket.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub socket: Option<String>,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_timeout_secs() -> u64 {
+    60
+}
+
+type PendingMap = Arc<Mutex<HashMap<u64, oneshot::Sender<Result<Value>>>>>;
+
+/// A JSON-RPC 2.0 connection using newline-delimited messages, as used by
+/// the MCP stdio transport.
+pub struct McpConnection {
+    name: String,
+    writer: mpsc::Sender<String>,
+    pending: PendingMap,
+    next_id: AtomicU64,
+    timeout: Duration,
+    /// Set once the server closes the connection
+    closed: watch::Receiver<bool>,
+    _child: Option<Child>,
+}
+
+impl McpConnection {
+    pub fn from_io<R, W>(name: &str, reader: R, writer: W, timeout: Duration, child: Option<Child>) -> Self
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
+        let pending: PendingMap = Arc::new(Mutex::new(HashMap::new()));
+        let (tx, mut rx) = mpsc::channel::<String>(100);
+
+        let mut writer = writer;
+        tokio::spawn(async move {
+            while let Some(line) = rx.recv().await {
+                if writer.write_all(line.as_bytes()).await.is_err() { break; }
+                if writer.write_all(b"\n").await.is_err() { break; }
+                if writer.flush().await.is_err() { break; }
+            }
+        });
+
+        let reader_pending = pending.clone();
+        let reply_tx = tx.clone();
+        let (closed_tx, closed) = watch::channel(false);
+        let server_name = name.to_string();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(reader).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if line.trim().is_empty() { continue; }
+                let msg: Value = match serde_json::from_str(&line) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        tracing::warn!(server = %server_name, error = %e, "Invalid JSON from MCP server.");
+                        continue;
+                    }
+                };
+                handle_incoming(&server_name, msg, &reader_pending, &reply_tx).await;
+            }
+            tracing::info!(server = %server_name, "MCP connection closed.");
+            // Fail any requests still waiting for an answer
+            for (_, sender) in reader_pending.lock().await.drain() {
+                let _ = sender.send(Err(anyhow!("MCP server {} disconnected", server_name)));
+            }
+            let _ = closed_tx.send(true);
+        });
+
+        Self {
+            name: name.to_string(),
+            writer: tx,
+            pending,
+            next_id: AtomicU64::new(1),
+            timeout,
+            closed,
+            _child: child,
+        }
+    }
+
+    /// Waits until the server closes the connection.
+    pub async fn closed(&self) {
+        let mut closed = self.closed.clone();
+        let _ = closed.wait_for(|c| *c).await;
+    }
+
+    pub async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().await.insert(id, tx);
+
+        let msg = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+        if self.writer.send(msg.to_string()).await.is_err() {
+            self.pending.lock().await.remove(&id);
+            return Err(anyhow!("MCP server {} is not connected", self.name));
+        }
+
+        match tokio::time::timeout(self.timeout, rx).await {
+            Ok(Ok(res)) => res,
+            Ok(Err(_)) => Err(anyhow!("MCP server {} dropped request {}", self.name, method)),
+            Err(_) => {
+                self.pending.lock().await.remove(&id);
+                Err(anyhow!("MCP request {} to {} timed out", method, self.name))
+            }
+        }
+    }
+
+    pub async fn notify(&self, method: &str, params: Value) -> Result<()> {
+        let msg = json!({"jsonrpc": "2.0", "method": method, "params": params});
+        self.writer.send(msg.to_string()).await
+            .map_err(|_| anyhow!("MCP server {} is not connected", self.name))
+    }
+}
+
+async fn handle_incoming(server_name: &str, msg: Value, pending: &PendingMap, reply_tx: &mpsc::Sender<String>) {
+    let method = msg["method"].as_str();
+    match (msg.get("id"), method) {
+        // Response to one of our requests
+        (Some(id), None) => {
+            let Some(id) = id.as_u64() else { return };
+            if let Some(sender) = pending.lock().await.remove(&id) {
+                let res = if let Some(err) = msg.get("error") {
+                    Err(anyhow!("MCP error from {}: {}", server_name, err["message"].as_str().unwrap_or(&err.to_string())))
+                } else {
+                    Ok(msg["result"].clone())
+                };
+                let _ = sender.send(res);
+            }
+        },
+        // Request from the server. We only answer pings; we advertise no client capabilities.
+        (Some(id), Some(method)) => {
+            let reply = if method == "ping" {
+                json!({"jsonrpc": "2.0", "id": id, "result": {}})
+            } else {
+                json!({"jsonrpc": "2.0", "id": id, "error": {"code": -32601, "message": format!("Method not found: {}", method)}})
+            };
+            let _ = reply_tx.send(reply.to_string()).await;
+        },
+        (None, Some(method)) => {
+            tracing::debug!(server = %server_name, method = %method, "MCP notification received.");
+        },
+        (None, None) => {
+            tracing::warn!(server = %server_name, "Ignoring malformed MCP message.");
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpTool {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(rename = "inputSchema", default)]
+    pub input_schema: Value,
+}
+
+pub struct McpServer {
+    pub name: String,
+    pub conn: McpConnection,
+    pub server_info: Value,
+    pub tools: Vec<McpTool>,
+    pub resources: Vec<Value>,
+    pub prompts: Vec<Value>,
+}
+
+impl McpServer {
+    pub async fn connect(config: &McpServerConfig) -> Result<Self> {
+        let timeout = Duration::from_secs(config.timeout_secs);
+        let conn = if let Some(command) = &config.command {
+            let mut child = Command::new(command)
+                .args(&config.args)
+                .envs(&config.env)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn()
+                .map_err(|e| anyhow!("Failed to launch MCP server {}: {}", config.name, e))?;
+
+            let stdin = child.stdin.take().ok_or_else(|| anyhow!("Missing stdin"))?;
+            let stdout = child.stdout.take().ok_or_else(|| anyhow!("Missing stdout"))?;
+            if let Some(stderr) = child.stderr.take() {
+                let name = config.name.clone();
+                tokio::spawn(async move {
+                    let mut lines = BufReader::new(stderr).lines();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        tracing::debug!(server = %name, "MCP stderr: {}", line);
+                    }
+                });
+            }
+            McpConnection::from_io(&config.name, stdout, stdin, timeout, Some(child))
+        } else if let Some(socket) = &config.socket {
+            let path = crate::config::expand_path(socket);
+            let stream = UnixStream::connect(&path).await
+                .map_err(|e| anyhow!("Failed to connect to MCP server {} at {:?}: {}", config.name, path, e))?;
+            let (reader, writer) = stream.into_split();
+            McpConnection::from_io(&config.name, reader, writer, timeout, None)
+        } else {
+            return Err(anyhow!("MCP server {} needs either 'command' or 'socket'", config.name));
+        };
+
+        Self::initialize(&config.name, conn).await
+    }
+
+    /// Performs the initialize handshake and fetches the tool, resource and
+    /// prompt listings the server supports.
+    pub async fn initialize(name: &str, conn: McpConnection) -> Result<Self> {
+        let init = conn.request("initialize", json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": {},
+            "clientInfo": {"name": "ruster", "version": env!("CARGO_PKG_VERSION")}
+        })).await?;
+        conn.notify("notifications/initialized", json!({})).await?;
+
+        let capabilities = &init["capabilities"];
+        let mut server = Self {
+            name: name.to_string(),
+            conn,
+            server_info: init["serverInfo"].clone(),
+            tools: Vec::new(),
+            resources: Vec::new(),
+            prompts: Vec::new(),
+        };
+
+        if capabilities.get("tools").is_some() {
+            for tool in server.list_all("tools/list", "tools").await? {
+                match serde_json::from_value::<McpTool>(tool) {
+                    Ok(t) => server.tools.push(t),
+                    Err(e) => tracing::warn!(server = %name, error = %e, "Skipping invalid MCP tool."),
+                }
+            }
+        }
+        if capabilities.get("resources").is_some() {
+            server.resources = server.list_all("resources/list", "resources").await?;
+        }
+        if capabilities.get("prompts").is_some() {
+            server.prompts = server.list_all("prompts/list", "prompts").await?;
+        }
+
+        tracing::info!(server = %name, tools = %server.tools.len(), resources = %server.resources.len(), prompts = %server.prompts.len(), "MCP server initialized.");
+        Ok(server)
+    }
+
+    async fn list_all(&self, method: &str, key: &str) -> Result<Vec<Value>> {
+        let mut items = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = match &cursor {
+                Some(c) => json!({"cursor": c}),
+                None => json!({}),
+            };
+            let res = self.conn.request(method, params).await?;
+            if let Some(arr) = res[key].as_array() {
+                items.extend(arr.iter().cloned());
+            }
+            match res["nextCursor"].as_str() {
+                Some(c) if !c.is_empty() => cursor = Some(c.to_string()),
+                _ => break,
+            }
+        }
+        Ok(items)
+    }
+
+    pub async fn call_tool(&self, tool: &str, arguments: Value) -> Result<String> {
+        let res = self.conn.request("tools/call", json!({"name": tool, "arguments": arguments})).await?;
+        let text = content_to_text(&res["content"]);
+        if res["isError"].as_bool().unwrap_or(false) {
+            Ok(format!("Error: {}", text))
+        } else {
+            Ok(text)
+        }
+    }
+
+    pub async fn read_resource(&self, uri: &str) -> Result<String> {
+        let res = self.conn.request("resources/read", json!({"uri": uri})).await?;
+        let mut parts = Vec::new();
+        for item in res["contents"].as_array().into_iter().flatten() {
+            if let Some(text) = item["text"].as_str() {
+                parts.push(text.to_string());
+            } else {
+                parts.push(format!("[binary resource {} ({})]", item["uri"].as_str().unwrap_or(uri), item["mimeType"].as_str().unwrap_or("unknown")));
+            }
+        }
+        Ok(parts.join("\n"))
+    }
+
+    pub async fn get_prompt(&self, name: &str, arguments: Value) -> Result<String> {
+        let res = self.conn.request("prompts/get", json!({"name": name, "arguments": arguments})).await?;
+        let mut parts = Vec::new();
+        for msg in res["messages"].as_array().into_iter().flatten() {
+            parts.push(format!("{}: {}", msg["role"].as_str().unwrap_or("user"), content_to_text(&json!([msg["content"]]))));
+        }
+        Ok(parts.join("\n"))
+    }
+}
+
+/// Flattens MCP content blocks into text for the LLM context.
+fn content_to_text(content: &Value) -> String {
+    let mut parts = Vec::new();
+    for block in content.as_array().into_iter().flatten() {
+        match block["type"].as_str() {
+            Some("text") => parts.push(block["text"].as_str().unwrap_or_default().to_string()),
+            Some("resource") => {
+                let res = &block["resource"];
+                match res["text"].as_str() {
+                    Some(t) => parts.push(t.to_string()),
+                    None => parts.push(format!("[resource {}]", res["uri"].as_str().unwrap_or_default())),
+                }
+            },
+            Some("resource_link") => parts.push(format!("[resource {}]", block["uri"].as_str().unwrap_or_default())),
+            Some(other) => parts.push(format!("[{} content ({})]", other, block["mimeType"].as_str().unwrap_or("unknown"))),
+            None => {}
+        }
+    }
+    parts.join("\n")
+}
+
+pub struct McpManager {
+    pub servers: RwLock<HashMap<String, Arc<McpServer>>>,
+}
+
+impl McpManager {
+    pub fn new() -> Self {
+        Self {
+            servers: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Connects to the servers in the background, so a slow or dead one
+    /// does not hold up startup. Each server's tools are offered once it is
+    /// initialized.
+    pub fn start(self: &Arc<Self>, configs: &[McpServerConfig]) {
+        for config in configs.iter().cloned() {
+            tokio::spawn(self.clone().maintain(config));
+        }
+    }
+
+    /// Keeps one server connected, reconnecting with backoff when connecting
+    /// fails or the server goes away.
+    async fn maintain(self: Arc<Self>, config: McpServerConfig) {
+        let mut delay = RECONNECT_DELAY;
+        let mut failing = false;
+        loop {
+            match McpServer::connect(&config).await {
+                Ok(server) => {
+                    let server = Arc::new(server);
+                    let connected_at = Instant::now();
+                    failing = false;
+                    self.servers.write().await.insert(config.name.clone(), server.clone());
+                    server.conn.closed().await;
+                    self.servers.write().await.remove(&config.name);
+                    // A server that keeps exiting right away still backs off
+                    if connected_at.elapsed() > MAX_RECONNECT_DELAY {
+                        delay = RECONNECT_DELAY;
+                    }
+                    tracing::warn!(server = %config.name, retry_in = ?delay, "MCP server disconnected.");
+                },
+                Err(e) if !failing => {
+                    tracing::error!(server = %config.name, error = %e, retry_in = ?delay, "Failed to start MCP server.");
+                    failing = true;
+                },
+                Err(e) => tracing::debug!(server = %config.name, error = %e, retry_in = ?delay, "MCP server reconnect failed."),
+            }
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+    }
+
+    /// Tools of all connected servers, named `<server>__<tool>`.
+    pub async fn tools(&self) -> Vec<Tool> {
+        let servers = self.servers.read().await;
+        let mut tools = Vec::new();
+        for server in servers.values() {
+            for t in &server.tools {
+                tools.push(Tool {
+                    name: format!("{}{}{}", server.name, TOOL_SEPARATOR, t.name),
+                    description: t.description.clone(),
+                    parameters: if t.input_schema.is_null() { json!({"type": "object", "properties": {}}) } else { t.input_schema.clone() },
+                    exec: None,
+                    working_dir: None,
+                    mcp_server: Some(server.name.clone()),
+                    server: None,
+                    output: ToolOutput::Text,
+                });
+            }
+        }
+        tools
+    }
+
+    pub async fn get(&self, name: &str) -> Result<Arc<McpServer>> {
+        self.servers.read().await.get(name).cloned()
+            .ok_or_else(|| anyhow!("MCP server {} not connected", name))
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.servers.read().await.is_empty()
+    }
+
+    pub async fn call_tool(&self, server_name: &str, exposed_name: &str, arguments: Value) -> Result<String> {
+        let server = self.get(server_name).await?;
+        let tool = exposed_name
+            .strip_prefix(&format!("{}{}", server_name, TOOL_SEPARATOR))
+            .unwrap_or(exposed_name);
+        server.call_tool(tool, arguments).await
+    }
+}
+
+/// Built-in tools that surface MCP resources and prompts to the LLM.
+pub fn builtin_tools() -> Vec<Tool> {
+    vec![
+        Tool {
+            name: "mcp_list_resources".to_string(),
+            description: "Lists the resources and prompts offered by connected MCP servers.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "server": {
+                        "type": "string",
+                        "description": "Optional MCP server name to restrict the listing to."
+                    }
+                }
+            }),
+            exec: None,
+            working_dir: None,
+            mcp_server: None,
+            server: None,
+            output: ToolOutput::Text,
+        },
+        Tool {
+            name: "mcp_read_resource".to_string(),
+            description: "Reads a resource from an MCP server by URI.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "server": { "type": "string", "description": "The MCP server name." },
+                    "uri": { "type": "string", "description": "The resource URI." }
+                },
+                "required": ["server", "uri"]
+            }),
+            exec: None,
+            working_dir: None,
+            mcp_server: None,
+            server: None,
+            output: ToolOutput::Text,
+        },
+        Tool {
+            name: "mcp_get_prompt".to_string(),
+            description: "Fetches a prompt template from an MCP server.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "server": { "type": "string", "description": "The MCP server name." },
+                    "name": { "type": "string", "description": "The prompt name." },
+                    "arguments": { "type": "object", "description": "Prompt arguments." }
+                },
+                "required": ["server", "name"]
+            }),
+            exec: None,
+            working_dir: None,
+            mcp_server: None,
+            server: None,
+            output: ToolOutput::Text,
+        },
+    ]
+}
+
+/// Executes one of the built-in MCP tools, returning `None` if `name` is not one.
+pub async fn execute_builtin(mcp: &McpManager, name: &str, args: &Value) -> Option<Result<String>> {
+    let res = match name {
+        "mcp_list_resources" => {
+            let filter = args["server"].as_str();
+            let servers = mcp.servers.read().await;
+            let listing: Vec<_> = servers.values()
+                .filter(|s| filter.is_none_or(|f| f == s.name))
+                .map(|s| json!({"server": s.name, "resources": s.resources, "prompts": s.prompts}))
+                .collect();
+            serde_json::to_string_pretty(&listing).map_err(Into::into)
+        },
+        "mcp_read_resource" => {
+            let server = args["server"].as_str().unwrap_or_default();
+            let uri = args["uri"].as_str().unwrap_or_default();
+            match mcp.get(server).await {
+                Ok(s) => s.read_resource(uri).await,
+                Err(e) => Err(e),
+            }
+        },
+        "mcp_get_prompt" => {
+            let server = args["server"].as_str().unwrap_or_default();
+            let prompt = args["name"].as_str().unwrap_or_default();
+            match mcp.get(server).await {
+                Ok(s) => s.get_prompt(prompt, args.get("arguments").cloned().unwrap_or(json!({}))).await,
+                Err(e) => Err(e),
+            }
+        },
+        _ => return None,
+    };
+    Some(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minimal MCP server answering the handshake, tools/list and tools/call.
+    async fn fake_server<S: AsyncRead + AsyncWrite>(stream: S) {
+        let (reader, mut writer) = tokio::io::split(stream);
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let req: Value = serde_json::from_str(&line).unwrap();
+            let Some(id) = req.get("id").cloned() else { continue };
+            let result = match req["method"].as_str().unwrap() {
+                "initialize" => json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": {"tools": {}},
+                    "serverInfo": {"name": "fake", "version": "1.0"}
+                }),
+                "tools/list" => json!({"tools": [{
+                    "name": "echo",
+                    "description": "Echoes its input",
+                    "inputSchema": {"type": "object", "properties": {"text": {"type": "string"}}}
+                }]}),
+                "tools/call" => json!({"content": [{"type": "text", "text": req["params"]["arguments"]["text"]}]}),
+                _ => json!({}),
+            };
+            let reply = json!({"jsonrpc": "2.0", "id": id, "result": result});
+            writer.write_all(format!("{}\n", reply).as_bytes()).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handshake_and_tool_call() {
+        let (client, server) = tokio::io::duplex(4096);
+        tokio::spawn(fake_server(server));
+
+        let (reader, writer) = tokio::io::split(client);
+        let conn = McpConnection::from_io("fake", reader, writer, Duration::from_secs(5), None);
+        let server = McpServer::initialize("fake", conn).await.unwrap();
+        assert_eq!(server.tools.len(), 1);
+        assert_eq!(server.tools[0].name, "echo");
+
+        let out = server.call_tool("echo", json!({"text": "hello"})).await.unwrap();
+        assert_eq!(out, "hello");
+    }
+
+    #[tokio::test]
+    async fn test_connects_in_background_and_reconnects() {
+        let dir = crate::test_util::TempDir::new("mcp");
+        let path = dir.join("fake.sock");
+        let listener = tokio::net::UnixListener::bind(&path).unwrap();
+        let config = McpServerConfig {
+            name: "fake".to_string(),
+            command: None,
+            args: Vec::new(),
+            env: HashMap::new(),
+            socket: Some(path.to_string_lossy().to_string()),
+            timeout_secs: 5,
+        };
+        let mcp = Arc::new(McpManager::new());
+        mcp.start(&[config]);
+        assert!(mcp.is_empty().await);
+
+        let wait_until = |connected: bool| {
+            let mcp = mcp.clone();
+            tokio::time::timeout(Duration::from_secs(5), async move {
+                while mcp.get("fake").await.is_ok() != connected {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            })
+        };
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let first = tokio::spawn(fake_server(stream));
+        wait_until(true).await.unwrap();
+        assert_eq!(mcp.tools().await[0].name, "fake__echo");
+
+        // The server going away removes its tools until it is back
+        first.abort();
+        wait_until(false).await.unwrap();
+        let (stream, _) = tokio::time::timeout(Duration::from_secs(5), listener.accept()).await.unwrap().unwrap();
+        tokio::spawn(fake_server(stream));
+        wait_until(true).await.unwrap();
+        assert_eq!(mcp.call_tool("fake", "fake__echo", json!({"text": "back"})).await.unwrap(), "back");
+    }
+}
@@ -0,0 +1,351 @@
+use std::path::Path;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::UnixListener;
+use tokio::sync::mpsc;
+use serde_json::{json, Value};
+use anyhow::{Result, anyhow};
+use crate::session::SessionManager;
+
+/// Serves the MCP protocol on stdin/stdout until stdin is closed.
+pub async fn serve_stdio(sm: Arc<SessionManager>) -> Result<()> {
+    tracing::info!("Serving MCP on stdio");
+    serve(tokio::io::stdin(), tokio::io::stdout(), sm).await
+}
+
+pub async fn start_socket_server(socket_path: &str, sm: Arc<SessionManager>) -> Result<()> {
+    if Path::new(socket_path).exists() {
+        std::fs::remove_file(socket_path)
+            .map_err(|e| anyhow!("Failed to remove existing MCP socket at {}: {}", socket_path, e))?;
+    }
+    let listener = UnixListener::bind(socket_path)
+        .map_err(|e| anyhow!("Failed to bind to MCP socket {}: {}", socket_path, e))?;
+    tracing::info!("MCP server listening on {}", socket_path);
+
+    loop {
+        match listener.accept().await {
+            Ok((stream, _addr)) => {
+                let sm = sm.clone();
+                tokio::spawn(async move {
+                    let (reader, writer) = stream.into_split();
+                    if let Err(e) = serve(reader, writer, sm).await {
+                        tracing::error!("MCP connection error: {}", e);
+                    }
+                });
+            }
+            Err(e) => {
+                tracing::error!("MCP accept error: {}", e);
+            }
+        }
+    }
+}
+
+pub async fn serve<R, W>(reader: R, writer: W, sm: Arc<SessionManager>) -> Result<()>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let (tx, mut rx) = mpsc::channel::<Value>(100);
+
+    let mut writer = writer;
+    let writer_task = tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            if writer.write_all(msg.to_string().as_bytes()).await.is_err() { break; }
+            if writer.write_all(b"\n").await.is_err() { break; }
+            if writer.flush().await.is_err() { break; }
+        }
+    });
+
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() { continue; }
+        let msg: Value = match serde_json::from_str(&line) {
+            Ok(v) => v,
+            Err(e) => {
+                let _ = tx.send(error_response(Value::Null, -32700, &format!("Parse error: {}", e))).await;
+                continue;
+            }
+        };
+
+        let Some(id) = msg.get("id").cloned() else {
+            // Notifications (e.g. notifications/initialized) need no answer
+            tracing::debug!(method = %msg["method"], "MCP notification received");
+            continue;
+        };
+        let method = msg["method"].as_str().unwrap_or_default().to_string();
+        let params = msg["params"].clone();
+        let sm = sm.clone();
+        let tx = tx.clone();
+
+        // Requests are handled concurrently so a long turn doesn't block pings or other calls
+        tokio::spawn(async move {
+            let reply = match handle_request(&method, params, sm, tx.clone()).await {
+                Ok(Some(result)) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+                Ok(None) => error_response(id, -32601, &format!("Method not found: {}", method)),
+                Err(e) => error_response(id, -32603, &e.to_string()),
+            };
+            let _ = tx.send(reply).await;
+        });
+    }
+
+    drop(tx);
+    let _ = writer_task.await;
+    Ok(())
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+}
+
+/// Handles one request, returning `None` for an unknown method.
+async fn handle_request(method: &str, params: Value, sm: Arc<SessionManager>, tx: mpsc::Sender<Value>) -> Result<Option<Value>> {
+    let result = match method {
+        "initialize" => json!({
+            "protocolVersion": crate::mcp::PROTOCOL_VERSION,
+            "capabilities": {"tools": {}},
+            "serverInfo": {"name": "ruster", "version": env!("CARGO_PKG_VERSION")}
+        }),
+        "ping" => json!({}),
+        "tools/list" => json!({"tools": tool_definitions()}),
+        "tools/call" => {
+            let name = params["name"].as_str().ok_or_else(|| anyhow!("Missing tool name"))?;
+            let args = params.get("arguments").cloned().unwrap_or(json!({}));
+            let progress_token = params["_meta"].get("progressToken").cloned();
+            match call_tool(name, args, progress_token, sm, tx).await {
+                Ok(text) => json!({"content": [{"type": "text", "text": text}], "isError": false}),
+                Err(e) => json!({"content": [{"type": "text", "text": e.to_string()}], "isError": true}),
+            }
+        },
+        _ => return Ok(None),
+    };
+    Ok(Some(result))
+}
+
+fn tool_definitions() -> Value {
+    json!([
+        {
+            "name": "ruster_send",
+            "description": "Sends a message to a Ruster session and returns the assistant's reply. Progress notifications stream the reply while it is generated.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "session_id": { "type": "string", "description": "The session to talk to. Created if it does not exist." },
+                    "message": { "type": "string", "description": "The user message." }
+                },
+                "required": ["session_id", "message"]
+            }
+        },
+        {
+            "name": "ruster_history",
+            "description": "Returns paginated message history of a Ruster session.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "session_id": { "type": "string" },
+                    "limit": { "type": "integer", "description": "Number of messages to return (default: 20)." },
+                    "offset": { "type": "integer", "description": "Number of messages to skip (default: 0)." }
+                },
+                "required": ["session_id"]
+            }
+        },
+        {
+            "name": "ruster_search_skills",
+            "description": "Searches the available Ruster skills using RAG.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string" }
+                },
+                "required": ["query"]
+            }
+        },
+        {
+            "name": "ruster_list_sessions",
+            "description": "Lists the loaded and stored Ruster sessions.",
+            "inputSchema": { "type": "object", "properties": {} }
+        }
+    ])
+}
+
+async fn call_tool(name: &str, args: Value, progress_token: Option<Value>, sm: Arc<SessionManager>, tx: mpsc::Sender<Value>) -> Result<String> {
+    match name {
+        "ruster_send" => {
+            let session_id = args["session_id"].as_str().ok_or_else(|| anyhow!("Missing session_id"))?.to_string();
+            let mut req = args.clone();
+            req["action"] = json!("send");
+
+            let mut events = run_session_action("send", req, sm);
+            let mut progress = 0u64;
+            let mut error = None;
+            let mut reply = ReplyCollector::default();
+            while let Some(event) = events.recv().await {
+                if let Some(e) = event.get("error") {
+                    error = Some(e.as_str().unwrap_or_default().to_string());
+                    continue;
+                }
+                reply.push(&event);
+                let Some(token) = &progress_token else { continue };
+                let message = match event["event"].as_str() {
+                    Some("response") => event["delta"].as_str().unwrap_or_default().to_string(),
+                    Some("status") => event["message"].as_str().unwrap_or_default().to_string(),
+                    Some("tool_call") => format!("[tool call: {}]", event["tool"].as_str().unwrap_or_default()),
+                    _ => continue,
+                };
+                if message.is_empty() { continue; }
+                progress += 1;
+                let _ = tx.send(json!({
+                    "jsonrpc": "2.0",
+                    "method": "notifications/progress",
+                    "params": {"progressToken": token, "progress": progress, "message": message}
+                })).await;
+            }
+            if let Some(e) = error {
+                return Err(anyhow!(e));
+            }
+            reply.finish().ok_or_else(|| anyhow!("No reply generated for session {}", session_id))
+        },
+        "ruster_history" => {
+            let mut req = args.clone();
+            req["action"] = json!("history");
+            let mut events = run_session_action("history", req, sm);
+            let mut result = None;
+            while let Some(event) = events.recv().await {
+                if let Some(e) = event.get("error") {
+                    return Err(anyhow!(e.as_str().unwrap_or_default().to_string()));
+                }
+                result = Some(event);
+            }
+            let result = result.ok_or_else(|| anyhow!("No history returned"))?;
+            Ok(serde_json::to_string_pretty(&result)?)
+        },
+        "ruster_search_skills" => {
+            let query = args["query"].as_str().ok_or_else(|| anyhow!("Missing query"))?;
+            let retrieval = crate::skills::RetrievalOptions::from_config(&*sm.config.read().await);
+            let mut mgr = sm.skills_manager.write().await;
+            let results = mgr.search_skills(query, &sm.llm_client, &retrieval).await?;
+            let metadata: Vec<_> = results.iter().map(|s| &s.metadata).collect();
+            Ok(serde_json::to_string_pretty(&metadata)?)
+        },
+        "ruster_list_sessions" => {
+            let sessions = sm.list_sessions().await?;
+            Ok(serde_json::to_string_pretty(&sessions)?)
+        },
+        _ => Err(anyhow!("Unknown tool: {}", name)),
+    }
+}
+
+/// Rebuilds the assistant's reply from the `response` chunks of one send.
+/// Reading it back from history instead could pick up the reply of a
+/// server-initiated turn that finished in the meantime.
+#[derive(Default)]
+struct ReplyCollector {
+    parts: Vec<String>,
+    current: String,
+}
+
+impl ReplyCollector {
+    fn push(&mut self, event: &Value) {
+        match event["event"].as_str() {
+            Some("response") => self.current.push_str(event["delta"].as_str().unwrap_or_default()),
+            // Each LLM round opens with a status event
+            Some("status") => self.end_round(),
+            _ => {},
+        }
+    }
+
+    fn end_round(&mut self) {
+        if !self.current.is_empty() {
+            self.parts.push(std::mem::take(&mut self.current));
+        }
+    }
+
+    /// The text of every round, as stored in history without the tool results.
+    fn finish(mut self) -> Option<String> {
+        self.end_round();
+        (!self.parts.is_empty()).then(|| self.parts.join("\n"))
+    }
+}
+
+/// Runs a session action on its own task and returns the channel of events
+/// it emits, exactly as a socket client would receive them.
+fn run_session_action(action: &'static str, req: Value, sm: Arc<SessionManager>) -> mpsc::Receiver<Value> {
+    let (tx, rx) = mpsc::channel::<Value>(100);
+    tokio::spawn(async move {
+        if let Err(e) = crate::server::handle_session_action(action, req, sm, tx.clone()).await {
+            let _ = tx.send(json!({"error": e.to_string()})).await;
+        }
+    });
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tokio::sync::RwLock;
+
+    #[test]
+    fn test_reply_is_collected_from_chunks() {
+        let mut reply = ReplyCollector::default();
+        for event in [
+            json!({"event": "status", "message": "Thinking..."}),
+            json!({"event": "response", "delta": "Let me ", "done": false}),
+            json!({"event": "response", "delta": "check.", "done": false}),
+            json!({"event": "tool_call", "tool": "clock"}),
+            json!({"event": "tool_call", "tool": "weather"}),
+            json!({"event": "status", "message": "Refining..."}),
+            json!({"event": "response", "delta": "It is noon.", "done": false}),
+            json!({"event": "response", "delta": "", "done": true}),
+        ] {
+            reply.push(&event);
+        }
+        assert_eq!(reply.finish().as_deref(), Some("Let me check.\nIt is noon."));
+        assert_eq!(ReplyCollector::default().finish(), None);
+    }
+
+    #[tokio::test]
+    async fn test_initialize_list_and_call_tools() {
+        let dir = crate::test_util::TempDir::new("mcp");
+        let sm = Arc::new(SessionManager::new(
+            Arc::new(RwLock::new(crate::config::Config::default())),
+            Arc::new(RwLock::new(crate::skills::SkillsManager::new())),
+            crate::llm::LlmClient::new("http://127.0.0.1:9".to_string()),
+            Arc::new(crate::servers::ServerRegistry::new(&dir, dir.to_path_buf())),
+            Arc::new(crate::mcp::McpManager::new()),
+        ));
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let (reader, writer) = tokio::io::split(server);
+        tokio::spawn(serve(reader, writer, sm));
+
+        let (reader, mut writer) = tokio::io::split(client);
+        for msg in [
+            json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}),
+            json!({"jsonrpc": "2.0", "method": "notifications/initialized"}),
+            json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list"}),
+            json!({"jsonrpc": "2.0", "id": 3, "method": "tools/call", "params": {"name": "ruster_nope", "arguments": {}}}),
+            json!({"jsonrpc": "2.0", "id": 4, "method": "tools/call", "params": {"name": "ruster_send", "arguments": {}}}),
+            json!({"jsonrpc": "2.0", "id": 5, "method": "resources/list"}),
+        ] {
+            writer.write_all(format!("{}\n", msg).as_bytes()).await.unwrap();
+        }
+
+        // Requests run concurrently, so answers are matched by id
+        let mut lines = BufReader::new(reader).lines();
+        let mut replies = HashMap::new();
+        while replies.len() < 5 {
+            let line = lines.next_line().await.unwrap().unwrap();
+            let reply: Value = serde_json::from_str(&line).unwrap();
+            replies.insert(reply["id"].as_i64().unwrap(), reply);
+        }
+
+        assert_eq!(replies[&1]["result"]["protocolVersion"], crate::mcp::PROTOCOL_VERSION);
+        assert_eq!(replies[&1]["result"]["serverInfo"]["name"], "ruster");
+        let names: Vec<_> = replies[&2]["result"]["tools"].as_array().unwrap().iter().map(|t| t["name"].as_str().unwrap()).collect();
+        assert_eq!(names, ["ruster_send", "ruster_history", "ruster_search_skills", "ruster_list_sessions"]);
+        assert_eq!(replies[&3]["result"]["isError"], true);
+        assert!(replies[&3]["result"]["content"][0]["text"].as_str().unwrap().contains("Unknown tool"));
+        assert_eq!(replies[&4]["result"]["isError"], true);
+        assert!(replies[&4]["result"]["content"][0]["text"].as_str().unwrap().contains("session_id"));
+        assert_eq!(replies[&5]["error"]["code"], -32601);
+    }
+}
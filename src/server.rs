@@ -20,60 +20,87 @@ struct CommandRequest {
     arguments: Value,
 }
 
+/// Everything a tool call may need from the turn that issued it.
+struct ToolContext<'a> {
+    tools: &'a [crate::llm::Tool],
+    skills: &'a [crate::skills::Skill],
+    skills_manager: &'a tokio::sync::RwLock<crate::skills::SkillsManager>,
+    config: &'a crate::config::Config,
+    mcp: &'a crate::mcp::McpManager,
+    servers: &'a crate::servers::ServerRegistry,
+    workspace: &'a crate::fs_tools::Workspace,
+    session_id: &'a str,
+}
+
+/// Runs an offered built-in tool, returning `None` if `name` is not one.
+async fn execute_builtin(name: &str, args: &Value, ctx: &ToolContext<'_>, run_dir: &Path) -> Option<Result<String>> {
+    let active: Vec<String> = ctx.skills.iter().map(|s| s.metadata.name.clone()).collect();
+    if let Some(res) = crate::skill_discovery::execute(name, args, &*ctx.skills_manager.read().await, &ctx.config.banned_skills, &active) {
+        return Some(res);
+    }
+    if let Some(res) = crate::skill_resources::execute(name, args, ctx.skills, ctx.config.fs_max_read_bytes) {
+        return Some(res);
+    }
+    if let Some(res) = crate::fs_tools::execute(name, args, ctx.workspace, ctx.config, run_dir) {
+        return Some(res);
+    }
+    crate::mcp::execute_builtin(ctx.mcp, name, args).await
+}
+
 async fn execute_tool(
     call: crate::llm::ToolCall,
-    tools: &[crate::llm::Tool],
-    skills: &[crate::skills::Skill],
-    config: &crate::config::Config,
+    ctx: &ToolContext<'_>,
     user_msg: &str,
     assistant_resp: &str,
 ) -> Result<(String, String)> {
+    let ToolContext { tools, skills, config, mcp, servers, session_id, .. } = *ctx;
     let tool_uuid = Uuid::new_v4().to_string();
-    let expanded_tool_run_dir = crate::config::expand_path(&config.tool_run_dir);
-    let tool_run_dir = expanded_tool_run_dir.join("tools").join(&tool_uuid);
+    let runs_dir = crate::tool_runs::runs_dir(config);
+    let tool_run_dir = runs_dir.join(&tool_uuid);
     fs::create_dir_all(&tool_run_dir)?;
 
+    let started_at = chrono::Local::now();
+    let started = std::time::Instant::now();
+
     let call_log_path = tool_run_dir.join("call");
     let mut call_log = fs::File::create(call_log_path)?;
-    writeln!(call_log, "Timestamp: {}", chrono::Local::now())?;
+    writeln!(call_log, "Timestamp: {}", started_at)?;
     writeln!(call_log, "Tool Call UUID: {}", tool_uuid)?;
+    writeln!(call_log, "Session ID: {}", session_id)?;
     writeln!(call_log, "Tool Name: {}", call.name)?;
     writeln!(call_log, "Arguments: {}", call.arguments)?;
     writeln!(call_log, "User Message: {}", user_msg)?;
     writeln!(call_log, "Assistant Response: {}", assistant_resp)?;
 
-    let stdout_all;
+    let mut stdout_all;
     let mut stderr_all = String::new();
+    let mut exit_code = None;
+    // Raw stdout of `exec` tools declaring JSON or artifact output
+    let mut raw_output: Option<(crate::llm::ToolOutput, Vec<u8>)> = None;
 
-    if call.name == "paginate_tool_output" {
+    let policy = crate::skills::ToolPolicy::for_skills(skills);
+    if !policy.allows_call(&call.name, &call.arguments) {
+        tracing::warn!(session_id = %session_id, tool = %call.name, "Tool call rejected by allowed-tools.");
+        stdout_all = format!("Error: Tool '{}' is not permitted by the allowed-tools of the active skills.", call.name);
+    } else if call.name == "paginate_tool_output" {
         let args: Value = serde_json::from_str(&call.arguments)?;
         let target_uuid = args["tool_call_uuid"].as_str().ok_or_else(|| anyhow!("Missing tool_call_uuid"))?;
         let offset = args["offset"].as_u64().unwrap_or(0) as usize;
         let limit = args["limit"].as_u64().unwrap_or(config.tool_output_lines as u64) as usize;
         let search = args["search"].as_str();
 
-        let target_stdout_path = expanded_tool_run_dir.join("tools").join(target_uuid).join("stdout");
-        if !target_stdout_path.exists() {
-            return Ok((tool_uuid, format!("Error: Tool run {} not found.", target_uuid)));
-        }
-
-        let content = fs::read_to_string(target_stdout_path)?;
-        let mut lines: Vec<_> = content.lines().collect();
-        
-        if let Some(term) = search {
-            lines.retain(|l| l.contains(term));
-        }
-
-        let total = lines.len();
-        let start = offset.min(total);
-        let end = (offset + limit).min(total);
-        let sliced = &lines[start..end];
-
-        let mut res = sliced.join("\n");
-        if end < total {
-            res.push_str(&format!("\n\n(Showing lines {}-{} of {}. Use paginate_tool_output for more.)", start, end, total));
+        match crate::tool_runs::read_output(&runs_dir, target_uuid, "stdout", offset, limit, search) {
+            Ok(page) => {
+                let mut res = page.content;
+                if page.end < page.total {
+                    res.push_str(&format!("\n\n(Showing lines {}-{} of {}. Use paginate_tool_output for more.)", page.start, page.end, page.total));
+                }
+                stdout_all = res;
+            },
+            Err(_) => {
+                stdout_all = format!("Error: Tool run {} not found.", target_uuid);
+            }
         }
-        stdout_all = res;
     } else if call.name == "run_skill_script" {
         let args: Value = serde_json::from_str(&call.arguments)?;
         let skill_name = args["skill_name"].as_str().ok_or_else(|| anyhow!("Missing skill_name"))?;
@@ -82,42 +109,54 @@ async fn execute_tool(
         if let Some(skill) = skills.iter().find(|s| s.metadata.name == skill_name) {
             let script_path = skill.path.join("scripts").join(script_name);
             if !script_path.exists() {
-                return Ok((tool_uuid, format!("Error: Script '{}' not found in skill '{}'.", script_name, skill_name)));
-            }
-
-            let mut cmd = Command::new("bash");
-            cmd.arg("-c");
-            
-            let mut full_cmd = format!("./scripts/{}", script_name);
-            if let Some(args_arr) = args["args"].as_array() {
-                for arg in args_arr {
-                    if let Some(s) = arg.as_str() {
-                        full_cmd.push(' ');
-                        full_cmd.push_str(&format!("'{}'", s.replace("'", "'\\''")));
+                stdout_all = format!("Error: Script '{}' not found in skill '{}'.", script_name, skill_name);
+            } else {
+                let mut cmd = Command::new("bash");
+                cmd.arg("-c");
+                
+                let mut full_cmd = format!("./scripts/{}", script_name);
+                if let Some(args_arr) = args["args"].as_array() {
+                    for arg in args_arr {
+                        if let Some(s) = arg.as_str() {
+                            full_cmd.push(' ');
+                            full_cmd.push_str(&format!("'{}'", s.replace("'", "'\\''")));
+                        }
                     }
                 }
-            }
-            cmd.arg(full_cmd);
-            cmd.current_dir(&skill.path);
+                cmd.arg(full_cmd);
+                cmd.current_dir(&skill.path);
 
-            let child = cmd
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .spawn()?;
+                let child = cmd
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .spawn()?;
 
-            let output = child.wait_with_output().await?;
-            stdout_all = String::from_utf8_lossy(&output.stdout).to_string();
-            stderr_all = String::from_utf8_lossy(&output.stderr).to_string();
+                let output = child.wait_with_output().await?;
+                stdout_all = String::from_utf8_lossy(&output.stdout).to_string();
+                stderr_all = String::from_utf8_lossy(&output.stderr).to_string();
+                exit_code = output.status.code();
+            }
         } else {
-            return Ok((tool_uuid, format!("Error: Skill '{}' not found or not active.", skill_name)));
+            stdout_all = format!("Error: Skill '{}' not found or not active.", skill_name);
         }
     } else if let Some(tool_def) = tools.iter().find(|t| t.name == call.name) {
-        if let Some(exec_cmd) = &tool_def.exec {
+        let args_json: Value = serde_json::from_str(&call.arguments).unwrap_or(json!({}));
+        if let Some(server_name) = &tool_def.mcp_server {
+            match mcp.call_tool(server_name, &call.name, args_json).await {
+                Ok(out) => stdout_all = out,
+                Err(e) => stdout_all = format!("Error: {}", e),
+            }
+        } else if let Some(server_name) = &tool_def.server {
+            let timeout = tokio::time::Duration::from_secs(config.server_tool_timeout_secs);
+            match servers.call_tool(server_name, &call.name, session_id, args_json, timeout).await {
+                Ok(out) => stdout_all = out,
+                Err(e) => stdout_all = format!("Error: {}", e),
+            }
+        } else if let Some(exec_cmd) = &tool_def.exec {
             let mut cmd = Command::new("bash");
             cmd.arg("-c");
             
             let mut full_cmd = exec_cmd.clone();
-            let args_json: Value = serde_json::from_str(&call.arguments).unwrap_or(json!({}));
             if let Some(args_arr) = args_json["args"].as_array() {
                 for arg in args_arr {
                     if let Some(s) = arg.as_str() {
@@ -141,6 +180,12 @@ async fn execute_tool(
             let output = child.wait_with_output().await?;
             stdout_all = String::from_utf8_lossy(&output.stdout).to_string();
             stderr_all = String::from_utf8_lossy(&output.stderr).to_string();
+            exit_code = output.status.code();
+            if !tool_def.output.is_text() {
+                raw_output = Some((tool_def.output, output.stdout));
+            }
+        } else if let Some(res) = execute_builtin(&call.name, &args_json, ctx, &tool_run_dir).await {
+            stdout_all = res.unwrap_or_else(|e| format!("Error: {}", e));
         } else {
             stdout_all = format!("Error: Tool {} has no execution logic defined.", call.name);
         }
@@ -148,13 +193,53 @@ async fn execute_tool(
         stdout_all = format!("Error: Tool {} not found.", call.name);
     }
 
+    // A result the model receives as-is instead of the first lines of stdout
+    let mut full_result = None;
+    let mut artifacts = Vec::new();
+    match raw_output {
+        Some((crate::llm::ToolOutput::Json, raw)) => match serde_json::from_slice::<Value>(&raw) {
+            Ok(value) => {
+                // Pretty-print the saved copy so it can be paginated line by line
+                stdout_all = serde_json::to_string_pretty(&value)?;
+                full_result = Some(json_result(&value, raw.len(), config.tool_json_max_bytes, &tool_uuid));
+            },
+            Err(e) => {
+                stdout_all = format!("Error: Tool {} declared JSON output but returned invalid JSON ({}).\n{}", call.name, e, stdout_all);
+            }
+        },
+        Some((crate::llm::ToolOutput::Artifact, raw)) => {
+            let artifact = crate::tool_runs::store_artifact(&tool_run_dir, &raw)?;
+            stdout_all = format!("Stored artifact {} ({}, {} bytes) in tool run {}.", artifact.id, artifact.mime_type, artifact.size, tool_uuid);
+            artifacts.push(artifact);
+        },
+        _ => {}
+    }
+
     fs::write(tool_run_dir.join("stdout"), &stdout_all)?;
     fs::write(tool_run_dir.join("stderr"), &stderr_all)?;
 
-    let mut result_summary = stdout_all.lines().take(config.tool_output_lines).collect::<Vec<_>>().join("\n");
-    if stdout_all.lines().count() > config.tool_output_lines {
-        result_summary.push_str(&format!("\n\n(Output truncated. Full output saved in tool run {}. Use paginate_tool_output to see more.)", tool_uuid));
-    }
+    let run = crate::tool_runs::ToolRun {
+        id: tool_uuid.clone(),
+        session_id: session_id.to_string(),
+        tool: call.name.clone(),
+        arguments: call.arguments.clone(),
+        started_at: started_at.to_rfc3339(),
+        duration_ms: started.elapsed().as_millis() as u64,
+        exit_code,
+        artifacts,
+    };
+    run.save(&tool_run_dir)?;
+
+    let mut result_summary = match full_result {
+        Some(result) => result,
+        None => {
+            let mut summary = stdout_all.lines().take(config.tool_output_lines).collect::<Vec<_>>().join("\n");
+            if stdout_all.lines().count() > config.tool_output_lines {
+                summary.push_str(&format!("\n\n(Output truncated. Full output saved in tool run {}. Use paginate_tool_output to see more.)", tool_uuid));
+            }
+            summary
+        }
+    };
 
     if !stderr_all.is_empty() {
         result_summary.push_str("\n\nStderr:\n");
@@ -164,6 +249,39 @@ async fn execute_tool(
     Ok((tool_uuid, result_summary))
 }
 
+/// The active skill a tool call belongs to, if any.
+fn skill_for_call(call: &crate::llm::ToolCall, skills: &[crate::skills::Skill]) -> Option<String> {
+    if let Some(skill) = skills.iter().find(|s| s.metadata.tools.iter().any(|t| t.name == call.name)) {
+        return Some(skill.metadata.name.clone());
+    }
+    if matches!(call.name.as_str(), "run_skill_script" | "list_skill_resources" | "read_skill_resource") {
+        let args: Value = serde_json::from_str(&call.arguments).ok()?;
+        return args["skill_name"].as_str().map(|s| s.to_string());
+    }
+    None
+}
+
+/// Passes JSON through unchanged when it fits in `budget` bytes, otherwise
+/// describes its shape and points at the saved copy.
+fn json_result(value: &Value, size: usize, budget: usize, tool_uuid: &str) -> String {
+    if size <= budget {
+        return value.to_string();
+    }
+    let shape = match value {
+        Value::Object(map) => json!({"type": "object", "keys": map.keys().collect::<Vec<_>>()}),
+        Value::Array(arr) => json!({"type": "array", "length": arr.len()}),
+        _ => json!({"type": "scalar"}),
+    };
+    json!({
+        "error": "output_too_large",
+        "bytes": size,
+        "budget": budget,
+        "shape": shape,
+        "tool_call_uuid": tool_uuid,
+        "hint": "Use paginate_tool_output to read the full pretty-printed JSON."
+    }).to_string()
+}
+
 pub async fn start_server(socket_path: &str, session_manager: Arc<SessionManager>) -> Result<()> {
     if Path::new(socket_path).exists() {
         if let Err(e) = fs::remove_file(socket_path) {
@@ -302,6 +420,18 @@ async fn process_command(req: CommandRequest, sm: Arc<SessionManager>, tx: mpsc:
             let action = arguments["action"].as_str().ok_or_else(|| anyhow!("Missing action in server arguments"))?;
             handle_server_action(action, arguments.clone(), sm, tx).await
         },
+        "mcp" => {
+            let action = arguments["action"].as_str().ok_or_else(|| anyhow!("Missing action in mcp arguments"))?;
+            handle_mcp_action(action, arguments.clone(), sm, tx).await
+        },
+        "tool" => {
+            let action = arguments["action"].as_str().ok_or_else(|| anyhow!("Missing action in tool arguments"))?;
+            handle_tool_action(action, arguments.clone(), sm, tx).await
+        },
+        "workspace" => {
+            let action = arguments["action"].as_str().ok_or_else(|| anyhow!("Missing action in workspace arguments"))?;
+            handle_workspace_action(action, arguments.clone(), sm, tx).await
+        },
         _ => {
             tx.send(json!({"error": format!("Unknown command: {}", command)})).await.map_err(|_| anyhow!("Send failed"))?;
             Ok(())
@@ -313,11 +443,35 @@ async fn handle_server_action(action: &str, args: Value, sm: Arc<SessionManager>
     tracing::info!(action = %action, "Processing server action");
     match action {
         "list" => {
+            let processes = sm.supervisor.status().await;
             let servers = sm.server_registry.servers.read().await;
             let list: Vec<_> = servers.values().collect();
             tx.send(json!({
                 "event": "server_list",
-                "servers": list
+                "servers": list,
+                "processes": processes
+            })).await.map_err(|_| anyhow!("Send failed"))?;
+        },
+        "start" | "restart" => {
+            let server_name = args["server_name"].as_str().ok_or_else(|| anyhow!("Missing server_name"))?;
+            let config = sm.config.read().await.servers.iter().find(|s| s.name == server_name).cloned()
+                .ok_or_else(|| anyhow!("Server {} is not declared in [[servers]]", server_name))?;
+            if action == "restart" {
+                let _ = sm.supervisor.stop(server_name).await;
+            }
+            let pid = sm.supervisor.start(&config).await?;
+            tx.send(json!({
+                "event": if action == "restart" { "server_restarted" } else { "server_launched" },
+                "server_name": server_name,
+                "pid": pid
+            })).await.map_err(|_| anyhow!("Send failed"))?;
+        },
+        "stop" => {
+            let server_name = args["server_name"].as_str().ok_or_else(|| anyhow!("Missing server_name"))?;
+            sm.supervisor.stop(server_name).await?;
+            tx.send(json!({
+                "event": "server_stopped",
+                "server_name": server_name
             })).await.map_err(|_| anyhow!("Send failed"))?;
         },
         "get" => {
@@ -337,19 +491,63 @@ async fn handle_server_action(action: &str, args: Value, sm: Arc<SessionManager>
             let server_name = args["server_name"].as_str().ok_or_else(|| anyhow!("Missing server_name"))?;
             let delivery = args["event_delivery"].as_str().unwrap_or("next-turn");
             let mode: crate::servers::EventDeliveryMode = serde_json::from_value(json!(delivery)).unwrap_or_default();
+            let mut attachment = crate::servers::Attachment::new(mode);
+            if !args["filter"].is_null() {
+                attachment.filter = serde_json::from_value(args["filter"].clone())
+                    .map_err(|e| anyhow!("Invalid filter: {}", e))?;
+                attachment.filter.validate()?;
+            }
+            if let Some(template) = args["template"].as_str() {
+                crate::event_filter::validate_template(template)?;
+                attachment.template = Some(template.to_string());
+            }
+            if !args["rate_limit"].is_null() {
+                attachment.rate_limit = serde_json::from_value(args["rate_limit"].clone())
+                    .map_err(|e| anyhow!("Invalid rate_limit: {}", e))?;
+                attachment.rate_limit.validate()?;
+            }
 
             // Connect if not connected
             sm.server_registry.connect_to_server(server_name, sm.clone()).await?;
 
             let session_arc = sm.get_session(session_id).await?;
             let mut session = session_arc.write().await;
-            session.attach_server(server_name.to_string(), mode)?;
+            session.attach_server(server_name.to_string(), attachment.clone())?;
 
             tx.send(json!({
                 "event": "server_attached",
                 "session_id": session_id,
                 "server_name": server_name,
-                "delivery": mode
+                "delivery": mode,
+                "filter": attachment.filter,
+                "template": attachment.template,
+                "rate_limit": attachment.rate_limit
+            })).await.map_err(|_| anyhow!("Send failed"))?;
+        },
+        "events" => {
+            let session_id = args["session_id"].as_str().ok_or_else(|| anyhow!("Missing session_id"))?;
+            let since = args["since"].as_u64().unwrap_or(0);
+            let limit = args["limit"].as_u64().unwrap_or(100) as usize;
+            let session_arc = sm.get_session(session_id).await?;
+            let (events, last_seq) = {
+                let session = session_arc.read().await;
+                (session.event_log.since(since, limit)?, session.event_log.last_seq())
+            };
+            tx.send(json!({
+                "event": "server_events",
+                "session_id": session_id,
+                "events": events,
+                "last_seq": last_seq
+            })).await.map_err(|_| anyhow!("Send failed"))?;
+        },
+        "attachments" => {
+            let session_id = args["session_id"].as_str().ok_or_else(|| anyhow!("Missing session_id"))?;
+            let session_arc = sm.get_session(session_id).await?;
+            let attachments = session_arc.read().await.attached_servers.clone();
+            tx.send(json!({
+                "event": "server_attachments",
+                "session_id": session_id,
+                "attachments": attachments
             })).await.map_err(|_| anyhow!("Send failed"))?;
         },
         "detach" => {
@@ -371,25 +569,48 @@ async fn handle_server_action(action: &str, args: Value, sm: Arc<SessionManager>
             let server_name = args["server_name"].as_str().ok_or_else(|| anyhow!("Missing server_name"))?;
             let message = args["message"].clone();
             let wait_reply = args["wait_reply"].as_bool().unwrap_or(false);
+            let block = args["block"].as_bool().unwrap_or(false);
+            let timeout_secs = match args["timeout_secs"].as_u64() {
+                Some(secs) => secs,
+                None => sm.config.read().await.server_reply_timeout_secs,
+            };
 
-            // Forward to server
-            let msg_id = sm.server_registry.send_message(server_name, session_id, message.clone()).await?;
+            // Registered with the request so a quick reply is not missed
+            let (waiter, reply) = if block {
+                let (waiter, reply) = tokio::sync::oneshot::channel();
+                (Some(waiter), Some(reply))
+            } else {
+                (None, None)
+            };
 
-            // Add original message to history if wait_reply is true
+            // Add original message to history if wait_reply is true, before
+            // sending so a quick reply cannot land ahead of it
             if wait_reply {
                  let session_arc = sm.get_session(session_id).await?;
                  let mut session = session_arc.write().await;
-                 // We add with server name but marked as request?
                  // Spec: "Ruster adds both original message and reply to session history"
-                 session.add_server_message(server_name, message)?;
+                 session.add_server_message(server_name, message.clone())?;
             }
 
-            tx.send(json!({
-                "event": "server_message_sent",
-                "session_id": session_id,
-                "server_name": server_name,
-                "id": msg_id
-            })).await.map_err(|_| anyhow!("Send failed"))?;
+            // Forward to server
+            let msg_id = sm.server_registry
+                .send_message(server_name, session_id, message, std::time::Duration::from_secs(timeout_secs), waiter)
+                .await?;
+
+            let Some(reply) = reply else {
+                tx.send(json!({
+                    "event": "server_message_sent",
+                    "session_id": session_id,
+                    "server_name": server_name,
+                    "id": msg_id
+                })).await.map_err(|_| anyhow!("Send failed"))?;
+                return Ok(());
+            };
+
+            // The request either gets a reply, fails on disconnect, or times out,
+            // and each of these resolves the waiter
+            let ev = reply.await.map_err(|_| anyhow!("No reply to request {}", msg_id))?;
+            tx.send(ev).await.map_err(|_| anyhow!("Send failed"))?;
         },
         "subscribe" => {
             let session_id = args["session_id"].as_str().ok_or_else(|| anyhow!("Missing session_id"))?;
@@ -415,7 +636,298 @@ async fn handle_server_action(action: &str, args: Value, sm: Arc<SessionManager>
     Ok(())
 }
 
+async fn handle_mcp_action(action: &str, _args: Value, sm: Arc<SessionManager>, tx: mpsc::Sender<Value>) -> Result<()> {
+    tracing::info!(action = %action, "Processing mcp action");
+    match action {
+        "list" => {
+            let servers = sm.mcp_manager.servers.read().await;
+            let list: Vec<_> = servers.values().map(|s| json!({
+                "name": s.name,
+                "server_info": s.server_info,
+                "tools": s.tools,
+                "resources": s.resources,
+                "prompts": s.prompts
+            })).collect();
+            tx.send(json!({
+                "event": "mcp_list",
+                "servers": list
+            })).await.map_err(|_| anyhow!("Send failed"))?;
+        },
+        _ => {
+            tx.send(json!({"error": format!("Unknown mcp action: {}", action)})).await.map_err(|_| anyhow!("Send failed"))?;
+        }
+    }
+    Ok(())
+}
+
+async fn handle_tool_action(action: &str, args: Value, sm: Arc<SessionManager>, tx: mpsc::Sender<Value>) -> Result<()> {
+    tracing::info!(action = %action, "Processing tool action");
+    let (runs_dir, max_read_bytes) = {
+        let cfg = sm.config.read().await;
+        (crate::tool_runs::runs_dir(&cfg), cfg.fs_max_read_bytes)
+    };
+    match action {
+        "list" => {
+            let session_id = args["session_id"].as_str();
+            let limit = args["limit"].as_u64().unwrap_or(20) as usize;
+            let offset = args["offset"].as_u64().unwrap_or(0) as usize;
+
+            let runs = crate::tool_runs::list_runs(&runs_dir, session_id)?;
+            let total = runs.len();
+            let start = offset.min(total);
+            let end = offset.saturating_add(limit).min(total);
+
+            tx.send(json!({
+                "event": "tool_list",
+                "session_id": session_id,
+                "runs": &runs[start..end],
+                "total": total,
+                "offset": offset,
+                "limit": limit
+            })).await.map_err(|_| anyhow!("Send failed"))?;
+        },
+        "get" => {
+            let call_id = args["call_id"].as_str().ok_or_else(|| anyhow!("Missing call_id"))?;
+            match crate::tool_runs::get_run(&runs_dir, call_id) {
+                Ok(run) => {
+                    tx.send(json!({
+                        "event": "tool_details",
+                        "run": run
+                    })).await.map_err(|_| anyhow!("Send failed"))?;
+                },
+                Err(e) => {
+                    tx.send(json!({"error": e.to_string()})).await.map_err(|_| anyhow!("Send failed"))?;
+                }
+            }
+        },
+        "output" => {
+            let call_id = args["call_id"].as_str().ok_or_else(|| anyhow!("Missing call_id"))?;
+            let stream = args["stream"].as_str().unwrap_or("stdout");
+            let limit = args["limit"].as_u64().unwrap_or(100) as usize;
+            let offset = args["offset"].as_u64().unwrap_or(0) as usize;
+            let search = args["search"].as_str();
+
+            match crate::tool_runs::read_output(&runs_dir, call_id, stream, offset, limit, search) {
+                Ok(page) => {
+                    tx.send(json!({
+                        "event": "tool_output",
+                        "call_id": call_id,
+                        "stream": stream,
+                        "content": page.content,
+                        "total": page.total,
+                        "offset": page.start,
+                        "limit": limit
+                    })).await.map_err(|_| anyhow!("Send failed"))?;
+                },
+                Err(e) => {
+                    tx.send(json!({"error": e.to_string()})).await.map_err(|_| anyhow!("Send failed"))?;
+                }
+            }
+        },
+        "artifact" => {
+            let call_id = args["call_id"].as_str().ok_or_else(|| anyhow!("Missing call_id"))?;
+            let artifact_id = args["artifact_id"].as_str().ok_or_else(|| anyhow!("Missing artifact_id"))?;
+            let offset = args["offset"].as_u64().unwrap_or(0);
+            let limit = args["limit"].as_u64().unwrap_or(max_read_bytes).min(max_read_bytes);
+            let read = crate::tool_runs::get_artifact(&runs_dir, call_id, artifact_id)
+                .and_then(|(artifact, path)| {
+                    let data = crate::tool_runs::read_artifact(&path, offset, limit)?;
+                    Ok((artifact, path, data))
+                });
+            match read {
+                Ok((artifact, path, data)) => {
+                    use base64::Engine as _;
+                    tx.send(json!({
+                        "event": "tool_artifact",
+                        "call_id": call_id,
+                        "artifact": artifact,
+                        "path": path,
+                        "offset": offset,
+                        "length": data.len(),
+                        "data_base64": base64::engine::general_purpose::STANDARD.encode(data)
+                    })).await.map_err(|_| anyhow!("Send failed"))?;
+                },
+                Err(e) => {
+                    tx.send(json!({"error": e.to_string()})).await.map_err(|_| anyhow!("Send failed"))?;
+                }
+            }
+        },
+        "revert" => {
+            let call_id = args["call_id"].as_str().ok_or_else(|| anyhow!("Missing call_id"))?;
+            let run_dir = crate::tool_runs::run_path(&runs_dir, call_id)?;
+            match crate::fs_tools::revert(&run_dir) {
+                Ok(files) => {
+                    tracing::info!(call_id = %call_id, files = ?files, "Reverted tool run file changes");
+                    tx.send(json!({
+                        "event": "tool_reverted",
+                        "call_id": call_id,
+                        "files": files
+                    })).await.map_err(|_| anyhow!("Send failed"))?;
+                },
+                Err(e) => {
+                    tx.send(json!({"error": e.to_string()})).await.map_err(|_| anyhow!("Send failed"))?;
+                }
+            }
+        },
+        _ => {
+            tx.send(json!({"error": format!("Unknown tool action: {}", action)})).await.map_err(|_| anyhow!("Send failed"))?;
+        }
+    }
+    Ok(())
+}
+
+async fn handle_workspace_action(action: &str, args: Value, sm: Arc<SessionManager>, tx: mpsc::Sender<Value>) -> Result<()> {
+    let session_id = args["session_id"].as_str().ok_or_else(|| anyhow!("Missing session_id"))?;
+    tracing::info!(session_id = %session_id, action = %action, "Processing workspace action");
+    let session_arc = sm.get_session(session_id).await?;
+
+    match action {
+        "add" => {
+            let path = args["path"].as_str().ok_or_else(|| anyhow!("Missing path"))?;
+            let mut session = session_arc.write().await;
+            match session.add_workspace_root(path) {
+                Ok(root) => {
+                    tx.send(json!({
+                        "event": "workspace_added",
+                        "session_id": session_id,
+                        "path": root
+                    })).await.map_err(|_| anyhow!("Send failed"))?;
+                },
+                Err(e) => {
+                    tx.send(json!({"error": e.to_string()})).await.map_err(|_| anyhow!("Send failed"))?;
+                }
+            }
+        },
+        "remove" => {
+            let path = args["path"].as_str().ok_or_else(|| anyhow!("Missing path"))?;
+            let mut session = session_arc.write().await;
+            session.remove_workspace_root(path)?;
+            tx.send(json!({
+                "event": "workspace_removed",
+                "session_id": session_id,
+                "path": path
+            })).await.map_err(|_| anyhow!("Send failed"))?;
+        },
+        "list" => {
+            let session = session_arc.read().await;
+            tx.send(json!({
+                "event": "workspace_list",
+                "session_id": session_id,
+                "roots": session.workspace.roots
+            })).await.map_err(|_| anyhow!("Send failed"))?;
+        },
+        _ => {
+            tx.send(json!({"error": format!("Unknown workspace action: {}", action)})).await.map_err(|_| anyhow!("Send failed"))?;
+        }
+    }
+    Ok(())
+}
+
+async fn handle_skill_install_action(action: &str, args: Value, sm: Arc<SessionManager>, tx: mpsc::Sender<Value>) -> Result<()> {
+    let installer = crate::skill_install::Installer::from_config(&*sm.config.read().await)?;
+    match action {
+        "install" => {
+            let source = args["source"].as_str().ok_or_else(|| anyhow!("Missing source"))?;
+            tracing::info!(source = %source, "Installing skill");
+            let req = crate::skill_install::InstallRequest {
+                source: source.to_string(),
+                reference: args["ref"].as_str().map(|s| s.to_string()),
+                subdir: args["subdir"].as_str().map(|s| s.to_string()),
+                force: args["force"].as_bool().unwrap_or(false),
+            };
+            let (entry, change) = installer.install(&req, &sm.skills_manager).await?;
+            if let Some(change) = change {
+                let _ = sm.event_sender.send(change.to_event());
+            }
+            tx.send(json!({
+                "event": "skill_installed",
+                "skill": entry.name,
+                "path": entry.path,
+                "source": entry.source,
+                "kind": entry.kind,
+                "revision": entry.revision
+            })).await.map_err(|_| anyhow!("Send failed"))?;
+        },
+        "uninstall" => {
+            let skill_name = args["skill"].as_str().ok_or_else(|| anyhow!("Missing skill name"))?;
+            tracing::info!(skill = %skill_name, "Uninstalling skill");
+            let change = installer.uninstall(skill_name, &sm.skills_manager).await?;
+            if let Some(change) = change {
+                let _ = sm.event_sender.send(change.to_event());
+            }
+            tx.send(json!({
+                "event": "skill_uninstalled",
+                "skill": skill_name
+            })).await.map_err(|_| anyhow!("Send failed"))?;
+        },
+        _ => {
+            let skill_name = args["skill"].as_str();
+            tracing::info!(skill = ?skill_name, "Updating installed skills");
+            let (results, changes) = installer.update(skill_name, &sm.skills_manager).await?;
+            for change in changes {
+                let _ = sm.event_sender.send(change.to_event());
+            }
+            tx.send(json!({
+                "event": "skill_update_results",
+                "results": results
+            })).await.map_err(|_| anyhow!("Send failed"))?;
+        }
+    }
+    Ok(())
+}
+
 async fn handle_skill_action(action: &str, args: Value, sm: Arc<SessionManager>, tx: mpsc::Sender<Value>) -> Result<()> {
+    // Actions on the global skill set, no session needed
+    if action == "reload" {
+        tracing::info!("Reloading skills from disk");
+        let dirs = sm.config.read().await.skill_search_path();
+        let changes = sm.skills_manager.write().await.reload_all(&dirs)?;
+        let events: Vec<Value> = changes.iter().map(|c| c.to_event()).collect();
+        for event in &events {
+            let _ = sm.event_sender.send(event.clone());
+        }
+        tx.send(json!({
+            "event": "skill_reloaded",
+            "changes": events
+        })).await.map_err(|_| anyhow!("Send failed"))?;
+        return Ok(());
+    }
+    if action == "which" {
+        let skill_name = args["skill"].as_str().ok_or_else(|| anyhow!("Missing skill name"))?;
+        let resolution = sm.skills_manager.read().await.which(skill_name)
+            .ok_or_else(|| anyhow!("Skill '{}' not found", skill_name))?;
+        tx.send(json!({
+            "event": "skill_which",
+            "skill": resolution.skill,
+            "path": resolution.path,
+            "shadowed": resolution.shadowed
+        })).await.map_err(|_| anyhow!("Send failed"))?;
+        return Ok(());
+    }
+    if action == "lint" {
+        let paths: Vec<std::path::PathBuf> = match &args["path"] {
+            Value::String(p) => vec![crate::config::expand_path(p)],
+            Value::Array(ps) => ps.iter().filter_map(|p| p.as_str()).map(crate::config::expand_path).collect(),
+            _ => sm.config.read().await.skill_search_path().iter()
+                .map(|d| crate::config::expand_path(d))
+                .filter(|d| d.exists())
+                .collect(),
+        };
+        tracing::info!(paths = ?paths, "Linting skills");
+        let diagnostics = crate::skill_lint::lint_paths(&paths);
+        let errors = diagnostics.iter().filter(|d| d.severity == crate::skill_lint::Severity::Error).count();
+        tx.send(json!({
+            "event": "skill_lint",
+            "errors": errors,
+            "warnings": diagnostics.len() - errors,
+            "diagnostics": diagnostics
+        })).await.map_err(|_| anyhow!("Send failed"))?;
+        return Ok(());
+    }
+    if matches!(action, "install" | "uninstall" | "update") {
+        return handle_skill_install_action(action, args, sm, tx).await;
+    }
+
     let session_id = args["session_id"].as_str().ok_or_else(|| anyhow!("Missing session_id"))?;
     tracing::info!(session_id = %session_id, action = %action, "Processing skill action");
     let session_arc = sm.get_session(session_id).await?;
@@ -447,21 +959,35 @@ async fn handle_skill_action(action: &str, args: Value, sm: Arc<SessionManager>,
              // List skills currently in session
              tracing::debug!(session_id = %session_id, "Listing session skills");
              let session = session_arc.read().await;
+             let (skills, shadowed): (Vec<_>, Vec<_>) = {
+                 let mgr = sm.skills_manager.read().await;
+                 (
+                     session.active_skills.iter()
+                         .filter_map(|name| mgr.get_skill(name))
+                         .map(|s| s.metadata.clone())
+                         .collect(),
+                     mgr.shadowed_skills().into_iter()
+                         .map(|(s, winner)| json!({"skill": s.metadata.name, "path": s.path, "shadowed_by": winner}))
+                         .collect(),
+                 )
+             };
              tx.send(json!({
                  "event": "skill_list",
                  "session_id": session_id,
-                 "active_skills": session.active_skills
+                 "active_skills": session.active_skills,
+                 "pinned_skills": session.active_skills.iter()
+                     .filter(|s| !session.skill_last_relevant.contains_key(*s))
+                     .collect::<Vec<_>>(),
+                 "skills": skills,
+                 "shadowed": shadowed
              })).await.map_err(|_| anyhow!("Send failed"))?;
         },
         "search" => {
             let query = args["query"].as_str().ok_or_else(|| anyhow!("Missing query"))?;
             tracing::info!(session_id = %session_id, query = %query, "Searching for skills");
             let mut mgr = sm.skills_manager.write().await;
-            let (rag_model, top_n, threshold) = {
-                let cfg = sm.config.read().await;
-                (cfg.rag_model.clone(), cfg.rag_top_n, cfg.rag_threshold)
-            };
-            let results = mgr.search_skills(query, &sm.llm_client, &rag_model, top_n, threshold).await?;
+            let retrieval = crate::skills::RetrievalOptions::from_config(&*sm.config.read().await);
+            let results = mgr.search_skills(query, &sm.llm_client, &retrieval).await?;
             let metadata: Vec<_> = results.iter().map(|s| &s.metadata).collect();
             tx.send(json!({
                 "event": "skill_search_results",
@@ -559,7 +1085,301 @@ async fn handle_config_action(action: &str, args: Value, sm: Arc<SessionManager>
     Ok(())
 }
 
-async fn handle_session_action(action: &str, req: Value, sm: Arc<SessionManager>, tx: mpsc::Sender<Value>) -> Result<()> {
+/// Runs one LLM turn over the session history, including the tool loop,
+/// and streams its events to `tx`. `message` is what triggered the turn.
+async fn run_turn(sm: &Arc<SessionManager>, session_id: &str, session_arc: &Arc<tokio::sync::RwLock<crate::session::Session>>, message: &str, tx: &mpsc::Sender<Value>) -> Result<()> {
+    // 1. Prepare context (detect skills)
+    let (context, mut skills, mut tools) = {
+        let mut session = session_arc.write().await;
+        session.prepare_context().await?
+    };
+    
+    if !skills.is_empty() {
+        let names: Vec<_> = skills.iter().map(|s| &s.metadata.name).collect();
+        tracing::info!(session_id = %session_id, skills = ?names, "LLM starting generation with skills enabled.");
+    } else {
+        tracing::info!(session_id = %session_id, "LLM starting generation (no skills).");
+    }
+    
+    for skill in &skills {
+        tracing::debug!(session_id = %session_id, skill = %skill.metadata.name, "Skill instructions injected into context");
+        tx.send(json!({
+            "event": "skill_used",
+            "session_id": session_id,
+            "skill": skill.metadata.name,
+            "result": "Skill instructions injected." 
+        })).await.map_err(|_| anyhow!("Send failed"))?;
+    }
+
+    // 2. Call LLM stream (with tool loop)
+    let (model_str, workspace) = {
+        let session = session_arc.read().await;
+        (session.model.clone(), session.workspace.clone())
+    };
+    
+    let mut context = context;
+    let mut full_response = String::new();
+    let mut iteration = 0;
+    let max_iterations = 10;
+
+    loop {
+        iteration += 1;
+        if iteration > max_iterations {
+            tracing::warn!(session_id = %session_id, "Max tool iterations reached");
+            break;
+        }
+
+        tracing::info!(session_id = %session_id, iteration = %iteration, model = %model_str, "Starting LLM stream");
+        
+        let mut stream = sm.llm_client.chat_stream(&model_str, context.clone(), if tools.is_empty() { None } else { Some(tools.clone()) }, None).await?;
+        
+        let mut current_text = String::new();
+        let mut tool_calls_this_turn = Vec::<crate::llm::ToolCall>::new();
+
+        tx.send(json!({
+            "event": "status",
+            "session_id": session_id,
+            "message": if iteration == 1 { "Thinking..." } else { "Refining..." }
+        })).await.map_err(|_| anyhow!("Send failed"))?;
+        
+        while let Some(chunk_res) = stream.next().await {
+            match chunk_res {
+                Ok(response) => {
+                    match response {
+                        crate::llm::LlmResponse::Text(chunk) => {
+                            current_text.push_str(&chunk);
+                            tx.send(json!({
+                                "event": "response",
+                                "session_id": session_id,
+                                "delta": chunk,
+                                "done": false
+                            })).await.map_err(|_| anyhow!("Send failed"))?;
+                        },
+                        crate::llm::LlmResponse::ToolCall(mut call) => {
+                            // Ensure unique ID for tool call if provider doesn't give one
+                            if call.id == "ollama" || call.id == "gemini" || call.id.is_empty() {
+                                call.id = format!("call_{}", Uuid::new_v4().to_string()[..8].to_string());
+                            }
+                            tool_calls_this_turn.push(call);
+                        }
+                    }
+                },
+                Err(e) => {
+                    tracing::error!(session_id = %session_id, error = %e, "LLM Stream Error occurred.");
+                    tx.send(json!({
+                        "error": format!("LLM Stream Error: {}", e),
+                        "session_id": session_id
+                    })).await.map_err(|_| anyhow!("Send failed"))?;
+                    return Err(e);
+                }
+            }
+        }
+
+        if !current_text.is_empty() {
+            if !full_response.is_empty() {
+                full_response.push_str("\n");
+            }
+            full_response.push_str(&current_text);
+        }
+
+        if tool_calls_this_turn.is_empty() {
+            break;
+        }
+
+        // Add assistant's tool calls to context
+        let mut assistant_msg = json!({
+            "role": "assistant",
+            "content": if current_text.is_empty() { Value::Null } else { json!(current_text) }
+        });
+        
+        let provider = model_str.split('/').next().unwrap_or("");
+        let tool_calls_json: Vec<_> = tool_calls_this_turn.iter().map(|tc| {
+            if provider == "ollama" || provider == "gemini" {
+                // Ollama and Gemini expect arguments as a JSON object.
+                let args_value: Value = serde_json::from_str(&tc.arguments).unwrap_or(json!(tc.arguments));
+                json!({
+                    "id": tc.id,
+                    "type": "function",
+                    "function": {
+                        "name": tc.name,
+                        "arguments": args_value
+                    }
+                })
+            } else {
+                // OpenAI/xAI expects arguments as a JSON string, not an object.
+                json!({
+                    "id": tc.id,
+                    "type": "function",
+                    "function": {
+                        "name": tc.name,
+                        "arguments": tc.arguments
+                    }
+                })
+            }
+        }).collect();
+        assistant_msg["tool_calls"] = json!(tool_calls_json);
+        context.push(assistant_msg);
+
+        // Execute tools and add results to context
+        let mut activated = false;
+        for call in tool_calls_this_turn {
+            tracing::info!(session_id = %session_id, tool = %call.name, "LLM requested tool call");
+            
+            let config = sm.config.read().await.clone();
+            let ctx = ToolContext {
+                tools: &tools,
+                skills: &skills,
+                skills_manager: &sm.skills_manager,
+                config: &config,
+                mcp: &sm.mcp_manager,
+                servers: &sm.server_registry,
+                workspace: &workspace,
+                session_id,
+            };
+            let (tool_uuid, result) = match execute_tool(call.clone(), &ctx, message, &full_response).await {
+                Ok(res) => res,
+                Err(e) => (Uuid::new_v4().to_string(), format!("Error executing tool: {}", e)),
+            };
+
+            // Using a skill's tools keeps it loaded
+            if let Some(skill) = skill_for_call(&call, &skills) {
+                session_arc.write().await.mark_skill_relevant(&skill);
+            }
+
+            if call.name == "activate_skill" && crate::skills::ToolPolicy::for_skills(&skills).allows_call(&call.name, &call.arguments) {
+                let args: Value = serde_json::from_str(&call.arguments).unwrap_or(json!({}));
+                let target = crate::skill_discovery::activation_target(&args, &*sm.skills_manager.read().await, &config.banned_skills);
+                if let Ok(skill) = target
+                    && session_arc.write().await.activate_skill(&skill)
+                {
+                    activated = true;
+                    tx.send(json!({
+                        "event": "skill_activated",
+                        "session_id": session_id,
+                        "skill": skill
+                    })).await.map_err(|_| anyhow!("Send failed"))?;
+                }
+            }
+
+            tx.send(json!({
+                "event": "tool_call",
+                "session_id": session_id,
+                "tool": call.name,
+                "arguments": call.arguments,
+                "call_id": tool_uuid,
+                "result_preview": result
+            })).await.map_err(|_| anyhow!("Send failed"))?;
+            
+            full_response.push_str(&format!("\n[Tool Call: {} (ID: {}) resulted in:\n{}]", call.name, tool_uuid, result));
+
+            context.push(json!({
+                "role": "tool",
+                "tool_call_id": call.id,
+                "name": call.name,
+                "content": result
+            }));
+        }
+
+        // Inject newly activated skills into the next iteration
+        if activated {
+            (skills, tools) = session_arc.read().await.skills_and_tools().await;
+            context[0] = json!({"role": "system", "content": crate::session::system_prompt(&skills)});
+            let names: Vec<_> = skills.iter().map(|s| &s.metadata.name).collect();
+            tracing::info!(session_id = %session_id, skills = ?names, "Context refreshed with activated skills.");
+        }
+    }
+    
+    tracing::info!(session_id = %session_id, response_len = %full_response.len(), "LLM stream completed");
+    
+    tx.send(json!({
+        "event": "response",
+        "session_id": session_id,
+        "delta": "", 
+        "done": true
+    })).await.map_err(|_| anyhow!("Send failed"))?;
+    
+    // 3. Add assistant message
+    {
+        let mut session = session_arc.write().await;
+        let current_skills = session.active_skills.clone();
+        session.add_assistant_message(full_response, current_skills)?;
+    }
+    Ok(())
+}
+
+type ServerTurnEvent = (String, Value);
+
+/// Answers a session's immediate server events, one turn at a time and in
+/// arrival order. Runs until the session is dropped.
+pub async fn run_server_turns(sm: Arc<SessionManager>, session_id: String, mut queued: mpsc::UnboundedReceiver<ServerTurnEvent>) {
+    let mut next = None;
+    loop {
+        let first = match next.take() {
+            Some(event) => event,
+            None => match queued.recv().await {
+                Some(event) => event,
+                None => return,
+            },
+        };
+        if let Err(e) = run_server_turn(&sm, &session_id, first, &mut queued, &mut next).await {
+            tracing::error!(session_id = %session_id, error = %e, "Server-initiated turn failed.");
+        }
+    }
+}
+
+/// Delivers a server event as a new turn once the session's earlier turns are
+/// done. Further events from the same server that queued up by then are
+/// answered in the same turn; the first event from another server is left in
+/// `next`. The response is streamed to every connected client.
+async fn run_server_turn(
+    sm: &Arc<SessionManager>,
+    session_id: &str,
+    (server_name, event): ServerTurnEvent,
+    queued: &mut mpsc::UnboundedReceiver<ServerTurnEvent>,
+    next: &mut Option<ServerTurnEvent>,
+) -> Result<()> {
+    let session_arc = sm.get_session(session_id).await?;
+    let turn_queue = session_arc.read().await.turn_queue.clone();
+    let _turn = turn_queue.lock().await;
+
+    let mut events = vec![event];
+    while let Ok((name, event)) = queued.try_recv() {
+        if name != server_name {
+            *next = Some((name, event));
+            break;
+        }
+        events.push(event);
+    }
+
+    tracing::info!(session_id = %session_id, server = %server_name, events = %events.len(), "Starting server-initiated turn");
+    {
+        let mut session = session_arc.write().await;
+        for event in &events {
+            session.add_server_message(&server_name, event.clone())?;
+        }
+    }
+    let _ = sm.event_sender.send(json!({
+        "event": "server_turn",
+        "session_id": session_id,
+        "server_name": server_name,
+        "events": events.len()
+    }));
+
+    let (tx, mut rx) = mpsc::channel::<Value>(100);
+    let broadcast = sm.event_sender.clone();
+    let forward = tokio::spawn(async move {
+        while let Some(event) = rx.recv().await {
+            let _ = broadcast.send(event);
+        }
+    });
+    let message = events.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("\n");
+    let res = run_turn(sm, session_id, &session_arc, &message, &tx).await;
+    drop(tx);
+    let _ = forward.await;
+    res
+}
+
+pub(crate) async fn handle_session_action(action: &str, req: Value, sm: Arc<SessionManager>, tx: mpsc::Sender<Value>) -> Result<()> {
     tracing::info!(action = %action, "Processing session action");
     match action {
         "create" => {
@@ -599,193 +1419,28 @@ async fn handle_session_action(action: &str, req: Value, sm: Arc<SessionManager>
             
             tracing::info!(session_id = %session_id, "Handling message send");
             let session_arc = sm.get_session(session_id).await?;
+
+            // Wait for turns already queued in this session
+            let turn_queue = session_arc.read().await.turn_queue.clone();
+            let _turn = turn_queue.lock().await;
             
             // 1. Add user message
             {
                 let mut session = session_arc.write().await;
                 // Get currently active skills to tag message
                 let current_skills = session.active_skills.clone();
-                session.add_user_message(message.to_string(), current_skills).await?;
-            }
-            
-            // 2. Prepare context (detect skills)
-            let (context, skills, tools) = {
-                let mut session = session_arc.write().await;
-                session.prepare_context().await?
-            };
-            
-            if !skills.is_empty() {
-                let names: Vec<_> = skills.iter().map(|s| &s.metadata.name).collect();
-                tracing::info!(session_id = %session_id, skills = ?names, "LLM starting generation with skills enabled.");
-            } else {
-                tracing::info!(session_id = %session_id, "LLM starting generation (no skills).");
-            }
-            
-            for skill in &skills {
-                tracing::debug!(session_id = %session_id, skill = %skill.metadata.name, "Skill instructions injected into context");
-                tx.send(json!({
-                    "event": "skill_used",
-                    "session_id": session_id,
-                    "skill": skill.metadata.name,
-                    "result": "Skill instructions injected." 
-                })).await.map_err(|_| anyhow!("Send failed"))?;
-            }
-
-            // 3. Call LLM stream (with tool loop)
-            let model_str = {
-                let session = session_arc.read().await;
-                session.model.clone()
-            };
-            
-            let mut context = context;
-            let mut full_response = String::new();
-            let mut iteration = 0;
-            let max_iterations = 10;
-
-            loop {
-                iteration += 1;
-                if iteration > max_iterations {
-                    tracing::warn!(session_id = %session_id, "Max tool iterations reached");
-                    break;
-                }
-
-                tracing::info!(session_id = %session_id, iteration = %iteration, model = %model_str, "Starting LLM stream");
-                
-                let mut stream = sm.llm_client.chat_stream(&model_str, context.clone(), if tools.is_empty() { None } else { Some(tools.clone()) }, None).await?;
-                
-                let mut current_text = String::new();
-                let mut tool_calls_this_turn = Vec::<crate::llm::ToolCall>::new();
-
-                tx.send(json!({
-                    "event": "response",
-                    "session_id": session_id,
-                    "delta": if iteration == 1 { "Thinking..." } else { "Refining..." },
-                    "done": false
-                })).await.map_err(|_| anyhow!("Send failed"))?;
-                
-                while let Some(chunk_res) = stream.next().await {
-                    match chunk_res {
-                        Ok(response) => {
-                            match response {
-                                crate::llm::LlmResponse::Text(chunk) => {
-                                    current_text.push_str(&chunk);
-                                    tx.send(json!({
-                                        "event": "response",
-                                        "session_id": session_id,
-                                        "delta": chunk,
-                                        "done": false
-                                    })).await.map_err(|_| anyhow!("Send failed"))?;
-                                },
-                                crate::llm::LlmResponse::ToolCall(mut call) => {
-                                    // Ensure unique ID for tool call if provider doesn't give one
-                                    if call.id == "ollama" || call.id == "gemini" || call.id.is_empty() {
-                                        call.id = format!("call_{}", Uuid::new_v4().to_string()[..8].to_string());
-                                    }
-                                    tool_calls_this_turn.push(call);
-                                }
-                            }
-                        },
-                        Err(e) => {
-                            tracing::error!(session_id = %session_id, error = %e, "LLM Stream Error occurred.");
-                            tx.send(json!({
-                                "error": format!("LLM Stream Error: {}", e),
-                                "session_id": session_id
-                            })).await.map_err(|_| anyhow!("Send failed"))?;
-                            return Err(e);
-                        }
-                    }
-                }
-
-                if !current_text.is_empty() {
-                    if !full_response.is_empty() {
-                        full_response.push_str("\n");
-                    }
-                    full_response.push_str(&current_text);
-                }
-
-                if tool_calls_this_turn.is_empty() {
-                    break;
-                }
-
-                // Add assistant's tool calls to context
-                let mut assistant_msg = json!({
-                    "role": "assistant",
-                    "content": if current_text.is_empty() { Value::Null } else { json!(current_text) }
-                });
-                
-                let provider = model_str.split('/').next().unwrap_or("");
-                let tool_calls_json: Vec<_> = tool_calls_this_turn.iter().map(|tc| {
-                    if provider == "ollama" || provider == "gemini" {
-                        // Ollama and Gemini expect arguments as a JSON object.
-                        let args_value: Value = serde_json::from_str(&tc.arguments).unwrap_or(json!(tc.arguments));
-                        json!({
-                            "id": tc.id,
-                            "type": "function",
-                            "function": {
-                                "name": tc.name,
-                                "arguments": args_value
-                            }
-                        })
-                    } else {
-                        // OpenAI/xAI expects arguments as a JSON string, not an object.
-                        json!({
-                            "id": tc.id,
-                            "type": "function",
-                            "function": {
-                                "name": tc.name,
-                                "arguments": tc.arguments
-                            }
-                        })
-                    }
-                }).collect();
-                assistant_msg["tool_calls"] = json!(tool_calls_json);
-                context.push(assistant_msg);
-
-                // Execute tools and add results to context
-                for call in tool_calls_this_turn {
-                    tracing::info!(session_id = %session_id, tool = %call.name, "LLM requested tool call");
-                    
-                    let config = sm.config.read().await.clone();
-                    let (tool_uuid, result) = match execute_tool(call.clone(), &tools, &skills, &config, message, &full_response).await {
-                        Ok(res) => res,
-                        Err(e) => (Uuid::new_v4().to_string(), format!("Error executing tool: {}", e)),
-                    };
-
+                let unloaded = session.add_user_message(message.to_string(), current_skills).await?;
+                for skill in unloaded {
                     tx.send(json!({
-                        "event": "tool_call",
+                        "event": "skill_unloaded",
                         "session_id": session_id,
-                        "tool": call.name,
-                        "arguments": call.arguments,
-                        "call_id": tool_uuid,
-                        "result_preview": result
+                        "skill": skill,
+                        "reason": "Not relevant for recent turns."
                     })).await.map_err(|_| anyhow!("Send failed"))?;
-                    
-                    full_response.push_str(&format!("\n[Tool Call: {} (ID: {}) resulted in:\n{}]", call.name, tool_uuid, result));
-
-                    context.push(json!({
-                        "role": "tool",
-                        "tool_call_id": call.id,
-                        "name": call.name,
-                        "content": result
-                    }));
                 }
             }
             
-            tracing::info!(session_id = %session_id, response_len = %full_response.len(), "LLM stream completed");
-            
-            tx.send(json!({
-                "event": "response",
-                "session_id": session_id,
-                "delta": "", 
-                "done": true
-            })).await.map_err(|_| anyhow!("Send failed"))?;
-            
-            // 4. Add assistant message
-            {
-                let mut session = session_arc.write().await;
-                let current_skills = session.active_skills.clone();
-                session.add_assistant_message(full_response, current_skills)?;
-            }
+            run_turn(&sm, session_id, &session_arc, message, &tx).await?;
         },
         "list" => {
             let sessions = sm.list_sessions().await?;
@@ -0,0 +1,949 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, Lines};
+use tokio::net::UnixStream;
+use tokio::sync::{mpsc, oneshot, Mutex, RwLock};
+use tokio::time::Duration;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use anyhow::{Result, anyhow};
+use uuid::Uuid;
+use crate::inotify::{self, Inotify};
+use crate::event_filter::{self, EventFilter};
+use crate::llm::{Tool, ToolOutput};
+use crate::session::SessionManager;
+
+// The wire format is shared with Servers written with the SDK
+pub use ruster_server_sdk::protocol::{error_code, socket_path, Frame, Hello, ToolSpec, PROTOCOL_VERSION, SOCKET_PREFIX, SOCKET_SUFFIX};
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+/// Rescan interval when the discovery directory cannot be watched
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+const DISCOVERY_MASK: u32 = inotify::IN_CREATE | inotify::IN_DELETE | inotify::IN_MOVED_FROM | inotify::IN_MOVED_TO | inotify::IN_DELETE_SELF | inotify::IN_MOVE_SELF;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum EventDeliveryMode {
+    Immediate,
+    Proactive,
+    #[default]
+    NextTurn,
+}
+
+/// How a session receives the events of one attached server.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "StoredAttachment")]
+pub struct Attachment {
+    pub delivery: EventDeliveryMode,
+    #[serde(default, skip_serializing_if = "EventFilter::is_empty")]
+    pub filter: EventFilter,
+    // Condenses each event to a line of text, see `event_filter::render`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    #[serde(default)]
+    pub rate_limit: RateLimit,
+    // Events the rate limit dropped or merged so far
+    #[serde(default)]
+    pub dropped: u64,
+    #[serde(default)]
+    pub coalesced: u64,
+}
+
+/// What happens to events arriving faster than the rate limit allows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverflowPolicy {
+    #[default]
+    Drop,
+    // Held back and delivered as one event with their count and the latest one
+    Coalesce,
+}
+
+/// Token bucket applied to the events of one attachment.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimit {
+    // Events per second
+    pub rate: f64,
+    // Events that may arrive at once
+    pub burst: u32,
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self { rate: 5.0, burst: 10, overflow: OverflowPolicy::Drop }
+    }
+}
+
+impl RateLimit {
+    pub fn validate(&self) -> Result<()> {
+        if !self.rate.is_finite() || self.rate <= 0.0 || self.burst == 0 {
+            return Err(anyhow!("Rate limit needs a positive rate and burst"));
+        }
+        Ok(())
+    }
+}
+
+impl Attachment {
+    pub fn new(delivery: EventDeliveryMode) -> Self {
+        Self { delivery, ..Default::default() }
+    }
+
+    /// Applies the filter and template. Returns what the session receives,
+    /// or `None` if the event is filtered out.
+    pub fn admit(&self, event: &Value) -> Option<Value> {
+        if !self.filter.matches(event) {
+            return None;
+        }
+        Some(match &self.template {
+            Some(t) => Value::String(event_filter::render(t, event)),
+            None => event.clone(),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredAttachment {
+    // Attachments saved as a bare delivery mode
+    Mode(EventDeliveryMode),
+    Full {
+        #[serde(default)]
+        delivery: EventDeliveryMode,
+        #[serde(default)]
+        filter: EventFilter,
+        #[serde(default)]
+        template: Option<String>,
+        #[serde(default)]
+        rate_limit: RateLimit,
+        #[serde(default)]
+        dropped: u64,
+        #[serde(default)]
+        coalesced: u64,
+    },
+}
+
+impl From<StoredAttachment> for Attachment {
+    fn from(stored: StoredAttachment) -> Self {
+        match stored {
+            StoredAttachment::Mode(delivery) => Self::new(delivery),
+            StoredAttachment::Full { delivery, filter, template, rate_limit, dropped, coalesced } => {
+                Self { delivery, filter, template, rate_limit, dropped, coalesced }
+            }
+        }
+    }
+}
+
+/// Receives the `server_reply` or `server_reply_timeout` event of one `send`.
+pub type ReplyWaiter = oneshot::Sender<Value>;
+
+/// What a connection hands to its owner.
+#[derive(Debug)]
+pub enum Inbound {
+    Event { event: String, data: Value },
+    /// Answer to a request sent on behalf of a session with `send`
+    Reply { id: String, session_id: String, result: Result<Value>, waiter: Option<ReplyWaiter> },
+    /// A request sent with `send` was not answered in time
+    ReplyTimeout { id: String, session_id: String, waiter: Option<ReplyWaiter> },
+    Closed,
+}
+
+enum Pending {
+    /// A caller of `request` is waiting for the answer
+    Waiter(oneshot::Sender<Result<Value>>),
+    /// The answer goes to the session that sent the request, and to the
+    /// caller blocked on it if any
+    Session(String, Option<ReplyWaiter>),
+}
+
+type PendingMap = Arc<Mutex<HashMap<String, Pending>>>;
+
+/// An established protocol connection to one Server.
+pub struct ServerConnection {
+    name: String,
+    pub hello: Hello,
+    writer: mpsc::Sender<String>,
+    pending: PendingMap,
+    inbound: mpsc::Sender<Inbound>,
+}
+
+impl ServerConnection {
+    /// Performs the handshake over `reader`/`writer` and starts serving the
+    /// connection. Events and session replies are delivered on the returned channel.
+    pub async fn handshake<R, W>(name: &str, reader: R, writer: W) -> Result<(Self, mpsc::Receiver<Inbound>)>
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
+        let mut writer = writer;
+        let mut lines = BufReader::new(reader).lines();
+
+        let ours = Frame::Hello(Hello {
+            protocol: PROTOCOL_VERSION,
+            name: "ruster".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            capabilities: vec!["events".to_string(), "requests".to_string(), "tools".to_string()],
+            events: BTreeMap::new(),
+            tools: Vec::new(),
+        });
+        writer.write_all(ours.to_line().as_bytes()).await?;
+        writer.flush().await?;
+
+        let line = tokio::time::timeout(HANDSHAKE_TIMEOUT, lines.next_line()).await
+            .map_err(|_| anyhow!("Server {} did not answer the handshake", name))??
+            .ok_or_else(|| anyhow!("Server {} closed the connection during the handshake", name))?;
+        let hello = match serde_json::from_str::<Frame>(&line) {
+            Ok(Frame::Hello(hello)) => hello,
+            Ok(Frame::Error { code, message, .. }) => return Err(anyhow!("Server {} refused the handshake ({}): {}", name, code, message)),
+            Ok(_) => {
+                let _ = writer.write_all(Frame::error(None, error_code::UNEXPECTED_FRAME, "Expected hello").to_line().as_bytes()).await;
+                return Err(anyhow!("Server {} did not start with hello", name));
+            }
+            Err(e) => {
+                let _ = writer.write_all(Frame::error(None, error_code::INVALID_FRAME, e.to_string()).to_line().as_bytes()).await;
+                return Err(anyhow!("Invalid handshake from server {}: {}", name, e));
+            }
+        };
+        if hello.protocol != PROTOCOL_VERSION {
+            let msg = format!("Protocol version {} is not supported, expected {}", hello.protocol, PROTOCOL_VERSION);
+            let _ = writer.write_all(Frame::error(None, error_code::UNSUPPORTED_VERSION, msg.clone()).to_line().as_bytes()).await;
+            return Err(anyhow!("Server {}: {}", name, msg));
+        }
+        if hello.name != name {
+            tracing::warn!(server = %name, announced = %hello.name, "Server announced a different name than its socket.");
+        }
+
+        let (tx, mut rx) = mpsc::channel::<String>(100);
+        tokio::spawn(async move {
+            while let Some(line) = rx.recv().await {
+                if writer.write_all(line.as_bytes()).await.is_err() { break; }
+                if writer.flush().await.is_err() { break; }
+            }
+        });
+
+        let pending: PendingMap = Arc::new(Mutex::new(HashMap::new()));
+        let (inbound_tx, inbound_rx) = mpsc::channel(100);
+        tokio::spawn(read_loop(name.to_string(), hello.events.clone(), lines, pending.clone(), tx.clone(), inbound_tx.clone()));
+
+        Ok((Self { name: name.to_string(), hello, writer: tx, pending, inbound: inbound_tx }, inbound_rx))
+    }
+
+    async fn send_request(&self, id: &str, method: &str, session_id: Option<&str>, params: Value, pending: Pending) -> Result<()> {
+        self.pending.lock().await.insert(id.to_string(), pending);
+        let frame = Frame::Request {
+            id: id.to_string(),
+            method: method.to_string(),
+            session_id: session_id.map(|s| s.to_string()),
+            params,
+        };
+        if self.writer.send(frame.to_line()).await.is_err() {
+            self.pending.lock().await.remove(id);
+            return Err(anyhow!("Server {} is not connected", self.name));
+        }
+        Ok(())
+    }
+
+    /// Sends a request and waits for its reply.
+    pub async fn request(&self, method: &str, session_id: Option<&str>, params: Value, timeout: Duration) -> Result<Value> {
+        let id = Uuid::new_v4().to_string();
+        let (tx, rx) = oneshot::channel();
+        self.send_request(&id, method, session_id, params, Pending::Waiter(tx)).await?;
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(res)) => res,
+            Ok(Err(_)) => Err(anyhow!("Server {} dropped request {}", self.name, id)),
+            Err(_) => {
+                self.pending.lock().await.remove(&id);
+                Err(anyhow!("Request {} to server {} timed out", method, self.name))
+            }
+        }
+    }
+
+    /// Sends a `message` request for a session. The reply arrives later as
+    /// `Inbound::Reply`, or `Inbound::ReplyTimeout` if there is none within
+    /// `timeout`. Both also reach `waiter`. Returns the request id.
+    pub async fn send(&self, session_id: &str, message: Value, timeout: Duration, waiter: Option<ReplyWaiter>) -> Result<String> {
+        let id = Uuid::new_v4().to_string();
+        self.send_request(&id, "message", Some(session_id), message, Pending::Session(session_id.to_string(), waiter)).await?;
+
+        let (pending, inbound, request_id) = (self.pending.clone(), self.inbound.clone(), id.clone());
+        tokio::spawn(async move {
+            tokio::time::sleep(timeout).await;
+            // Still pending: a later reply is ignored
+            if let Some(Pending::Session(session_id, waiter)) = pending.lock().await.remove(&request_id) {
+                let _ = inbound.send(Inbound::ReplyTimeout { id: request_id, session_id, waiter }).await;
+            }
+        });
+        Ok(id)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.writer.is_closed()
+    }
+}
+
+async fn read_loop<R>(
+    name: String,
+    events: BTreeMap<String, Value>,
+    mut lines: Lines<BufReader<R>>,
+    pending: PendingMap,
+    writer: mpsc::Sender<String>,
+    inbound: mpsc::Sender<Inbound>,
+) where
+    R: AsyncRead + Unpin,
+{
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() { continue; }
+        let frame = match serde_json::from_str::<Frame>(&line) {
+            Ok(frame) => frame,
+            Err(e) => {
+                tracing::warn!(server = %name, error = %e, "Invalid frame from server.");
+                let _ = writer.send(Frame::error(None, error_code::INVALID_FRAME, e.to_string()).to_line()).await;
+                continue;
+            }
+        };
+        match frame {
+            Frame::Event { event, data } => {
+                // Servers may only emit the event types declared in their hello
+                if !events.is_empty() && !events.contains_key(&event) {
+                    tracing::warn!(server = %name, event = %event, "Dropping undeclared event type.");
+                    let _ = writer.send(Frame::error(None, error_code::UNKNOWN_EVENT, format!("Event type '{}' was not declared in hello", event)).to_line()).await;
+                    continue;
+                }
+                let _ = inbound.send(Inbound::Event { event, data }).await;
+            }
+            Frame::Reply { id, result } => resolve(&name, &pending, &inbound, id, Ok(result)).await,
+            Frame::Error { id: Some(id), code, message } => {
+                resolve(&name, &pending, &inbound, id, Err(anyhow!("Server {} error ({}): {}", name, code, message))).await
+            }
+            Frame::Error { id: None, code, message } => {
+                tracing::warn!(server = %name, code = %code, message = %message, "Server reported a protocol error.");
+            }
+            Frame::Request { id, method, .. } => {
+                let _ = writer.send(Frame::error(Some(id), error_code::UNKNOWN_METHOD, format!("Ruster does not handle '{}'", method)).to_line()).await;
+            }
+            Frame::Hello(_) => {
+                let _ = writer.send(Frame::error(None, error_code::UNEXPECTED_FRAME, "Handshake already completed").to_line()).await;
+            }
+        }
+    }
+    tracing::info!(server = %name, "Server connection closed.");
+    let unanswered: Vec<(String, Pending)> = pending.lock().await.drain().collect();
+    for (id, p) in unanswered {
+        let result = Err(anyhow!("Server {} disconnected", name));
+        match p {
+            Pending::Waiter(sender) => { let _ = sender.send(result); }
+            Pending::Session(session_id, waiter) => { let _ = inbound.send(Inbound::Reply { id, session_id, result, waiter }).await; }
+        }
+    }
+    let _ = inbound.send(Inbound::Closed).await;
+}
+
+async fn resolve(name: &str, pending: &PendingMap, inbound: &mpsc::Sender<Inbound>, id: String, result: Result<Value>) {
+    match pending.lock().await.remove(&id) {
+        Some(Pending::Waiter(sender)) => { let _ = sender.send(result); }
+        Some(Pending::Session(session_id, waiter)) => { let _ = inbound.send(Inbound::Reply { id, session_id, result, waiter }).await; }
+        None => tracing::warn!(server = %name, id = %id, "Reply to unknown request."),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerInfo {
+    pub name: String,
+    pub socket_path: String,
+    /// Filled in from the server's hello on connect
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub protocol: Option<u32>,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    #[serde(default)]
+    pub events: BTreeMap<String, Value>,
+    #[serde(default)]
+    pub tools: Vec<ToolSpec>,
+    /// The socket is present and the server not known to be down
+    #[serde(default)]
+    pub up: bool,
+    #[serde(default)]
+    pub connected: bool,
+}
+
+/// Servers whose availability changed in a scan.
+#[derive(Debug, Default)]
+pub struct ScanChanges {
+    pub up: Vec<String>,
+    pub down: Vec<String>,
+}
+
+pub struct ServerRegistry {
+    pub servers: RwLock<HashMap<String, ServerInfo>>,
+    connections: RwLock<HashMap<String, Arc<ServerConnection>>>,
+    /// One lock per server name, so a server is never connected twice while
+    /// a slow handshake does not hold up other servers
+    connecting: Mutex<HashMap<String, Arc<Mutex<()>>>>,
+    registry_path: PathBuf,
+    discovery_dir: PathBuf,
+}
+
+impl ServerRegistry {
+    pub fn new(config_dir: &Path, discovery_dir: PathBuf) -> Self {
+        let registry_path = config_dir.join("servers.json");
+        let mut servers: HashMap<String, ServerInfo> = std::fs::read_to_string(&registry_path).ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        for info in servers.values_mut() {
+            info.up = false;
+            info.connected = false;
+        }
+        Self {
+            servers: RwLock::new(servers),
+            connections: RwLock::new(HashMap::new()),
+            connecting: Mutex::new(HashMap::new()),
+            registry_path,
+            discovery_dir,
+        }
+    }
+
+    pub fn discovery_dir(&self) -> &Path {
+        &self.discovery_dir
+    }
+
+    /// Adds servers whose socket appeared and drops unconnected ones whose socket is gone.
+    pub async fn scan_and_update(&self) -> Result<ScanChanges> {
+        let mut found = HashMap::new();
+        for entry in std::fs::read_dir(&self.discovery_dir)?.flatten() {
+            if let Some(name) = server_name(&entry.file_name().to_string_lossy()) {
+                found.insert(name.to_string(), entry.path());
+            }
+        }
+
+        let mut changes = ScanChanges::default();
+        let mut servers = self.servers.write().await;
+        servers.retain(|name, info| {
+            if found.contains_key(name) {
+                return true;
+            }
+            if info.up {
+                info.up = false;
+                changes.down.push(name.clone());
+            }
+            info.connected
+        });
+        for (name, path) in found {
+            let info = servers.entry(name.clone()).or_insert_with(|| {
+                tracing::info!(server = %name, path = ?path, "Discovered server.");
+                ServerInfo {
+                    name: name.clone(),
+                    socket_path: path.to_string_lossy().to_string(),
+                    version: String::new(),
+                    protocol: None,
+                    capabilities: Vec::new(),
+                    events: BTreeMap::new(),
+                    tools: Vec::new(),
+                    up: false,
+                    connected: false,
+                }
+            });
+            if !info.up {
+                info.up = true;
+                changes.up.push(name);
+            }
+        }
+        Ok(changes)
+    }
+
+    pub async fn save(&self) -> Result<()> {
+        let servers = self.servers.read().await;
+        if let Some(parent) = self.registry_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.registry_path, serde_json::to_string_pretty(&*servers)?)?;
+        Ok(())
+    }
+
+    /// Connects and handshakes with a server unless already connected.
+    pub async fn connect_to_server(&self, name: &str, sm: Arc<SessionManager>) -> Result<()> {
+        let lock = self.connecting.lock().await.entry(name.to_string()).or_default().clone();
+        let _connecting = lock.lock().await;
+        if self.connections.read().await.get(name).is_some_and(|c| !c.is_closed()) {
+            return Ok(());
+        }
+        let socket_path = {
+            let servers = self.servers.read().await;
+            servers.get(name).map(|s| s.socket_path.clone())
+                .ok_or_else(|| anyhow!("Server {} not found", name))?
+        };
+
+        let stream = UnixStream::connect(&socket_path).await
+            .map_err(|e| anyhow!("Failed to connect to server {}: {}", name, e))?;
+        let (reader, writer) = stream.into_split();
+        let (conn, mut inbound) = ServerConnection::handshake(name, reader, writer).await?;
+        tracing::info!(server = %name, version = %conn.hello.version, capabilities = ?conn.hello.capabilities, "Connected to server.");
+
+        let mut came_up = false;
+        if let Some(info) = self.servers.write().await.get_mut(name) {
+            info.version = conn.hello.version.clone();
+            info.protocol = Some(conn.hello.protocol);
+            info.capabilities = conn.hello.capabilities.clone();
+            info.events = conn.hello.events.clone();
+            info.tools = conn.hello.tools.clone();
+            info.connected = true;
+            came_up = !info.up;
+            info.up = true;
+        }
+        let conn = Arc::new(conn);
+        let current = Arc::downgrade(&conn);
+        self.connections.write().await.insert(name.to_string(), conn);
+        if came_up {
+            let _ = sm.event_sender.send(json!({"event": "server_up", "server_name": name, "socket_path": socket_path}));
+        }
+
+        let server_name = name.to_string();
+        tokio::spawn(async move {
+            while let Some(msg) = inbound.recv().await {
+                let res = match msg {
+                    Inbound::Event { event, data } => {
+                        sm.handle_server_event(&server_name, json!({"event": event, "data": data})).await
+                    }
+                    Inbound::Reply { id, session_id, result, waiter } => {
+                        let reply = result.unwrap_or_else(|e| json!({"error": e.to_string()}));
+                        tracing::debug!(server = %server_name, id = %id, "Server replied.");
+                        sm.handle_server_reply(&session_id, &server_name, &id, reply).await
+                            .map(|event| notify(waiter, event))
+                    }
+                    Inbound::ReplyTimeout { id, session_id, waiter } => {
+                        tracing::warn!(server = %server_name, id = %id, "Server did not reply in time.");
+                        sm.handle_server_reply_timeout(&session_id, &server_name, &id).await
+                            .map(|event| notify(waiter, event))
+                    }
+                    Inbound::Closed => break,
+                };
+                if let Err(e) = res {
+                    tracing::error!(server = %server_name, error = %e, "Failed to handle server message.");
+                }
+            }
+            if sm.server_registry.disconnected(&server_name, &current).await {
+                let _ = sm.event_sender.send(json!({"event": "server_down", "server_name": server_name, "reason": "disconnected"}));
+            }
+            spawn_reconnect(sm, server_name);
+        });
+        Ok(())
+    }
+
+    /// Forgets a closed connection. Returns true if the server was up. Does
+    /// nothing if `conn` was already replaced by a newer connection.
+    async fn disconnected(&self, name: &str, conn: &std::sync::Weak<ServerConnection>) -> bool {
+        {
+            let mut connections = self.connections.write().await;
+            if !connections.get(name).is_some_and(|c| std::ptr::eq(Arc::as_ptr(c), conn.as_ptr())) {
+                return false;
+            }
+            connections.remove(name);
+        }
+        match self.servers.write().await.get_mut(name) {
+            Some(info) => {
+                info.connected = false;
+                std::mem::replace(&mut info.up, false)
+            }
+            None => false,
+        }
+    }
+
+    pub async fn is_connected(&self, name: &str) -> bool {
+        self.connections.read().await.get(name).is_some_and(|c| !c.is_closed())
+    }
+
+    async fn connection(&self, name: &str) -> Result<Arc<ServerConnection>> {
+        self.connections.read().await.get(name).cloned()
+            .ok_or_else(|| anyhow!("Server {} is not connected", name))
+    }
+
+    /// Tools of the given servers that are connected, named `<server>__<tool>`.
+    pub async fn tools(&self, names: &[&String]) -> Vec<Tool> {
+        let connections = self.connections.read().await;
+        let mut tools = Vec::new();
+        for name in names {
+            let Some(conn) = connections.get(*name) else { continue };
+            for t in &conn.hello.tools {
+                tools.push(Tool {
+                    name: format!("{}{}{}", name, crate::mcp::TOOL_SEPARATOR, t.name),
+                    description: t.description.clone(),
+                    parameters: if t.parameters.is_null() { json!({"type": "object", "properties": {}}) } else { t.parameters.clone() },
+                    exec: None,
+                    working_dir: None,
+                    mcp_server: None,
+                    server: Some(name.to_string()),
+                    output: ToolOutput::Text,
+                });
+            }
+        }
+        tools
+    }
+
+    /// Calls a server tool on behalf of a session and returns its result as text.
+    pub async fn call_tool(&self, name: &str, exposed_name: &str, session_id: &str, arguments: Value, timeout: Duration) -> Result<String> {
+        let tool = exposed_name
+            .strip_prefix(&format!("{}{}", name, crate::mcp::TOOL_SEPARATOR))
+            .unwrap_or(exposed_name);
+        let params = json!({"name": tool, "arguments": arguments});
+        match self.connection(name).await?.request("tool_call", Some(session_id), params, timeout).await? {
+            Value::String(s) => Ok(s),
+            other => Ok(serde_json::to_string_pretty(&other)?),
+        }
+    }
+
+    /// Forwards a session's message to a server. Returns the request id.
+    pub async fn send_message(&self, name: &str, session_id: &str, msg: Value, timeout: Duration, waiter: Option<ReplyWaiter>) -> Result<String> {
+        self.connection(name).await?.send(session_id, msg, timeout, waiter).await
+    }
+}
+
+fn notify(waiter: Option<ReplyWaiter>, event: Value) {
+    if let Some(waiter) = waiter {
+        let _ = waiter.send(event);
+    }
+}
+
+/// The server name in a socket file name, if it follows `ruster-srv-<name>.sock`.
+pub fn server_name(file_name: &str) -> Option<&str> {
+    file_name.strip_prefix(SOCKET_PREFIX)
+        .and_then(|s| s.strip_suffix(SOCKET_SUFFIX))
+        .filter(|name| !name.is_empty())
+}
+
+/// Keeps reconnecting to a lost server, with backoff, while a session is attached to it.
+fn spawn_reconnect(sm: Arc<SessionManager>, name: String) {
+    tokio::spawn(async move {
+        let mut delay = RECONNECT_DELAY;
+        loop {
+            tokio::time::sleep(delay).await;
+            if !sm.is_server_attached(&name).await || sm.server_registry.is_connected(&name).await {
+                return;
+            }
+            match sm.server_registry.connect_to_server(&name, sm.clone()).await {
+                Ok(()) => {
+                    tracing::info!(server = %name, "Reconnected to server.");
+                    return;
+                }
+                Err(e) => tracing::debug!(server = %name, error = %e, retry_in = ?delay, "Reconnect failed."),
+            }
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+    });
+}
+
+/// Watches the discovery directory and keeps the registry and connections current.
+/// The directory is rescanned at least every `POLL_INTERVAL`, and watched
+/// again if it is removed and recreated.
+pub async fn start_discovery_loop(sm: Arc<SessionManager>) {
+    let registry = sm.server_registry.clone();
+    let dir = registry.discovery_dir().to_path_buf();
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        tracing::warn!(dir = ?dir, error = %e, "Cannot create server directory.");
+    }
+
+    let mut watcher = None;
+    let mut warned = false;
+    loop {
+        if watcher.is_none() {
+            match Inotify::new().and_then(|w| w.add_watch(&dir, DISCOVERY_MASK).map(|_| w)) {
+                Ok(w) => {
+                    watcher = Some(w);
+                    warned = false;
+                }
+                Err(e) if !warned => {
+                    tracing::warn!(dir = ?dir, error = %e, "Cannot watch server directory; polling instead.");
+                    warned = true;
+                }
+                Err(_) => {}
+            }
+        }
+
+        match registry.scan_and_update().await {
+            Ok(changes) => apply_changes(&sm, changes).await,
+            Err(e) => tracing::error!("Server discovery error: {}", e),
+        }
+
+        let Some(w) = &watcher else {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            continue;
+        };
+        let deadline = tokio::time::Instant::now() + POLL_INTERVAL;
+        let watch_lost = loop {
+            match tokio::time::timeout_at(deadline, w.read_events()).await {
+                Err(_) => break false,
+                Ok(Ok(events)) => {
+                    if events.iter().any(|e| e.mask & inotify::IN_IGNORED != 0) {
+                        break true;
+                    }
+                    // A queue overflow means events were lost, so rescan
+                    let changed = events.iter().any(|e| {
+                        e.mask & inotify::IN_Q_OVERFLOW != 0
+                            || e.name.as_ref().is_some_and(|n| server_name(&n.to_string_lossy()).is_some())
+                    });
+                    if changed {
+                        break false;
+                    }
+                }
+                Ok(Err(e)) => {
+                    tracing::error!(error = %e, "Server directory watcher failed.");
+                    tokio::time::sleep(POLL_INTERVAL).await;
+                    break true;
+                }
+            }
+        };
+        if watch_lost {
+            tracing::info!(dir = ?dir, "Server directory watch lost; watching again once it exists.");
+            watcher = None;
+        }
+    }
+}
+
+async fn apply_changes(sm: &Arc<SessionManager>, changes: ScanChanges) {
+    for name in changes.down {
+        tracing::info!(server = %name, "Server socket removed.");
+        let _ = sm.event_sender.send(json!({"event": "server_down", "server_name": name, "reason": "socket_removed"}));
+    }
+    for name in changes.up {
+        let socket_path = socket_path(sm.server_registry.discovery_dir(), &name);
+        let _ = sm.event_sender.send(json!({"event": "server_up", "server_name": name, "socket_path": socket_path}));
+        // Sessions that stayed attached get their server back right away
+        if sm.is_server_attached(&name).await && !sm.server_registry.is_connected(&name).await {
+            let sm = sm.clone();
+            tokio::spawn(async move {
+                // The socket exists slightly before the server listens on it
+                for _ in 0..5 {
+                    match sm.server_registry.connect_to_server(&name, sm.clone()).await {
+                        Ok(()) => return,
+                        Err(e) => tracing::debug!(server = %name, error = %e, "Connect after discovery failed."),
+                    }
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Conforming server: answers the handshake, emits a declared event and
+    /// replies to requests in reverse order to exercise correlation.
+    async fn fake_server(stream: tokio::io::DuplexStream, protocol: u32) {
+        let (reader, mut writer) = tokio::io::split(stream);
+        let mut lines = BufReader::new(reader).lines();
+        let send = |frame: Frame| frame.to_line();
+
+        let hello: Frame = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert!(matches!(hello, Frame::Hello(Hello { protocol: PROTOCOL_VERSION, .. })));
+        let ours = Hello {
+            protocol,
+            name: "fake".to_string(),
+            version: "0.1.0".to_string(),
+            capabilities: vec!["events".to_string(), "requests".to_string()],
+            events: BTreeMap::from([("cpu".to_string(), json!({"type": "object"}))]),
+            tools: Vec::new(),
+        };
+        writer.write_all(send(Frame::Hello(ours)).as_bytes()).await.unwrap();
+
+        writer.write_all(send(Frame::Event { event: "undeclared".to_string(), data: json!({}) }).as_bytes()).await.unwrap();
+        writer.write_all(send(Frame::Event { event: "cpu".to_string(), data: json!({"load": 0.5}) }).as_bytes()).await.unwrap();
+
+        let mut held = Vec::new();
+        while let Ok(Some(line)) = lines.next_line().await {
+            match serde_json::from_str::<Frame>(&line).unwrap() {
+                Frame::Request { id, method, params, .. } => {
+                    let reply = match method.as_str() {
+                        "echo" => Frame::Reply { id, result: params },
+                        "message" => Frame::Reply { id, result: json!({"ok": true}) },
+                        _ => Frame::error(Some(id), error_code::UNKNOWN_METHOD, method),
+                    };
+                    held.push(reply);
+                    if held.len() == 2 {
+                        for reply in held.drain(..).rev() {
+                            writer.write_all(send(reply).as_bytes()).await.unwrap();
+                        }
+                    }
+                }
+                // Ruster rejects the undeclared event
+                Frame::Error { code, .. } => assert_eq!(code, error_code::UNKNOWN_EVENT),
+                other => panic!("Unexpected frame {:?}", other),
+            }
+        }
+    }
+
+    async fn connect(protocol: u32) -> Result<(Arc<ServerConnection>, mpsc::Receiver<Inbound>)> {
+        let (client, server) = tokio::io::duplex(4096);
+        tokio::spawn(fake_server(server, protocol));
+        let (reader, writer) = tokio::io::split(client);
+        ServerConnection::handshake("fake", reader, writer).await.map(|(c, rx)| (Arc::new(c), rx))
+    }
+
+    #[test]
+    fn test_frame_wire_format() {
+        let frame: Frame = serde_json::from_str(r#"{"type":"event","event":"cpu","data":{"load":1}}"#).unwrap();
+        assert_eq!(frame, Frame::Event { event: "cpu".to_string(), data: json!({"load": 1}) });
+        let err = serde_json::to_value(Frame::error(None, error_code::INVALID_FRAME, "bad")).unwrap();
+        assert_eq!(err, json!({"type": "error", "code": "invalid_frame", "message": "bad"}));
+        assert!(serde_json::from_str::<Frame>(r#"{"type":"reply"}"#).is_err());
+    }
+
+    #[test]
+    fn test_attachments_saved_with_filter_and_legacy_mode() {
+        let mut attachment = Attachment::new(EventDeliveryMode::Immediate);
+        attachment.filter.events = vec!["cpu".to_string()];
+        attachment.template = Some("load {$.data.load}".to_string());
+        attachment.rate_limit = RateLimit { rate: 0.5, burst: 2, overflow: OverflowPolicy::Coalesce };
+        attachment.coalesced = 7;
+        let saved = toml::to_string(&HashMap::from([("mon".to_string(), attachment.clone())])).unwrap();
+        let loaded: HashMap<String, Attachment> = toml::from_str(&saved).unwrap();
+        assert_eq!(loaded["mon"], attachment);
+
+        let legacy: HashMap<String, Attachment> = toml::from_str("mon = \"proactive\"").unwrap();
+        assert_eq!(legacy["mon"], Attachment::new(EventDeliveryMode::Proactive));
+
+        assert_eq!(attachment.admit(&json!({"event": "cpu", "data": {"load": 2}})), Some(json!("load 2")));
+        assert_eq!(attachment.admit(&json!({"event": "disk", "data": {}})), None);
+    }
+
+    #[tokio::test]
+    async fn test_handshake_events_and_correlation() {
+        let (conn, mut inbound) = connect(PROTOCOL_VERSION).await.unwrap();
+        assert_eq!(conn.hello.name, "fake");
+        assert!(conn.hello.events.contains_key("cpu"));
+
+        // The undeclared event is rejected, the declared one delivered
+        match inbound.recv().await.unwrap() {
+            Inbound::Event { event, data } => {
+                assert_eq!(event, "cpu");
+                assert_eq!(data, json!({"load": 0.5}));
+            }
+            other => panic!("Unexpected {:?}", other),
+        }
+
+        // Replies come back in reverse order and still reach the right caller
+        let a = { let c = conn.clone(); tokio::spawn(async move { c.request("echo", None, json!("a"), Duration::from_secs(5)).await }) };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let b = { let c = conn.clone(); tokio::spawn(async move { c.request("echo", None, json!("b"), Duration::from_secs(5)).await }) };
+        assert_eq!(a.await.unwrap().unwrap(), json!("a"));
+        assert_eq!(b.await.unwrap().unwrap(), json!("b"));
+
+        // Session messages are answered through the inbound channel; errors carry their code
+        let id = conn.send("s1", json!({"text": "hi"}), Duration::from_secs(5), None).await.unwrap();
+        let failed = { let c = conn.clone(); tokio::spawn(async move { c.request("nope", None, json!(null), Duration::from_secs(5)).await }) };
+        let err = failed.await.unwrap().unwrap_err().to_string();
+        assert!(err.contains("unknown_method"), "{}", err);
+        match inbound.recv().await.unwrap() {
+            Inbound::Reply { id: reply_id, session_id, result, .. } => {
+                assert_eq!(reply_id, id);
+                assert_eq!(session_id, "s1");
+                assert_eq!(result.unwrap(), json!({"ok": true}));
+            }
+            other => panic!("Unexpected {:?}", other),
+        }
+
+        // The fake holds a lone request back, so it times out
+        let (waiter, _reply) = oneshot::channel();
+        let id = conn.send("s1", json!({"text": "anyone?"}), Duration::from_millis(50), Some(waiter)).await.unwrap();
+        match inbound.recv().await.unwrap() {
+            Inbound::ReplyTimeout { id: timed_out, session_id, waiter } => {
+                assert_eq!(timed_out, id);
+                assert_eq!(session_id, "s1");
+                assert!(waiter.is_some());
+            }
+            other => panic!("Unexpected {:?}", other),
+        }
+    }
+
+    /// Server advertising an `uptime` tool and a `hang` tool that never answers.
+    async fn tool_server(stream: tokio::io::DuplexStream) {
+        let (reader, mut writer) = tokio::io::split(stream);
+        let mut lines = BufReader::new(reader).lines();
+        lines.next_line().await.unwrap();
+        let hello = Hello {
+            protocol: PROTOCOL_VERSION,
+            name: "mon".to_string(),
+            capabilities: vec!["tools".to_string()],
+            tools: vec![
+                ToolSpec { name: "uptime".to_string(), description: "Seconds since boot".to_string(), parameters: Value::Null },
+                ToolSpec { name: "hang".to_string(), ..Default::default() },
+            ],
+            ..Default::default()
+        };
+        writer.write_all(Frame::Hello(hello).to_line().as_bytes()).await.unwrap();
+        while let Ok(Some(line)) = lines.next_line().await {
+            if let Frame::Request { id, method, session_id, params } = serde_json::from_str(&line).unwrap() {
+                assert_eq!(method, "tool_call");
+                assert_eq!(session_id.as_deref(), Some("s1"));
+                if params["name"] == "uptime" {
+                    let result = json!({"seconds": params["arguments"]["scale"].as_u64().unwrap() * 42});
+                    writer.write_all(Frame::Reply { id, result }.to_line().as_bytes()).await.unwrap();
+                }
+            }
+        }
+    }
+
+    async fn tool_connection() -> Arc<ServerConnection> {
+        let (client, server) = tokio::io::duplex(4096);
+        tokio::spawn(tool_server(server));
+        let (reader, writer) = tokio::io::split(client);
+        Arc::new(ServerConnection::handshake("mon", reader, writer).await.unwrap().0)
+    }
+
+    #[tokio::test]
+    async fn test_server_tools_are_routed_by_request() {
+        let registry = ServerRegistry::new(&std::env::temp_dir().join(format!("ruster-srv-{}", Uuid::new_v4())), std::env::temp_dir());
+        let old = tool_connection().await;
+        let conn = tool_connection().await;
+        registry.connections.write().await.insert("mon".to_string(), conn.clone());
+
+        // A late notice about a replaced connection leaves the current one alone
+        assert!(!registry.disconnected("mon", &Arc::downgrade(&old)).await);
+        assert!(registry.is_connected("mon").await);
+
+        let name = "mon".to_string();
+        let tools = registry.tools(&[&name, &"absent".to_string()]).await;
+        assert_eq!(tools.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(), ["mon__uptime", "mon__hang"]);
+        assert_eq!(tools[0].server.as_deref(), Some("mon"));
+        assert_eq!(tools[0].parameters, json!({"type": "object", "properties": {}}));
+
+        let out = registry.call_tool("mon", "mon__uptime", "s1", json!({"scale": 2}), Duration::from_secs(5)).await.unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&out).unwrap(), json!({"seconds": 84}));
+
+        let err = registry.call_tool("mon", "mon__hang", "s1", json!({}), Duration::from_millis(100)).await.unwrap_err();
+        assert!(err.to_string().contains("timed out"), "{}", err);
+        assert!(registry.call_tool("absent", "absent__x", "s1", json!({}), Duration::from_secs(1)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_scan_reports_sockets_coming_and_going() {
+        let dir = crate::test_util::TempDir::new("disc");
+        std::fs::write(dir.join("unrelated.sock"), "").unwrap();
+        let registry = ServerRegistry::new(&dir.join("config"), dir.to_path_buf());
+
+        let listener = tokio::net::UnixListener::bind(socket_path(&dir, "mon")).unwrap();
+        let changes = registry.scan_and_update().await.unwrap();
+        assert_eq!((changes.up, changes.down), (vec!["mon".to_string()], vec![]));
+        assert!(registry.scan_and_update().await.unwrap().up.is_empty());
+
+        drop(listener);
+        std::fs::remove_file(socket_path(&dir, "mon")).unwrap();
+        let changes = registry.scan_and_update().await.unwrap();
+        assert_eq!((changes.up, changes.down), (vec![], vec!["mon".to_string()]));
+        assert!(registry.servers.read().await.is_empty());
+
+        assert_eq!(server_name("ruster-srv-mon.sock"), Some("mon"));
+        assert_eq!(server_name("ruster-srv-.sock"), None);
+    }
+
+    #[tokio::test]
+    async fn test_handshake_rejects_other_versions() {
+        let err = connect(PROTOCOL_VERSION + 1).await.err().unwrap().to_string();
+        assert!(err.contains("not supported"), "{}", err);
+    }
+}
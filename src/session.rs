@@ -10,9 +10,13 @@ use serde_json::{json, Value};
 use chrono::Local;
 use anyhow::{Result, anyhow};
 use crate::config::Config;
-use crate::llm::LlmClient;
-use crate::skills::{SkillsManager, Skill};
-use crate::servers::{EventDeliveryMode, ServerRegistry};
+use crate::llm::{LlmClient, ToolOutput};
+use crate::skills::{SkillsManager, Skill, RetrievalOptions, ToolPolicy};
+use crate::mcp::McpManager;
+use crate::fs_tools::Workspace;
+use crate::servers::{Attachment, EventDeliveryMode, OverflowPolicy, RateLimit, ServerRegistry};
+use crate::supervisor::Supervisor;
+use crate::event_log::EventLog;
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Message {
@@ -23,23 +27,60 @@ pub struct Message {
     pub skills: Vec<String>,
 }
 
+/// The system prompt carrying the instructions of the active skills.
+pub fn system_prompt(skills: &[Skill]) -> String {
+    let mut system_prompt = String::from("You are Ruster, a persistent, proactive LLM agent.\n");
+    if !skills.is_empty() {
+        system_prompt.push_str("\n# Enabled Skills:\n");
+        for skill in skills {
+            system_prompt.push_str(&format!("## {}\n{}\n", skill.metadata.name, skill.instructions));
+        }
+    }
+    system_prompt
+}
+
 pub struct RateLimiter {
     pub tokens: f64,
     pub last_update: std::time::Instant,
+    pub limit: RateLimit,
+    /// Events held back under the coalesce policy: their count and the latest one
+    pub coalescing: Option<(u64, Value)>,
 }
 
 impl RateLimiter {
-    pub fn new() -> Self {
+    pub fn new(limit: RateLimit) -> Self {
         Self {
-            tokens: 10.0,
+            tokens: limit.burst as f64,
             last_update: std::time::Instant::now(),
+            limit,
+            coalescing: None,
+        }
+    }
+
+    /// Time until the next token is available.
+    pub fn wait(&self) -> std::time::Duration {
+        std::time::Duration::from_secs_f64((1.0 - self.tokens).max(0.0) / self.limit.rate)
+    }
+
+    /// Holds back an event. Returns true if it starts a new batch.
+    pub fn coalesce(&mut self, event: Value) -> bool {
+        match &mut self.coalescing {
+            Some((count, latest)) => {
+                *count += 1;
+                *latest = event;
+                false
+            }
+            None => {
+                self.coalescing = Some((1, event));
+                true
+            }
         }
     }
 
     pub fn check(&mut self) -> bool {
         let now = std::time::Instant::now();
         let elapsed = now.duration_since(self.last_update).as_secs_f64();
-        self.tokens = (self.tokens + elapsed * 5.0).min(10.0);
+        self.tokens = (self.tokens + elapsed * self.limit.rate).min(self.limit.burst as f64);
         self.last_update = now;
 
         if self.tokens >= 1.0 {
@@ -55,17 +96,36 @@ pub struct Session {
     pub id: String,
     pub history: Vec<Message>,
     pub active_skills: Vec<String>,
-    pub attached_servers: HashMap<String, EventDeliveryMode>,
+    /// Turn each RAG-selected skill was last relevant in. Active skills
+    /// missing here (manual or `initial_skills`) are pinned.
+    pub skill_last_relevant: HashMap<String, usize>,
+    /// Number of user turns so far
+    pub turn: usize,
+    pub attached_servers: HashMap<String, Attachment>,
     pub pending_events: Vec<serde_json::Value>,
+    /// Every server event delivered to the session, for replay and restart
+    pub event_log: EventLog,
+    /// Held for the duration of a turn so user and server-initiated turns
+    /// run one at a time, in arrival order
+    pub turn_queue: Arc<tokio::sync::Mutex<()>>,
+    /// Feeds immediate server events, in arrival order, to the session's
+    /// server turn worker. Started with the first such event.
+    pub server_turns: Option<tokio::sync::mpsc::UnboundedSender<(String, Value)>>,
     pub rate_limiters: HashMap<String, RateLimiter>,
+    /// Overflow counters changed since `attached_servers` was last saved
+    pub counters_dirty: bool,
     pub model: String, // provider/model
     pub memory_dir: PathBuf,
     pub history_file: PathBuf,
     pub activity_file: PathBuf,
     pub attached_servers_file: PathBuf,
+    pub workspace: Workspace,
+    pub workspace_file: PathBuf,
     pub skills_manager: Arc<RwLock<SkillsManager>>,
     pub llm_client: LlmClient,
     pub config: Arc<RwLock<Config>>,
+    pub mcp_manager: Arc<McpManager>,
+    pub server_registry: Arc<ServerRegistry>,
 }
 
 impl Session {
@@ -74,6 +134,8 @@ impl Session {
         config: Arc<RwLock<Config>>,
         skills_manager: Arc<RwLock<SkillsManager>>,
         llm_client: LlmClient,
+        mcp_manager: Arc<McpManager>,
+        server_registry: Arc<ServerRegistry>,
         model_override: Option<String>,
     ) -> Result<Self> {
         tracing::info!(session_id = %id, "Initializing session");
@@ -84,6 +146,7 @@ impl Session {
         let history_file = base_dir.join("history.jsonl");
         let activity_file = base_dir.join("activity.log");
         let attached_servers_file = base_dir.join("attached_servers.toml");
+        let workspace_file = base_dir.join("workspace.toml");
         
         let history: Vec<Message> = if history_file.exists() {
             tracing::debug!(session_id = %id, "Loading history from {:?}", history_file);
@@ -96,13 +159,26 @@ impl Session {
             Vec::new()
         };
 
-        let attached_servers: HashMap<String, EventDeliveryMode> = if attached_servers_file.exists() {
+        let attached_servers: HashMap<String, Attachment> = if attached_servers_file.exists() {
             let content = fs::read_to_string(&attached_servers_file)?;
             toml::from_str(&content).unwrap_or_default()
         } else {
             HashMap::new()
         };
 
+        let workspace = Workspace::load(&workspace_file);
+
+        // Next-turn events that never reached a turn before the last shutdown
+        let mut event_log = EventLog::open(&base_dir)?;
+        let (max_age, max_bytes) = event_retention(&*config.read().await);
+        if let Err(e) = event_log.prune(max_age, max_bytes) {
+            tracing::warn!(session_id = %id, error = %e, "Failed to prune server events.");
+        }
+        let pending_events: Vec<Value> = event_log.undelivered()?.into_iter().map(|e| e.content).collect();
+        if !pending_events.is_empty() {
+            tracing::info!(session_id = %id, count = %pending_events.len(), "Restored undelivered server events");
+        }
+
         let active_skills = {
             let cfg = config.read().await;
             cfg.initial_skills.clone()
@@ -123,55 +199,109 @@ impl Session {
             id,
             history,
             active_skills,
+            skill_last_relevant: HashMap::new(),
+            turn: 0,
             attached_servers,
-            pending_events: Vec::new(),
+            pending_events,
+            event_log,
+            turn_queue: Arc::new(tokio::sync::Mutex::new(())),
+            server_turns: None,
             rate_limiters: HashMap::new(),
+            counters_dirty: false,
             model,
             memory_dir: base_dir.join("memory"),
             history_file,
             activity_file,
             attached_servers_file,
+            workspace,
+            workspace_file,
             skills_manager,
             llm_client,
             config,
+            mcp_manager,
+            server_registry,
         })
     }
 
-    pub fn save_attachments(&self) -> Result<()> {
+    pub fn save_attachments(&mut self) -> Result<()> {
         let content = toml::to_string(&self.attached_servers)?;
         fs::write(&self.attached_servers_file, content)?;
+        self.counters_dirty = false;
         Ok(())
     }
 
-    pub fn attach_server(&mut self, name: String, mode: EventDeliveryMode) -> Result<()> {
-        self.attached_servers.insert(name, mode);
+    /// Saves the attachments if their overflow counters changed.
+    pub fn save_counters(&mut self) -> Result<()> {
+        if self.counters_dirty {
+            self.save_attachments()?;
+        }
+        Ok(())
+    }
+
+    /// Attaches or re-attaches a server. Counters of a previous attachment are kept.
+    pub fn attach_server(&mut self, name: String, mut attachment: Attachment) -> Result<()> {
+        if let Some(old) = self.attached_servers.get(&name) {
+            attachment.dropped = old.dropped;
+            attachment.coalesced = old.coalesced;
+        }
+        self.rate_limiters.remove(&name);
+        self.attached_servers.insert(name, attachment);
         self.save_attachments()
     }
 
+    /// Counts an event the rate limit dropped or held back. The counters are
+    /// saved by `save_counters`, not on every event.
+    pub fn record_overflow(&mut self, server_name: &str, policy: OverflowPolicy) {
+        if let Some(attachment) = self.attached_servers.get_mut(server_name) {
+            match policy {
+                OverflowPolicy::Drop => attachment.dropped += 1,
+                OverflowPolicy::Coalesce => attachment.coalesced += 1,
+            }
+            self.counters_dirty = true;
+        }
+    }
+
     pub fn detach_server(&mut self, name: &str) -> Result<()> {
         self.attached_servers.remove(name);
+        self.rate_limiters.remove(name);
         self.save_attachments()
     }
 
+    pub fn add_workspace_root(&mut self, path: &str) -> Result<PathBuf> {
+        let root = self.workspace.add_root(path)?;
+        self.workspace.save(&self.workspace_file)?;
+        Ok(root)
+    }
+
+    pub fn remove_workspace_root(&mut self, path: &str) -> Result<()> {
+        let expanded = crate::config::expand_path(path);
+        let canonical = expanded.canonicalize().unwrap_or(expanded);
+        self.workspace.roots.retain(|r| *r != canonical);
+        self.workspace.save(&self.workspace_file)
+    }
+
     pub fn subscribe_server(&mut self, name: String, mode: EventDeliveryMode) -> Result<()> {
-        if self.attached_servers.contains_key(&name) {
-            self.attached_servers.insert(name, mode);
+        if let Some(attachment) = self.attached_servers.get_mut(&name) {
+            attachment.delivery = mode;
             self.save_attachments()
         } else {
             Err(anyhow!("Server not attached"))
         }
     }
 
-    pub async fn add_user_message(&mut self, content: String, _skills: Vec<String>) -> Result<()> {
-        let (rag_model, rag_top_n, rag_threshold, banned_skills) = {
+    /// Records the message and selects skills for it. Returns the RAG-selected
+    /// skills unloaded for having been irrelevant too long.
+    pub async fn add_user_message(&mut self, content: String, _skills: Vec<String>) -> Result<Vec<String>> {
+        let (retrieval, banned_skills, unload_after) = {
             let cfg = self.config.read().await;
-            (cfg.rag_model.clone(), cfg.rag_top_n, cfg.rag_threshold, cfg.banned_skills.clone())
+            (RetrievalOptions::from_config(&cfg), cfg.banned_skills.clone(), cfg.skill_unload_after_turns)
         };
+        self.turn += 1;
 
         // Select dynamic skills (RAG)
         let dynamic_skills = {
             let mut mgr = self.skills_manager.write().await;
-            mgr.select_skills(&content, &self.llm_client, &rag_model, rag_top_n, rag_threshold).await?
+            mgr.select_skills(&content, &self.llm_client, &retrieval).await?
         };
 
         let mut discovered_skills = Vec::new();
@@ -181,11 +311,16 @@ impl Session {
                 discovered_skills.push(name.clone());
                 if !self.active_skills.contains(&name) {
                     tracing::info!(session_id = %self.id, skill = %name, "Dynamically loading skill into session.");
-                    self.active_skills.push(name);
+                    self.active_skills.push(name.clone());
+                    self.skill_last_relevant.insert(name, self.turn);
+                } else {
+                    self.mark_skill_relevant(&name);
                 }
             }
         }
 
+        let unloaded = if unload_after > 0 { self.unload_idle_skills(unload_after) } else { Vec::new() };
+
         self.log_activity(&format!("User: {}", content))?;
         let msg = Message {
             role: "user".to_string(),
@@ -195,10 +330,84 @@ impl Session {
         };
         self.history.push(msg.clone());
         self.append_history(&msg)?;
-        Ok(())
+        Ok(unloaded)
+    }
+
+    /// Refreshes a RAG-selected skill; pinned skills are left alone.
+    pub fn mark_skill_relevant(&mut self, name: &str) {
+        if let Some(turn) = self.skill_last_relevant.get_mut(name) {
+            *turn = self.turn;
+        }
+    }
+
+    fn unload_idle_skills(&mut self, after_turns: usize) -> Vec<String> {
+        let current = self.turn;
+        let idle: Vec<String> = self.skill_last_relevant.iter()
+            .filter(|(_, last)| current - **last >= after_turns)
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in &idle {
+            tracing::info!(session_id = %self.id, skill = %name, idle_turns = %after_turns, "Unloading skill no longer relevant.");
+            self.skill_last_relevant.remove(name);
+            self.active_skills.retain(|s| s != name);
+        }
+        idle
+    }
+
+    /// Activates a skill the model asked for. It is tracked like a
+    /// RAG-selected skill. Returns false if it was already active.
+    pub fn activate_skill(&mut self, name: &str) -> bool {
+        if self.active_skills.iter().any(|s| s == name) {
+            self.mark_skill_relevant(name);
+            return false;
+        }
+        tracing::info!(session_id = %self.id, skill = %name, "Model activated skill.");
+        self.active_skills.push(name.to_string());
+        self.skill_last_relevant.insert(name.to_string(), self.turn);
+        true
     }
 
     pub async fn prepare_context(&mut self) -> Result<(Vec<serde_json::Value>, Vec<Skill>, Vec<crate::llm::Tool>)> {
+        let (skills, tools) = self.skills_and_tools().await;
+
+        if !skills.is_empty() {
+            let names: Vec<_> = skills.iter().map(|s| &s.metadata.name).collect();
+            tracing::info!(session_id = %self.id, "Activating skills: {:?}", names);
+        } else {
+            tracing::debug!(session_id = %self.id, "No relevant skills found for message");
+        }
+
+        let mut messages = Vec::new();
+
+        let system_prompt = system_prompt(&skills);
+        tracing::debug!("System prompt: '{}'", &system_prompt);
+        
+        messages.push(json!({"role": "system", "content": system_prompt}));
+        
+        // Add pending events as system messages and clear them
+        if !self.pending_events.is_empty() {
+            for event in self.pending_events.drain(..) {
+                 messages.push(json!({
+                     "role": "system",
+                     "content": format!("Background Event: {}", serde_json::to_string(&event).unwrap_or_default())
+                 }));
+            }
+            self.event_log.mark_delivered()?;
+        }
+
+        for msg in &self.history {
+            match msg.role.as_str() {
+                "user" | "assistant" | "system" | "tool" => messages.push(json!({"role": msg.role, "content": msg.content})),
+                // Server messages are stored under the server's name, which providers reject as a role
+                server => messages.push(json!({"role": "user", "content": format!("[Server {}]: {}", server, msg.content)})),
+            }
+        }
+
+        Ok((messages, skills, tools))
+    }
+
+    /// The active skills and every tool offered alongside them.
+    pub async fn skills_and_tools(&self) -> (Vec<Skill>, Vec<crate::llm::Tool>) {
         let mut skills = Vec::new();
         {
             let mgr = self.skills_manager.read().await;
@@ -213,6 +422,7 @@ impl Session {
         for skill in &skills {
             tools.extend(skill.metadata.tools.clone());
         }
+        let skill_tool_count = tools.len();
 
         // Add built-in pagination tool
         tools.push(crate::llm::Tool {
@@ -242,6 +452,9 @@ impl Session {
             }),
             exec: None, // Built-in
             working_dir: None,
+            mcp_server: None,
+            server: None,
+            output: ToolOutput::Text,
         });
 
         // Add run_skill_script tool
@@ -269,42 +482,51 @@ impl Session {
             }),
             exec: None, // Built-in logic in server.rs
             working_dir: None,
+            mcp_server: None,
+            server: None,
+            output: ToolOutput::Text,
         });
 
-        if !skills.is_empty() {
-            let names: Vec<_> = skills.iter().map(|s| &s.metadata.name).collect();
-            tracing::info!(session_id = %self.id, "Activating skills: {:?}", names);
-        } else {
-            tracing::debug!(session_id = %self.id, "No relevant skills found for message");
-        }
+        // Let the model find and activate skills RAG did not select
+        tools.extend(crate::skill_discovery::tools());
 
-        let mut messages = Vec::new();
-        
-        let mut system_prompt = String::from("You are Ruster, a persistent, proactive LLM agent.\n");
+        // Let the model load references and assets of active skills on demand
         if !skills.is_empty() {
-            system_prompt.push_str("\n# Enabled Skills:\n");
-            for skill in &skills {
-                system_prompt.push_str(&format!("## {}\n{}\n", skill.metadata.name, skill.instructions));
-            }
+            tools.extend(crate::skill_resources::tools());
         }
 
-        tracing::debug!("System prompt: '{}'", &system_prompt);
-        
-        messages.push(json!({"role": "system", "content": system_prompt}));
-        
-        // Add pending events as system messages and clear them
-        for event in self.pending_events.drain(..) {
-             messages.push(json!({
-                 "role": "system",
-                 "content": format!("Background Event: {}", serde_json::to_string(&event).unwrap_or_default())
-             }));
+        // Add file system tools when the session has a workspace
+        if !self.workspace.roots.is_empty() {
+            tools.extend(crate::fs_tools::tools());
         }
 
-        for msg in &self.history {
-            messages.push(json!({"role": msg.role, "content": msg.content}));
+        // Add tools imported from MCP servers, plus access to their resources and prompts
+        if !self.mcp_manager.is_empty().await {
+            tools.extend(self.mcp_manager.tools().await);
+            tools.extend(crate::mcp::builtin_tools());
         }
 
-        Ok((messages, skills, tools))
+        // Add tools advertised by attached servers
+        let attached: Vec<&String> = self.attached_servers.keys().collect();
+        tools.extend(self.server_registry.tools(&attached).await);
+
+        // A skill tool may not shadow a built-in or imported tool of the same name
+        let reserved: std::collections::HashSet<String> = tools[skill_tool_count..].iter().map(|t| t.name.clone()).collect();
+        let mut index = 0;
+        tools.retain(|t| {
+            index += 1;
+            let shadows = index <= skill_tool_count && reserved.contains(&t.name);
+            if shadows {
+                tracing::warn!(session_id = %self.id, tool = %t.name, "Skill tool dropped: its name is taken by a built-in tool.");
+            }
+            !shadows
+        });
+
+        // Only offer tools the active skills' allowed-tools permit
+        let policy = ToolPolicy::for_skills(&skills);
+        tools.retain(|t| policy.allows_tool(&t.name));
+
+        (skills, tools)
     }
 
     pub fn add_server_message(&mut self, server_name: &str, content: Value) -> Result<()> {
@@ -334,6 +556,8 @@ impl Session {
     }
 
     pub fn add_skill(&mut self, name: String) -> Result<()> {
+        // Manually added skills are pinned
+        self.skill_last_relevant.remove(&name);
         if !self.active_skills.contains(&name) {
             self.active_skills.push(name);
         }
@@ -342,6 +566,7 @@ impl Session {
 
     pub fn remove_skill(&mut self, name: &str) -> Result<()> {
         self.active_skills.retain(|s| s != name);
+        self.skill_last_relevant.remove(name);
         // Also remove from history
         for msg in &mut self.history {
             msg.skills.retain(|s| s != name);
@@ -388,6 +613,8 @@ pub struct SessionManager {
     pub llm_client: LlmClient,
     pub event_sender: broadcast::Sender<serde_json::Value>,
     pub server_registry: Arc<ServerRegistry>,
+    pub supervisor: Supervisor,
+    pub mcp_manager: Arc<McpManager>,
 }
 
 impl SessionManager {
@@ -396,8 +623,13 @@ impl SessionManager {
         skills_manager: Arc<RwLock<SkillsManager>>,
         llm_client: LlmClient,
         server_registry: Arc<ServerRegistry>,
+        mcp_manager: Arc<McpManager>,
     ) -> Self {
         let (tx, _) = broadcast::channel(100);
+        let server_log_dir = crate::logging::get_log_dir()
+            .map(|d| d.join("servers"))
+            .unwrap_or_else(|_| std::env::temp_dir().join("ruster-servers"));
+        let supervisor = Supervisor::new(server_log_dir, server_registry.discovery_dir().to_path_buf(), tx.clone());
         Self {
             sessions: RwLock::new(HashMap::new()),
             config,
@@ -405,87 +637,200 @@ impl SessionManager {
             llm_client,
             event_sender: tx,
             server_registry,
+            supervisor,
+            mcp_manager,
+        }
+    }
+
+    /// Whether any loaded session is attached to the server.
+    pub async fn is_server_attached(&self, server_name: &str) -> bool {
+        let sessions = self.sessions.read().await;
+        for session_arc in sessions.values() {
+            if session_arc.read().await.attached_servers.contains_key(server_name) {
+                return true;
+            }
         }
+        false
     }
 
-    pub async fn handle_server_reply(&self, session_id: &str, server_name: &str, reply: Value) -> Result<()> {
+    /// Records a reply in history and broadcasts it. Returns the broadcast event.
+    pub async fn handle_server_reply(&self, session_id: &str, server_name: &str, id: &str, reply: Value) -> Result<Value> {
         let session_arc = self.get_session(session_id).await?;
         let mut session = session_arc.write().await;
         session.add_server_message(server_name, reply.clone())?;
         
         // Notify client via broadcast
-        let _ = self.event_sender.send(json!({
+        let event = json!({
             "event": "server_reply",
             "session_id": session_id,
             "server_name": server_name,
+            "id": id,
             "content": reply
-        }));
-        Ok(())
+        });
+        let _ = self.event_sender.send(event.clone());
+        Ok(event)
     }
 
-    pub async fn handle_server_event(&self, server_name: &str, event: Value) -> Result<()> {
+    pub async fn handle_server_reply_timeout(&self, session_id: &str, server_name: &str, id: &str) -> Result<Value> {
+        let session_arc = self.get_session(session_id).await?;
+        session_arc.read().await.log_activity(&format!("{}: no reply to request {}", server_name, id))?;
+        let event = json!({
+            "event": "server_reply_timeout",
+            "session_id": session_id,
+            "server_name": server_name,
+            "id": id
+        });
+        let _ = self.event_sender.send(event.clone());
+        Ok(event)
+    }
+
+    pub async fn handle_server_event(self: &Arc<Self>, server_name: &str, event: Value) -> Result<()> {
         let sessions = self.sessions.read().await;
         for (session_id, session_arc) in sessions.iter() {
             let mut session = session_arc.write().await;
-            if let Some(mode) = session.attached_servers.get(server_name).cloned() {
-                // Rate limiting
-                let limiter = session.rate_limiters.entry(server_name.to_string()).or_insert_with(RateLimiter::new);
-                if !limiter.check() {
-                    // Send rate limit event to client via broadcast
-                    let _ = self.event_sender.send(json!({
-                        "event": "rate_limited",
-                        "session_id": session_id,
-                        "server_name": server_name,
-                        "dropped": 1
-                    }));
+            if let Some(attachment) = session.attached_servers.get(server_name) {
+                let (mode, limit) = (attachment.delivery, attachment.rate_limit);
+                let Some(event) = attachment.admit(&event) else {
+                    tracing::debug!(session_id = %session_id, server = %server_name, "Event filtered out.");
                     continue;
-                }
-
-                match mode {
-                    EventDeliveryMode::Immediate => {
-                        // Add to history and trigger LLM (immediate implies it should generate response)
-                        // Actually, just adding it to history might not be enough if we want immediate generation.
-                        // The spec says: Event added as {"role":server_name, "content":...} -> LLM immediately generates reply
-                        // This means we need to trigger the "send" logic but with no new user message.
-                        
-                        session.add_server_message(server_name, event.clone())?;
-                        
-                        // To trigger LLM, we can send a special internal event or just call a method.
-                        // For now, let's just broadcast that a server event happened.
-                        let _ = self.event_sender.send(json!({
-                            "event": "server_event",
-                            "session_id": session_id,
-                            "server_name": server_name,
-                            "content": event,
-                            "delivery": "immediate"
-                        }));
-                    }
-                    EventDeliveryMode::Proactive => {
-                        // For proactive, we just broadcast it, and proactive loop can pick it up.
-                         let _ = self.event_sender.send(json!({
-                            "event": "server_event",
-                            "session_id": session_id,
-                            "server_name": server_name,
-                            "content": event,
-                            "delivery": "proactive"
-                        }));
-                    }
-                    EventDeliveryMode::NextTurn => {
-                        session.pending_events.push(event.clone());
-                         let _ = self.event_sender.send(json!({
-                            "event": "server_event",
-                            "session_id": session_id,
-                            "server_name": server_name,
-                            "content": event,
-                            "delivery": "next-turn"
-                        }));
+                };
+
+                // Rate limiting. Once a batch is being coalesced, later events join it
+                // so they are not delivered ahead of it.
+                let limiter = session.rate_limiters.entry(server_name.to_string()).or_insert_with(|| RateLimiter::new(limit));
+                if limiter.coalescing.is_some() || !limiter.check() {
+                    match limit.overflow {
+                        OverflowPolicy::Drop => {
+                            // Send rate limit event to client via broadcast
+                            let _ = self.event_sender.send(json!({
+                                "event": "rate_limited",
+                                "session_id": session_id,
+                                "server_name": server_name,
+                                "dropped": 1
+                            }));
+                        }
+                        OverflowPolicy::Coalesce => {
+                            if limiter.coalesce(event) {
+                                let wait = limiter.wait();
+                                let sm = self.clone();
+                                let (id, name) = (session_id.clone(), server_name.to_string());
+                                tokio::spawn(async move {
+                                    tokio::time::sleep(wait).await;
+                                    sm.flush_coalesced(&id, &name).await;
+                                });
+                            }
+                        }
                     }
+                    session.record_overflow(server_name, limit.overflow);
+                    continue;
                 }
+
+                self.deliver_server_event(&mut session, server_name, mode, event);
             }
         }
         Ok(())
     }
 
+    /// Delivers the events held back for a coalescing attachment as one
+    /// `{"coalesced": <count>, "latest": <event>}` event.
+    async fn flush_coalesced(self: &Arc<Self>, session_id: &str, server_name: &str) {
+        let Some(session_arc) = self.sessions.read().await.get(session_id).cloned() else { return };
+        let mut session = session_arc.write().await;
+        let Some(limiter) = session.rate_limiters.get_mut(server_name) else { return };
+        let Some((count, latest)) = limiter.coalescing.take() else { return };
+        limiter.check();
+        if let Some(mode) = session.attached_servers.get(server_name).map(|a| a.delivery) {
+            tracing::debug!(session_id = %session_id, server = %server_name, count = %count, "Delivering coalesced events.");
+            self.deliver_server_event(&mut session, server_name, mode, json!({"coalesced": count, "latest": latest}));
+        }
+        if let Err(e) = session.save_counters() {
+            tracing::warn!(session_id = %session_id, error = %e, "Failed to save event counters.");
+        }
+    }
+
+    /// Prunes the server event log of every loaded session.
+    pub async fn prune_event_logs(&self) {
+        let (max_age, max_bytes) = event_retention(&*self.config.read().await);
+        let sessions: Vec<_> = self.sessions.read().await.values().cloned().collect();
+        for session_arc in sessions {
+            let mut session = session_arc.write().await;
+            match session.event_log.prune(max_age, max_bytes) {
+                Ok(removed) if removed > 0 => {
+                    tracing::info!(session_id = %session.id, removed = %removed, "Pruned server events.");
+                },
+                Ok(_) => {},
+                Err(e) => tracing::warn!(session_id = %session.id, error = %e, "Failed to prune server events."),
+            }
+        }
+    }
+
+    /// Saves the overflow counters of every session that changed them.
+    pub async fn save_event_counters(&self) {
+        let sessions: Vec<_> = self.sessions.read().await.values().cloned().collect();
+        for session_arc in sessions {
+            let mut session = session_arc.write().await;
+            if let Err(e) = session.save_counters() {
+                tracing::warn!(session_id = %session.id, error = %e, "Failed to save event counters.");
+            }
+        }
+    }
+
+    fn deliver_server_event(self: &Arc<Self>, session: &mut Session, server_name: &str, mode: EventDeliveryMode, event: Value) {
+        let session_id = session.id.clone();
+        let seq = match session.event_log.append(server_name, mode, event.clone()) {
+            Ok(seq) => Some(seq),
+            Err(e) => {
+                tracing::warn!(session_id = %session_id, error = %e, "Failed to log server event.");
+                None
+            }
+        };
+        match mode {
+            EventDeliveryMode::Immediate => {
+                // The event enters history and the LLM replies to it in a turn
+                // of its own, queued behind any turn already running
+                let turns = session.server_turns.get_or_insert_with(|| {
+                    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+                    tokio::spawn(crate::server::run_server_turns(self.clone(), session_id.clone(), rx));
+                    tx
+                });
+                if turns.send((server_name.to_string(), event.clone())).is_err() {
+                    tracing::error!(session_id = %session_id, "Server turn worker is gone; event not answered.");
+                }
+
+                let _ = self.event_sender.send(json!({
+                    "event": "server_event",
+                    "session_id": session_id,
+                    "server_name": server_name,
+                    "content": event,
+                    "seq": seq,
+                    "delivery": "immediate"
+                }));
+            }
+            EventDeliveryMode::Proactive => {
+                // For proactive, we just broadcast it, and proactive loop can pick it up.
+                 let _ = self.event_sender.send(json!({
+                    "event": "server_event",
+                    "session_id": session_id,
+                    "server_name": server_name,
+                    "content": event,
+                    "seq": seq,
+                    "delivery": "proactive"
+                }));
+            }
+            EventDeliveryMode::NextTurn => {
+                session.pending_events.push(event.clone());
+                 let _ = self.event_sender.send(json!({
+                    "event": "server_event",
+                    "session_id": session_id,
+                    "server_name": server_name,
+                    "content": event,
+                    "seq": seq,
+                    "delivery": "next-turn"
+                }));
+            }
+        }
+    }
+
     pub async fn get_session(&self, id: &str) -> Result<Arc<RwLock<Session>>> {
         {
             let map = self.sessions.read().await;
@@ -499,6 +844,8 @@ impl SessionManager {
             self.config.clone(),
             self.skills_manager.clone(),
             self.llm_client.clone(),
+            self.mcp_manager.clone(),
+            self.server_registry.clone(),
             None,
         ).await?;
         
@@ -552,3 +899,47 @@ impl SessionManager {
         Ok(())
     }
 }
+
+/// Retention limits for server event logs, `None` where disabled.
+fn event_retention(config: &Config) -> (Option<std::time::Duration>, Option<u64>) {
+    (
+        (config.server_event_retention_secs > 0).then(|| std::time::Duration::from_secs(config.server_event_retention_secs)),
+        (config.server_event_max_bytes > 0).then_some(config.server_event_max_bytes),
+    )
+}
+
+pub async fn start_event_retention_loop(sm: Arc<SessionManager>) {
+    let mut timer = tokio::time::interval(std::time::Duration::from_secs(3600));
+    loop {
+        timer.tick().await;
+        sm.prune_event_logs().await;
+    }
+}
+
+/// How often changed overflow counters are saved.
+const COUNTER_SAVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+pub async fn start_counter_save_loop(sm: Arc<SessionManager>) {
+    let mut timer = tokio::time::interval(COUNTER_SAVE_INTERVAL);
+    loop {
+        timer.tick().await;
+        sm.save_event_counters().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter_burst_and_coalesce() {
+        let mut limiter = RateLimiter::new(RateLimit { rate: 1.0, burst: 3, overflow: OverflowPolicy::Coalesce });
+        assert_eq!((0..5).filter(|_| limiter.check()).count(), 3);
+        assert!(limiter.wait() > std::time::Duration::from_millis(900));
+
+        assert!(limiter.coalesce(json!(1)));
+        assert!(!limiter.coalesce(json!(2)));
+        assert_eq!(limiter.coalescing.take(), Some((2, json!(2))));
+        assert!(RateLimit { rate: 0.0, ..Default::default() }.validate().is_err());
+    }
+}
@@ -0,0 +1,96 @@
+use serde_json::{Value, json};
+use anyhow::{Result, anyhow};
+use crate::llm::{Tool, ToolOutput};
+use crate::skills::SkillsManager;
+
+pub fn tools() -> Vec<Tool> {
+    vec![
+        Tool {
+            name: "list_available_skills".to_string(),
+            description: "Lists every installed skill with its name and description, including those not active in this conversation. Use activate_skill to load one you need.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {}
+            }),
+            exec: None,
+            working_dir: None,
+            mcp_server: None,
+            server: None,
+            output: ToolOutput::Text,
+        },
+        Tool {
+            name: "activate_skill".to_string(),
+            description: "Activates an installed skill. Its instructions and tools become available from your next step.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "skill_name": { "type": "string", "description": "The name of the skill, as given by list_available_skills." }
+                },
+                "required": ["skill_name"]
+            }),
+            exec: None,
+            working_dir: None,
+            mcp_server: None,
+            server: None,
+            output: ToolOutput::Text,
+        },
+    ]
+}
+
+/// Runs a skill discovery tool. Returns `None` if `name` is not one of them.
+/// Activation itself is applied to the session by the caller.
+pub fn execute(name: &str, args: &Value, mgr: &SkillsManager, banned: &[String], active: &[String]) -> Option<Result<String>> {
+    match name {
+        "list_available_skills" => {
+            let skills: Vec<Value> = mgr.list_skills().into_iter()
+                .filter(|m| !banned.contains(&m.name))
+                .map(|m| json!({
+                    "name": m.name,
+                    "description": m.description,
+                    "active": active.contains(&m.name),
+                }))
+                .collect();
+            Some(serde_json::to_string_pretty(&skills).map_err(Into::into))
+        }
+        "activate_skill" => Some(activation_target(args, mgr, banned).map(|skill| {
+            if active.contains(&skill) {
+                format!("Skill '{}' is already active.", skill)
+            } else {
+                format!("Skill '{}' activated. Its instructions and tools are available from your next step.", skill)
+            }
+        })),
+        _ => None,
+    }
+}
+
+/// The skill an `activate_skill` call may load.
+pub fn activation_target(args: &Value, mgr: &SkillsManager, banned: &[String]) -> Result<String> {
+    let name = args["skill_name"].as_str().ok_or_else(|| anyhow!("Missing skill_name"))?;
+    if banned.iter().any(|b| b == name) || mgr.get_skill(name).is_none() {
+        return Err(anyhow!("Skill '{}' not found. Use list_available_skills to see installed skills.", name));
+    }
+    Ok(name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{write_skill, TempDir};
+
+    #[test]
+    fn test_discovery_respects_banned_skills() {
+        let base = TempDir::new("disc");
+        write_skill(&base, "alpha", "The alpha skill");
+        write_skill(&base, "beta", "The beta skill");
+        let mut mgr = SkillsManager::new();
+        mgr.load_from_dirs(&[base.to_string_lossy().to_string()]).unwrap();
+        let banned = vec!["beta".to_string()];
+
+        let listed: Value = serde_json::from_str(&execute("list_available_skills", &json!({}), &mgr, &banned, &[]).unwrap().unwrap()).unwrap();
+        assert_eq!(listed, json!([{"name": "alpha", "description": "The alpha skill", "active": false}]));
+
+        assert_eq!(activation_target(&json!({"skill_name": "alpha"}), &mgr, &banned).unwrap(), "alpha");
+        assert!(activation_target(&json!({"skill_name": "beta"}), &mgr, &banned).is_err());
+        assert!(activation_target(&json!({"skill_name": "gamma"}), &mgr, &banned).is_err());
+    }
+}
@@ -0,0 +1,526 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use anyhow::{Result, anyhow};
+use tokio::process::Command;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+use crate::skills::{Skill, SkillsManager};
+
+/// Where an installed skill came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceKind {
+    Path,
+    Tarball,
+    Git,
+}
+
+/// A skill installed by `skill install`, recorded in the lockfile.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockedSkill {
+    pub name: String,
+    pub source: String,
+    pub kind: SourceKind,
+    /// Git branch or tag to check out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+    /// Directory of the skill inside the source
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subdir: Option<String>,
+    /// Git commit, or a hash of the skill files for other sources
+    pub revision: String,
+    pub installed_at: String,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SkillLock {
+    #[serde(default)]
+    pub skills: Vec<LockedSkill>,
+}
+
+impl SkillLock {
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)?;
+        toml::from_str(&content).map_err(|e| anyhow!("Invalid skill lockfile {}: {}", path.display(), e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = toml::to_string_pretty(self).map_err(|e| anyhow!("Failed to serialize skill lockfile: {}", e))?;
+        fs::write(path, content)?;
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&LockedSkill> {
+        self.skills.iter().find(|s| s.name == name)
+    }
+}
+
+/// What `install`/`update` should fetch.
+#[derive(Debug, Clone)]
+pub struct InstallRequest {
+    pub source: String,
+    pub reference: Option<String>,
+    pub subdir: Option<String>,
+    /// Replace a same-named skill that was not installed from the lockfile
+    pub force: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateResult {
+    pub skill: String,
+    pub old_revision: String,
+    pub revision: String,
+    pub updated: bool,
+}
+
+/// Installs skills into `install_dir`, tracking them in `lock_path`.
+pub struct Installer {
+    pub install_dir: PathBuf,
+    pub lock_path: PathBuf,
+}
+
+impl Installer {
+    /// Installs into the first configured skills dir, so the skills watcher
+    /// and later scans pick installed skills up.
+    pub fn from_config(config: &crate::config::Config) -> Result<Self> {
+        let install_dir = config.skills_dirs.first()
+            .map(|d| crate::config::expand_path(d))
+            .ok_or_else(|| anyhow!("No skills_dirs configured"))?;
+        Ok(Self {
+            install_dir,
+            lock_path: crate::config::get_config_dir()?.join("skills.lock"),
+        })
+    }
+
+    pub async fn install(&self, req: &InstallRequest, mgr: &RwLock<SkillsManager>) -> Result<(LockedSkill, Option<crate::skills::SkillChange>)> {
+        let work = TempDir::new()?;
+        let fetched = fetch(req, &work.0).await?;
+        let skill = validate(&fetched.dir)?;
+        self.place(req, fetched, &skill.metadata.name, mgr).await
+    }
+
+    /// Moves a fetched skill into the install dir, records it and registers it.
+    async fn place(&self, req: &InstallRequest, fetched: Fetched, name: &str, mgr: &RwLock<SkillsManager>) -> Result<(LockedSkill, Option<crate::skills::SkillChange>)> {
+        let mut lock = SkillLock::load(&self.lock_path)?;
+        let name = name.to_string();
+
+        let dest = self.install_dir.join(&name);
+        if dest.exists() && lock.get(&name).is_none() && !req.force {
+            return Err(anyhow!("Skill '{}' already exists at {} and was not installed by ruster. Use force to replace it", name, dest.display()));
+        }
+        self.put_in_place(&fetched.dir, &dest)?;
+
+        let entry = LockedSkill {
+            name: name.clone(),
+            source: req.source.clone(),
+            kind: fetched.kind,
+            reference: req.reference.clone(),
+            subdir: req.subdir.clone(),
+            revision: fetched.revision,
+            installed_at: chrono::Local::now().to_rfc3339(),
+            path: dest.clone(),
+        };
+        lock.skills.retain(|s| s.name != name);
+        lock.skills.push(entry.clone());
+        lock.save(&self.lock_path)?;
+        tracing::info!(skill = %name, source = %req.source, revision = %entry.revision, "Installed skill.");
+
+        let change = mgr.write().await.reload_skill_dir(&dest);
+        Ok((entry, change))
+    }
+
+    pub async fn uninstall(&self, name: &str, mgr: &RwLock<SkillsManager>) -> Result<Option<crate::skills::SkillChange>> {
+        let mut lock = SkillLock::load(&self.lock_path)?;
+        let entry = lock.get(name).cloned()
+            .ok_or_else(|| anyhow!("Skill '{}' was not installed with skill install", name))?;
+        if entry.path.exists() {
+            fs::remove_dir_all(&entry.path)?;
+        }
+        lock.skills.retain(|s| s.name != name);
+        lock.save(&self.lock_path)?;
+        tracing::info!(skill = %name, "Uninstalled skill.");
+        Ok(mgr.write().await.reload_skill_dir(&entry.path))
+    }
+
+    /// Re-fetches installed skills (all when `name` is `None`) and replaces
+    /// those whose revision changed.
+    pub async fn update(&self, name: Option<&str>, mgr: &RwLock<SkillsManager>) -> Result<(Vec<UpdateResult>, Vec<crate::skills::SkillChange>)> {
+        let lock = SkillLock::load(&self.lock_path)?;
+        let targets: Vec<LockedSkill> = match name {
+            Some(name) => vec![lock.get(name).cloned().ok_or_else(|| anyhow!("Skill '{}' was not installed with skill install", name))?],
+            None => lock.skills.clone(),
+        };
+
+        let mut results = Vec::new();
+        let mut changes = Vec::new();
+        for locked in targets {
+            let req = InstallRequest {
+                source: locked.source.clone(),
+                reference: locked.reference.clone(),
+                subdir: locked.subdir.clone(),
+                force: true,
+            };
+            let work = TempDir::new()?;
+            let fetched = fetch(&req, &work.0).await?;
+            let revision = fetched.revision.clone();
+            let updated = revision != locked.revision;
+            if updated {
+                let skill = validate(&fetched.dir)?;
+                if skill.metadata.name != locked.name {
+                    return Err(anyhow!("Source of '{}' now provides skill '{}'", locked.name, skill.metadata.name));
+                }
+                let (_, change) = self.place(&req, fetched, &locked.name, mgr).await?;
+                changes.extend(change);
+                tracing::info!(skill = %locked.name, old = %locked.revision, new = %revision, "Updated skill.");
+            }
+            results.push(UpdateResult {
+                skill: locked.name,
+                old_revision: locked.revision,
+                revision,
+                updated,
+            });
+        }
+        Ok((results, changes))
+    }
+
+    /// Copies the skill to a staging dir next to `dest`, then swaps it in.
+    fn put_in_place(&self, src: &Path, dest: &Path) -> Result<()> {
+        fs::create_dir_all(&self.install_dir)?;
+        let staging = self.install_dir.join(format!(".install-{}", Uuid::new_v4()));
+        if let Err(e) = copy_dir(src, &staging) {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+        if dest.exists() {
+            fs::remove_dir_all(dest)?;
+        }
+        fs::rename(&staging, dest)?;
+        Ok(())
+    }
+}
+
+struct Fetched {
+    kind: SourceKind,
+    dir: PathBuf,
+    revision: String,
+}
+
+/// Scratch directory removed on drop.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new() -> Result<Self> {
+        let dir = std::env::temp_dir().join(format!("ruster-install-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir)?;
+        Ok(Self(dir))
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+pub fn classify(source: &str) -> SourceKind {
+    if source.ends_with(".tar.gz") || source.ends_with(".tgz") {
+        return SourceKind::Tarball;
+    }
+    let remote = ["http://", "https://", "git://", "ssh://", "file://", "git@"];
+    if remote.iter().any(|p| source.starts_with(p)) || source.ends_with(".git") {
+        return SourceKind::Git;
+    }
+    // A local bare repository
+    let path = crate::config::expand_path(source);
+    if path.join("HEAD").is_file() && path.join("objects").is_dir() {
+        return SourceKind::Git;
+    }
+    SourceKind::Path
+}
+
+async fn fetch(req: &InstallRequest, work: &Path) -> Result<Fetched> {
+    // Would be read as options by git
+    if req.source.starts_with('-') {
+        return Err(anyhow!("Invalid skill source: {}", req.source));
+    }
+    if let Some(reference) = req.reference.as_deref().filter(|r| r.starts_with('-')) {
+        return Err(anyhow!("Invalid reference: {}", reference));
+    }
+    let kind = classify(&req.source);
+    let root = match kind {
+        SourceKind::Path => {
+            let path = crate::config::expand_path(&req.source);
+            if !path.is_dir() {
+                return Err(anyhow!("Skill source {} is not a directory", path.display()));
+            }
+            path
+        },
+        SourceKind::Tarball => {
+            let archive = if req.source.starts_with("http://") || req.source.starts_with("https://") {
+                let bytes = reqwest::get(&req.source).await?.error_for_status()?.bytes().await?;
+                let archive = work.join("skill.tar.gz");
+                fs::write(&archive, &bytes)?;
+                archive
+            } else {
+                crate::config::expand_path(&req.source)
+            };
+            let dest = work.join("unpacked");
+            fs::create_dir_all(&dest)?;
+            run("tar", &["-xzf".as_ref(), archive.as_os_str(), "-C".as_ref(), dest.as_os_str()], None).await?;
+            dest
+        },
+        SourceKind::Git => {
+            let dest = work.join("repo");
+            let mut args: Vec<&std::ffi::OsStr> = vec!["clone".as_ref(), "--depth".as_ref(), "1".as_ref()];
+            if let Some(reference) = &req.reference {
+                args.push("--branch".as_ref());
+                args.push(reference.as_ref());
+            }
+            let source = crate::config::expand_path(&req.source);
+            args.push("--".as_ref());
+            args.push(if source.exists() { source.as_os_str() } else { req.source.as_ref() });
+            args.push(dest.as_os_str());
+            run("git", &args, None).await?;
+            dest
+        },
+    };
+
+    let dir = match &req.subdir {
+        Some(subdir) => {
+            let dir = root.join(subdir).canonicalize()?;
+            if !dir.starts_with(root.canonicalize()?) {
+                return Err(anyhow!("Subdirectory {} is outside the source", subdir));
+            }
+            dir
+        },
+        None => find_skill_root(&root)?,
+    };
+
+    let revision = match kind {
+        SourceKind::Git => run("git", &["rev-parse".as_ref(), "HEAD".as_ref()], Some(&root)).await?.trim().to_string(),
+        _ => tree_hash(&dir)?,
+    };
+    Ok(Fetched { kind, dir, revision })
+}
+
+/// The source's root if it holds a SKILL.md, otherwise its only
+/// subdirectory (at any depth) that does.
+fn find_skill_root(root: &Path) -> Result<PathBuf> {
+    if root.join("SKILL.md").is_file() {
+        return Ok(root.to_path_buf());
+    }
+    let mut found = Vec::new();
+    find_skill_dirs(root, &mut found);
+    match found.len() {
+        0 => Err(anyhow!("No SKILL.md found in source")),
+        1 => Ok(found.remove(0)),
+        _ => Err(anyhow!("Source contains {} skills; pick one with subdir", found.len())),
+    }
+}
+
+fn find_skill_dirs(dir: &Path, found: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !entry.file_type().is_ok_and(|t| t.is_dir()) || entry.file_name() == ".git" {
+            continue;
+        }
+        if path.join("SKILL.md").is_file() {
+            found.push(path);
+        } else {
+            find_skill_dirs(&path, found);
+        }
+    }
+}
+
+/// Parses the fetched SKILL.md and checks the name is usable as a folder.
+fn validate(dir: &Path) -> Result<Skill> {
+    let skill = SkillsManager::parse_skill(&dir.join("SKILL.md"))?;
+    let name = &skill.metadata.name;
+    let valid = !name.is_empty()
+        && name != "."
+        && name != ".."
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '.');
+    if !valid {
+        return Err(anyhow!("Invalid skill name '{}'", name));
+    }
+    if skill.metadata.description.trim().is_empty() {
+        return Err(anyhow!("Skill '{}' has an empty description", name));
+    }
+    Ok(skill)
+}
+
+async fn run(program: &str, args: &[&std::ffi::OsStr], dir: Option<&Path>) -> Result<String> {
+    let mut cmd = Command::new(program);
+    cmd.args(args);
+    if let Some(dir) = dir {
+        cmd.current_dir(dir);
+    }
+    let output = cmd.output().await.map_err(|e| anyhow!("Failed to run {}: {}", program, e))?;
+    if !output.status.success() {
+        return Err(anyhow!("{} failed: {}", program, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Copies a fetched skill. Symlinks are kept only if they are relative and
+/// resolve inside the skill, so a skill cannot expose files outside itself.
+fn copy_dir(src: &Path, dest: &Path) -> Result<()> {
+    fn copy(root: &Path, src: &Path, dest: &Path) -> Result<()> {
+        fs::create_dir_all(dest)?;
+        for entry in fs::read_dir(src)? {
+            let entry = entry?;
+            if entry.file_name() == ".git" {
+                continue;
+            }
+            let file_type = entry.file_type()?;
+            let target = dest.join(entry.file_name());
+            if file_type.is_dir() {
+                copy(root, &entry.path(), &target)?;
+            } else if file_type.is_symlink() {
+                let link = fs::read_link(entry.path())?;
+                let inside = !link.is_absolute()
+                    && entry.path().canonicalize().is_ok_and(|resolved| resolved.starts_with(root));
+                if !inside {
+                    let rel = entry.path().strip_prefix(root).unwrap_or(&entry.path()).to_path_buf();
+                    return Err(anyhow!("Symlink {} points outside the skill", rel.display()));
+                }
+                std::os::unix::fs::symlink(link, &target)?;
+            } else {
+                fs::copy(entry.path(), &target)?;
+            }
+        }
+        Ok(())
+    }
+    let root = src.canonicalize()?;
+    copy(&root, &root, dest)
+}
+
+/// Hash over relative paths and contents of every file under `dir`.
+fn tree_hash(dir: &Path) -> Result<String> {
+    fn walk(base: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            if entry.file_name() == ".git" {
+                continue;
+            }
+            if entry.file_type()?.is_dir() {
+                walk(base, &entry.path(), files)?;
+            } else {
+                files.push(entry.path().strip_prefix(base)?.to_path_buf());
+            }
+        }
+        Ok(())
+    }
+    let mut files = Vec::new();
+    walk(dir, dir, &mut files)?;
+    files.sort();
+
+    let mut hasher = Sha256::new();
+    for file in files {
+        hasher.update(file.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        hasher.update(fs::read(dir.join(&file)).unwrap_or_default());
+        hasher.update([0]);
+    }
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = std::process::Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap();
+        assert!(status.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&status.stderr));
+    }
+
+    #[tokio::test]
+    async fn test_install_and_update_from_bare_repo() {
+        let base = TempDir::new("install-test");
+        let bare = base.join("skill.git");
+        let work = base.join("work");
+        fs::create_dir_all(&work).unwrap();
+        git(&base, &["init", "--bare", "-q", bare.to_str().unwrap()]);
+        git(&work, &["init", "-q"]);
+        fs::write(work.join("SKILL.md"), "---\nname: greeter\ndescription: Says hello\n---\nHello").unwrap();
+        git(&work, &["add", "."]);
+        git(&work, &["commit", "-q", "-m", "init"]);
+        git(&work, &["push", "-q", bare.to_str().unwrap(), "HEAD:refs/heads/main"]);
+
+        let installer = Installer {
+            install_dir: base.join("skills"),
+            lock_path: base.join("skills.lock"),
+        };
+        let mgr = RwLock::new(SkillsManager::new());
+        let req = InstallRequest { source: bare.to_string_lossy().to_string(), reference: Some("main".into()), subdir: None, force: false };
+        let (entry, change) = installer.install(&req, &mgr).await.unwrap();
+        assert_eq!(entry.kind, SourceKind::Git);
+        assert!(matches!(change, Some(crate::skills::SkillChange::Loaded(..))));
+        assert!(mgr.read().await.get_skill("greeter").is_some());
+        assert!(!base.join("skills/greeter/.git").exists());
+
+        let (results, _) = installer.update(Some("greeter"), &mgr).await.unwrap();
+        assert!(!results[0].updated);
+
+        fs::write(work.join("SKILL.md"), "---\nname: greeter\ndescription: Says hello politely\n---\nHello").unwrap();
+        git(&work, &["commit", "-q", "-am", "polite"]);
+        git(&work, &["push", "-q", bare.to_str().unwrap(), "HEAD:refs/heads/main"]);
+        let (results, changes) = installer.update(None, &mgr).await.unwrap();
+        assert!(results[0].updated);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(mgr.read().await.get_skill("greeter").unwrap().metadata.description, "Says hello politely");
+
+        installer.uninstall("greeter", &mgr).await.unwrap();
+        assert!(mgr.read().await.get_skill("greeter").is_none());
+        assert!(SkillLock::load(&installer.lock_path).unwrap().skills.is_empty());
+    }
+
+    #[test]
+    fn test_rejects_symlinks_leaving_the_skill() {
+        let base = TempDir::new("install-test");
+        let src = base.join("src");
+        fs::create_dir_all(src.join("references")).unwrap();
+        fs::write(src.join("references/guide.md"), "guide").unwrap();
+        std::os::unix::fs::symlink("references/guide.md", src.join("GUIDE.md")).unwrap();
+        let installer = Installer { install_dir: base.join("skills"), lock_path: base.join("skills.lock") };
+
+        installer.put_in_place(&src, &base.join("skills/ok")).unwrap();
+        assert_eq!(fs::read_to_string(base.join("skills/ok/GUIDE.md")).unwrap(), "guide");
+
+        for (link, target) in [("assets", "/etc"), ("assets", "../.."), ("assets", "missing")] {
+            let _ = fs::remove_file(src.join(link));
+            std::os::unix::fs::symlink(target, src.join(link)).unwrap();
+            let err = installer.put_in_place(&src, &base.join("skills/bad")).unwrap_err();
+            assert!(err.to_string().contains("points outside the skill"), "{}: {}", target, err);
+            assert!(!base.join("skills/bad").exists());
+        }
+        assert_eq!(fs::read_dir(base.join("skills")).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_option_like_source_and_reference() {
+        let work = TempDir::new("install-test");
+        let req = InstallRequest { source: "--upload-pack=touch /tmp/pwned".into(), reference: None, subdir: None, force: false };
+        assert!(fetch(&req, &work).await.is_err());
+        let req = InstallRequest { source: "https://example.com/skill.git".into(), reference: Some("--upload-pack=x".into()), subdir: None, force: false };
+        let err = fetch(&req, &work).await.err().unwrap();
+        assert!(err.to_string().contains("Invalid reference"));
+    }
+}
@@ -0,0 +1,382 @@
+use std::collections::HashMap;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use serde_json::Value;
+use crate::skills::SkillMetadata;
+
+/// Limits from the Agent Skills spec.
+const MAX_NAME_LEN: usize = 64;
+const MAX_DESCRIPTION_LEN: usize = 1024;
+const KNOWN_FIELDS: &[&str] = &["name", "description", "version", "license", "compatibility", "allowed-tools", "metadata", "tools"];
+const SCHEMA_TYPES: &[&str] = &["string", "number", "integer", "boolean", "array", "object", "null"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A single lint finding.
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub path: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skill: Option<String>,
+    pub message: String,
+}
+
+struct Linter {
+    diagnostics: Vec<Diagnostic>,
+    path: PathBuf,
+    skill: Option<String>,
+}
+
+impl Linter {
+    fn report(&mut self, severity: Severity, code: &'static str, message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            code,
+            path: self.path.clone(),
+            skill: self.skill.clone(),
+            message,
+        });
+    }
+
+    fn error(&mut self, code: &'static str, message: String) {
+        self.report(Severity::Error, code, message);
+    }
+
+    fn warn(&mut self, code: &'static str, message: String) {
+        self.report(Severity::Warning, code, message);
+    }
+}
+
+/// Lints every path given. A path is either a skill directory (holding a
+/// `SKILL.md`) or a skills root whose subdirectories are skills. Duplicate
+/// names are reported across all of them; earlier paths take precedence.
+pub fn lint_paths(paths: &[PathBuf]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut names: HashMap<String, Vec<PathBuf>> = HashMap::new();
+
+    for path in paths {
+        let skill_dirs: Vec<PathBuf> = if path.join("SKILL.md").exists() {
+            vec![path.clone()]
+        } else if path.is_dir() {
+            let mut dirs: Vec<PathBuf> = fs::read_dir(path).into_iter().flatten().flatten()
+                .filter(|e| e.path().is_dir() && !e.file_name().to_string_lossy().starts_with('.'))
+                .map(|e| e.path())
+                .collect();
+            dirs.sort();
+            dirs
+        } else {
+            diagnostics.push(Diagnostic {
+                severity: Severity::Error,
+                code: "not-found",
+                path: path.clone(),
+                skill: None,
+                message: "Path is not a directory".to_string(),
+            });
+            continue;
+        };
+
+        for dir in skill_dirs {
+            let (name, found) = lint_skill(&dir);
+            diagnostics.extend(found);
+            if let Some(name) = name {
+                names.entry(name).or_default().push(dir);
+            }
+        }
+    }
+
+    let mut duplicates: Vec<_> = names.into_iter().filter(|(_, dirs)| dirs.len() > 1).collect();
+    duplicates.sort();
+    for (name, dirs) in duplicates {
+        for dir in &dirs[1..] {
+            diagnostics.push(Diagnostic {
+                severity: Severity::Warning,
+                code: "duplicate-name",
+                path: dir.clone(),
+                skill: Some(name.clone()),
+                message: format!("Skill name '{}' is shadowed by {}", name, dirs[0].display()),
+            });
+        }
+    }
+    diagnostics
+}
+
+/// Lints one skill directory, returning the skill's name if it could be read.
+pub fn lint_skill(dir: &Path) -> (Option<String>, Vec<Diagnostic>) {
+    let skill_md = dir.join("SKILL.md");
+    let mut lint = Linter { diagnostics: Vec::new(), path: skill_md.clone(), skill: None };
+
+    let content = match fs::read_to_string(&skill_md) {
+        Ok(content) => content,
+        Err(e) => {
+            lint.error("missing-skill-md", format!("Cannot read SKILL.md: {}", e));
+            return (None, lint.diagnostics);
+        }
+    };
+    let Some((frontmatter, body)) = split_frontmatter(&content) else {
+        lint.error("missing-frontmatter", "SKILL.md must start with YAML frontmatter between '---' lines".to_string());
+        return (None, lint.diagnostics);
+    };
+
+    let raw: serde_yaml::Value = match serde_yaml::from_str(frontmatter) {
+        Ok(raw) => raw,
+        Err(e) => {
+            lint.error("invalid-yaml", format!("Frontmatter is not valid YAML: {}", e));
+            return (None, lint.diagnostics);
+        }
+    };
+    let Some(fields) = raw.as_mapping() else {
+        lint.error("invalid-frontmatter", "Frontmatter must be a YAML mapping".to_string());
+        return (None, lint.diagnostics);
+    };
+    for key in fields.keys() {
+        let key = key.as_str().unwrap_or_default();
+        if !KNOWN_FIELDS.contains(&key) {
+            lint.warn("unknown-field", format!("Unknown frontmatter field '{}'", key));
+        }
+    }
+    for required in ["name", "description"] {
+        if !fields.get(required).is_some_and(|v| v.is_string()) {
+            lint.error("missing-field", format!("Frontmatter field '{}' is required and must be a string", required));
+        }
+    }
+
+    let metadata: SkillMetadata = match serde_yaml::from_str(frontmatter) {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            lint.error("invalid-frontmatter", format!("Invalid frontmatter: {}", e));
+            return (None, lint.diagnostics);
+        }
+    };
+    let name = metadata.name.clone();
+    lint.skill = Some(name.clone());
+
+    check_name(&mut lint, &metadata, dir);
+    if metadata.description.trim().is_empty() {
+        lint.error("empty-description", "Description is empty".to_string());
+    } else if metadata.description.chars().count() > MAX_DESCRIPTION_LEN {
+        lint.warn("description-too-long", format!("Description is longer than {} characters", MAX_DESCRIPTION_LEN));
+    }
+    if body.trim().is_empty() {
+        lint.warn("empty-body", "SKILL.md has no instructions after the frontmatter".to_string());
+    }
+    for pattern in metadata.allowed_tools.iter().flatten() {
+        let tool = pattern.split_once('(').map(|(t, _)| t).unwrap_or(pattern);
+        if glob::Pattern::new(tool).is_err() {
+            lint.error("invalid-allowed-tools", format!("Invalid allowed-tools pattern '{}'", pattern));
+        }
+    }
+
+    check_tools(&mut lint, &metadata);
+    check_scripts(&mut lint, &metadata, body, dir);
+
+    (Some(name), lint.diagnostics)
+}
+
+fn split_frontmatter(content: &str) -> Option<(&str, &str)> {
+    let rest = content.strip_prefix("---")?;
+    let end = rest.find("---")?;
+    Some((&rest[..end], &rest[end + 3..]))
+}
+
+fn check_name(lint: &mut Linter, metadata: &SkillMetadata, dir: &Path) {
+    let name = &metadata.name;
+    if name.chars().count() > MAX_NAME_LEN {
+        lint.warn("name-too-long", format!("Name is longer than {} characters", MAX_NAME_LEN));
+    }
+    if !name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-') {
+        lint.warn("name-format", "Name should only contain lowercase letters, digits and hyphens".to_string());
+    }
+    if let Some(dir_name) = dir.file_name()
+        && dir_name.to_string_lossy() != *name {
+        lint.warn("name-mismatch", format!("Folder name '{}' does not match skill name '{}'", dir_name.to_string_lossy(), name));
+    }
+}
+
+fn check_tools(lint: &mut Linter, metadata: &SkillMetadata) {
+    let mut seen = Vec::new();
+    for tool in &metadata.tools {
+        if seen.contains(&&tool.name) {
+            lint.error("duplicate-tool", format!("Tool '{}' is defined more than once", tool.name));
+        }
+        seen.push(&tool.name);
+        if tool.exec.as_deref().is_none_or(|e| e.trim().is_empty()) {
+            lint.error("missing-exec", format!("Tool '{}' has no exec command", tool.name));
+        }
+        if tool.description.trim().is_empty() {
+            lint.warn("empty-tool-description", format!("Tool '{}' has no description", tool.name));
+        }
+        let mut problems = Vec::new();
+        check_schema(&tool.parameters, "parameters", true, &mut problems);
+        for problem in problems {
+            lint.error("invalid-tool-schema", format!("Tool '{}': {}", tool.name, problem));
+        }
+    }
+}
+
+/// Checks the subset of JSON Schema used for tool parameters.
+fn check_schema(schema: &Value, at: &str, top_level: bool, problems: &mut Vec<String>) {
+    let Some(obj) = schema.as_object() else {
+        problems.push(format!("{} must be an object", at));
+        return;
+    };
+    let types: Vec<&str> = match obj.get("type") {
+        Some(Value::String(t)) => vec![t.as_str()],
+        Some(Value::Array(ts)) => ts.iter().filter_map(|t| t.as_str()).collect(),
+        Some(_) => {
+            problems.push(format!("{}.type must be a string or an array of strings", at));
+            return;
+        },
+        None => Vec::new(),
+    };
+    for t in &types {
+        if !SCHEMA_TYPES.contains(t) {
+            problems.push(format!("{}.type '{}' is not a JSON Schema type", at, t));
+        }
+    }
+    if top_level && types != ["object"] {
+        problems.push(format!("{}.type must be \"object\"", at));
+    }
+
+    if let Some(properties) = obj.get("properties") {
+        match properties.as_object() {
+            Some(props) => {
+                for (name, prop) in props {
+                    check_schema(prop, &format!("{}.properties.{}", at, name), false, problems);
+                }
+            },
+            None => problems.push(format!("{}.properties must be an object", at)),
+        }
+    }
+    if let Some(required) = obj.get("required") {
+        match required.as_array() {
+            Some(required) => {
+                for r in required {
+                    match r.as_str() {
+                        Some(r) if obj.get("properties").and_then(|p| p.get(r)).is_none() => {
+                            problems.push(format!("{}.required lists '{}' which is not in properties", at, r));
+                        },
+                        Some(_) => {},
+                        None => problems.push(format!("{}.required must only contain strings", at)),
+                    }
+                }
+            },
+            None => problems.push(format!("{}.required must be an array", at)),
+        }
+    }
+    if types.contains(&"array") {
+        match obj.get("items") {
+            Some(items) => check_schema(items, &format!("{}.items", at), false, problems),
+            None => problems.push(format!("{} is an array without items", at)),
+        }
+    }
+}
+
+/// Checks that scripts referenced from tools or instructions exist and are
+/// executable, since `run_skill_script` runs them directly.
+fn check_scripts(lint: &mut Linter, metadata: &SkillMetadata, body: &str, dir: &Path) {
+    let re = regex::Regex::new(r"(?:\./)?scripts/[A-Za-z0-9._\-/]*[A-Za-z0-9_\-]").unwrap();
+    let sources = metadata.tools.iter().filter_map(|t| t.exec.as_deref()).chain(std::iter::once(body));
+    let mut referenced: Vec<String> = Vec::new();
+    for text in sources {
+        for m in re.find_iter(text) {
+            let rel = m.as_str().trim_start_matches("./").to_string();
+            if !referenced.contains(&rel) {
+                referenced.push(rel);
+            }
+        }
+    }
+
+    for rel in &referenced {
+        let path = dir.join(rel);
+        if !path.exists() {
+            lint.error("missing-script", format!("Referenced script '{}' does not exist", rel));
+        } else if path.is_file() && !is_executable(&path) {
+            lint.error("script-not-executable", format!("Script '{}' is not executable", rel));
+        }
+    }
+
+    // Scripts nobody references can still be run with run_skill_script
+    let Ok(entries) = fs::read_dir(dir.join("scripts")) else { return };
+    for entry in entries.flatten() {
+        let rel = format!("scripts/{}", entry.file_name().to_string_lossy());
+        if entry.path().is_file() && !referenced.contains(&rel) && !is_executable(&entry.path()) {
+            lint.warn("script-not-executable", format!("Script '{}' is not executable", rel));
+        }
+    }
+}
+
+fn is_executable(path: &Path) -> bool {
+    fs::metadata(path).map(|m| m.permissions().mode() & 0o111 != 0).unwrap_or(false)
+}
+
+/// `ruster skill lint [dir...]`: prints one JSON diagnostic per line and
+/// exits non-zero when any error is found. Lints `skills_dirs` when no
+/// directory is given.
+pub fn run_cli(args: &[String]) -> anyhow::Result<()> {
+    let paths: Vec<PathBuf> = if args.is_empty() {
+        crate::config::load_config()?.skill_search_path().iter()
+            .map(|d| crate::config::expand_path(d))
+            .filter(|d| d.exists())
+            .collect()
+    } else {
+        args.iter().map(|a| crate::config::expand_path(a)).collect()
+    };
+
+    let diagnostics = lint_paths(&paths);
+    for diagnostic in &diagnostics {
+        println!("{}", serde_json::to_string(diagnostic)?);
+    }
+    if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{write_skill, TempDir};
+
+    #[test]
+    fn test_lint_reports_common_problems() {
+        let base = TempDir::new("lint");
+        let dir = base.join("folder");
+        fs::create_dir_all(dir.join("scripts")).unwrap();
+        fs::write(dir.join("scripts/run.sh"), "#!/bin/sh\n").unwrap();
+        fs::write(dir.join("SKILL.md"), "---
+name: broken
+description: Does things
+tools:
+  - name: go
+    description: Runs
+    parameters:
+      type: object
+      properties:
+        items: { type: array }
+      required: [missing]
+    exec: ./scripts/run.sh
+---
+Also see scripts/other.py").unwrap();
+
+        let (name, diagnostics) = lint_skill(&dir);
+        assert_eq!(name.as_deref(), Some("broken"));
+        let codes: Vec<_> = diagnostics.iter().map(|d| d.code).collect();
+        assert!(codes.contains(&"name-mismatch"));
+        assert!(codes.contains(&"script-not-executable"));
+        assert!(codes.contains(&"missing-script"));
+        assert_eq!(codes.iter().filter(|c| **c == "invalid-tool-schema").count(), 2);
+
+        write_skill(&base, "broken", "Same name");
+        let all = lint_paths(&[base.to_path_buf()]);
+        assert!(all.iter().any(|d| d.code == "duplicate-name"));
+    }
+}
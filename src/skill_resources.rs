@@ -0,0 +1,169 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde_json::{Value, json};
+use anyhow::{Result, anyhow};
+use crate::llm::{Tool, ToolOutput};
+use crate::skills::Skill;
+
+/// Skill subdirectories the model may browse on demand.
+pub const RESOURCE_DIRS: &[&str] = &["references", "assets"];
+
+pub fn tools() -> Vec<Tool> {
+    vec![
+        Tool {
+            name: "list_skill_resources".to_string(),
+            description: "Lists the reference documents and assets shipped with an active skill. Read them with read_skill_resource when the skill's instructions point to them.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "skill_name": { "type": "string", "description": "The name of the active skill." }
+                },
+                "required": ["skill_name"]
+            }),
+            exec: None,
+            working_dir: None,
+            mcp_server: None,
+            server: None,
+            output: ToolOutput::Text,
+        },
+        Tool {
+            name: "read_skill_resource".to_string(),
+            description: "Reads a file from an active skill's 'references' or 'assets' directory, e.g. 'references/api.md'.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "skill_name": { "type": "string", "description": "The name of the active skill." },
+                    "path": { "type": "string", "description": "Path relative to the skill directory, starting with 'references/' or 'assets/'." }
+                },
+                "required": ["skill_name", "path"]
+            }),
+            exec: None,
+            working_dir: None,
+            mcp_server: None,
+            server: None,
+            output: ToolOutput::Text,
+        },
+    ]
+}
+
+/// Runs a skill resource tool. Returns `None` if `name` is not one of them.
+pub fn execute(name: &str, args: &Value, skills: &[Skill], max_bytes: u64) -> Option<Result<String>> {
+    let run = || -> Result<String> {
+        let skill_name = args["skill_name"].as_str().ok_or_else(|| anyhow!("Missing skill_name"))?;
+        let skill = skills.iter().find(|s| s.metadata.name == skill_name)
+            .ok_or_else(|| anyhow!("Skill '{}' not found or not active", skill_name))?;
+        match name {
+            "list_skill_resources" => list(skill),
+            _ => {
+                let path = args["path"].as_str().ok_or_else(|| anyhow!("Missing path"))?;
+                read(skill, path, max_bytes)
+            }
+        }
+    };
+    match name {
+        "list_skill_resources" | "read_skill_resource" => Some(run()),
+        _ => None,
+    }
+}
+
+fn list(skill: &Skill) -> Result<String> {
+    let mut entries = Vec::new();
+    for dir in RESOURCE_DIRS {
+        if resource_dir(skill, dir).is_some() {
+            collect_files(&skill.path, &skill.path.join(dir), &mut entries);
+        }
+    }
+    if entries.is_empty() {
+        return Ok(format!("Skill '{}' has no references or assets.", skill.metadata.name));
+    }
+    entries.sort();
+    Ok(entries.into_iter()
+        .map(|(path, size)| format!("{} ({} bytes)", path, size))
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+fn collect_files(skill_dir: &Path, dir: &Path, out: &mut Vec<(String, u64)>) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    for entry in entries.flatten() {
+        let Ok(file_type) = entry.file_type() else { continue };
+        let path = entry.path();
+        if file_type.is_dir() {
+            collect_files(skill_dir, &path, out);
+        } else if file_type.is_file() {
+            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            let rel = path.strip_prefix(skill_dir).unwrap_or(&path);
+            out.push((rel.to_string_lossy().to_string(), size));
+        }
+    }
+}
+
+/// The canonical path of one of the skill's resource directories, or `None`
+/// if it is missing or a link out of the skill.
+fn resource_dir(skill: &Skill, dir: &str) -> Option<PathBuf> {
+    let skill_dir = skill.path.canonicalize().ok()?;
+    let dir = skill.path.join(dir).canonicalize().ok()?;
+    dir.starts_with(skill_dir).then_some(dir)
+}
+
+/// Resolves `rel` inside one of the skill's resource directories, following
+/// symlinks before checking so nothing outside them can be reached.
+pub fn resolve(skill: &Skill, rel: &str) -> Result<PathBuf> {
+    let path = skill.path.join(rel).canonicalize()
+        .map_err(|_| anyhow!("Resource '{}' not found in skill '{}'", rel, skill.metadata.name))?;
+    let inside = RESOURCE_DIRS.iter().any(|dir| {
+        resource_dir(skill, dir).is_some_and(|d| path.starts_with(d))
+    });
+    if !inside {
+        return Err(anyhow!("Path '{}' is outside the skill's references and assets", rel));
+    }
+    Ok(path)
+}
+
+fn read(skill: &Skill, rel: &str, max_bytes: u64) -> Result<String> {
+    let path = resolve(skill, rel)?;
+    let size = fs::metadata(&path)?.len();
+    if size > max_bytes {
+        return Err(anyhow!("Resource '{}' is {} bytes, above the {} byte limit", rel, size, max_bytes));
+    }
+    let data = fs::read(&path)?;
+    match String::from_utf8(data) {
+        Ok(text) => Ok(text),
+        Err(e) => {
+            let data = e.into_bytes();
+            Ok(format!("Binary resource '{}' ({}, {} bytes). Reference it by path instead of reading it.", rel, crate::tool_runs::sniff_mime(&data), data.len()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::skills::SkillMetadata;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_resolve_stays_inside_resource_dirs() {
+        let base = TempDir::new("res");
+        fs::create_dir_all(base.join("references")).unwrap();
+        fs::write(base.join("references/api.md"), "# API").unwrap();
+        fs::write(base.join("SKILL.md"), "---").unwrap();
+        let skill = Skill {
+            path: base.to_path_buf(),
+            metadata: serde_yaml::from_str::<SkillMetadata>("name: s\ndescription: d").unwrap(),
+            instructions: String::new(),
+        };
+
+        assert_eq!(read(&skill, "references/api.md", 1024).unwrap(), "# API");
+        assert!(resolve(&skill, "SKILL.md").is_err());
+        assert!(resolve(&skill, "references/../SKILL.md").is_err());
+        assert!(resolve(&skill, "/etc/passwd").is_err());
+        assert_eq!(list(&skill).unwrap(), "references/api.md (5 bytes)");
+
+        let outside = TempDir::new("res-outside");
+        fs::write(outside.join("id_rsa"), "secret").unwrap();
+        std::os::unix::fs::symlink(&*outside, base.join("assets")).unwrap();
+        assert!(resolve(&skill, "assets/id_rsa").is_err());
+        assert_eq!(list(&skill).unwrap(), "references/api.md (5 bytes)");
+    }
+}
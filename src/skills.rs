@@ -1,20 +1,208 @@
 use std::path::{Path, PathBuf};
 use std::fs;
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{RwLock, broadcast};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use anyhow::Result;
 use glob::glob;
+use sha2::{Digest, Sha256};
 use crate::llm::LlmClient;
+use crate::bm25::Bm25Index;
+use crate::inotify::{self, Inotify};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SkillMetadata {
     pub name: String,
     pub description: String,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "de_opt_scalar")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compatibility: Option<String>,
+    /// Tool patterns this skill may use, e.g. `read_file` or `github__*`.
+    /// Accepts a list or a space separated string as in the spec.
+    #[serde(rename = "allowed-tools", default, skip_serializing_if = "Option::is_none", deserialize_with = "de_allowed_tools")]
+    pub allowed_tools: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub metadata: serde_json::Map<String, serde_json::Value>,
     #[serde(default)]
     pub tools: Vec<crate::llm::Tool>,
 }
 
+/// Accepts `version: 1.2` as well as `version: "1.2"`.
+fn de_opt_scalar<'de, D: serde::Deserializer<'de>>(de: D) -> std::result::Result<Option<String>, D::Error> {
+    Ok(match Option::<serde_json::Value>::deserialize(de)? {
+        None | Some(serde_json::Value::Null) => None,
+        Some(serde_json::Value::String(s)) => Some(s),
+        Some(other) => Some(other.to_string()),
+    })
+}
+
+fn de_allowed_tools<'de, D: serde::Deserializer<'de>>(de: D) -> std::result::Result<Option<Vec<String>>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum AllowedTools {
+        List(Vec<String>),
+        Text(String),
+    }
+    Ok(Option::<AllowedTools>::deserialize(de)?.map(|tools| match tools {
+        AllowedTools::List(list) => list,
+        AllowedTools::Text(text) => split_allowed_tools(&text),
+    }))
+}
+
+/// Splits `allowed-tools` text on spaces and commas outside parentheses, so
+/// `Bash(git status:*)` stays one entry.
+fn split_allowed_tools(text: &str) -> Vec<String> {
+    let mut entries = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0usize;
+    for c in text.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            c if depth == 0 && (c.is_whitespace() || c == ',') => {
+                if !current.is_empty() {
+                    entries.push(std::mem::take(&mut current));
+                }
+                continue;
+            },
+            _ => {},
+        }
+        current.push(c);
+    }
+    if !current.is_empty() {
+        entries.push(current);
+    }
+    entries
+}
+
+/// Lexically normalizes a path-like argument, or `None` if it climbs out
+/// with `..`.
+fn normalize_argument(value: &str) -> Option<String> {
+    use std::path::Component;
+    let mut path = PathBuf::new();
+    for component in std::path::Path::new(value).components() {
+        match component {
+            Component::ParentDir => return None,
+            Component::CurDir => {},
+            other => path.push(other),
+        }
+    }
+    Some(path.to_string_lossy().to_string())
+}
+
+/// Built-in tools that only act on the active skills themselves, so
+/// `allowed-tools` never hides them. `list_available_skills` and
+/// `activate_skill` are deliberately left out: activating a skill brings in
+/// its tools, so a restricted skill must list them to allow it.
+const SKILL_SCOPED_TOOLS: &[&str] = &["paginate_tool_output", "run_skill_script", "list_skill_resources", "read_skill_resource"];
+
+/// Arguments an `allowed-tools` argument glob applies to as paths.
+const PATH_ARGUMENTS: &[&str] = &["path", "paths", "root", "file", "files", "dir", "directory"];
+
+/// Arguments an `allowed-tools` argument glob applies to as plain text.
+const COMMAND_ARGUMENTS: &[&str] = &["command", "cmd"];
+
+/// The argument values of a call an argument glob must match, each flagged
+/// with whether it is a path. Falls back to every string argument when the
+/// call has no path or command arguments.
+fn call_subjects(args: &serde_json::Value) -> Vec<(&str, bool)> {
+    fn strings(value: &serde_json::Value) -> Vec<&str> {
+        match value {
+            serde_json::Value::String(s) => vec![s.as_str()],
+            serde_json::Value::Array(items) => items.iter().filter_map(|v| v.as_str()).collect(),
+            _ => Vec::new(),
+        }
+    }
+    let serde_json::Value::Object(map) = args else { return Vec::new() };
+    let mut subjects = Vec::new();
+    for (key, value) in map {
+        if PATH_ARGUMENTS.contains(&key.as_str()) {
+            subjects.extend(strings(value).into_iter().map(|s| (s, true)));
+        } else if COMMAND_ARGUMENTS.contains(&key.as_str()) {
+            subjects.extend(strings(value).into_iter().map(|s| (s, false)));
+        }
+    }
+    if subjects.is_empty() {
+        subjects = map.values().filter_map(|v| v.as_str()).map(|s| (s, false)).collect();
+    }
+    subjects
+}
+
+/// Restricts tool use to what the active skills' `allowed-tools` permit.
+///
+/// Once any active skill declares `allowed-tools`, only these tools remain
+/// available: the tools defined by active skills, the skill scoped built-ins
+/// and tools matching an `allowed-tools` pattern. A pattern is a tool name
+/// glob, optionally followed by `(glob)` which every path argument of the
+/// call must match, e.g. `read_file(docs/*)`. Paths are matched normalized:
+/// `*` does not cross `/` and `..` never matches. Command arguments, or
+/// every string argument of a call with neither, are matched as plain text.
+#[derive(Debug, Default)]
+pub struct ToolPolicy {
+    /// `None` when no active skill restricts tools
+    patterns: Option<Vec<(glob::Pattern, Option<glob::Pattern>)>>,
+}
+
+impl ToolPolicy {
+    pub fn for_skills(skills: &[Skill]) -> Self {
+        if skills.iter().all(|s| s.metadata.allowed_tools.is_none()) {
+            return Self::default();
+        }
+
+        let mut patterns = Vec::new();
+        let mut push = |name: &str, arg: Option<&str>| {
+            match (glob::Pattern::new(name), arg.map(glob::Pattern::new).transpose()) {
+                (Ok(name), Ok(arg)) => patterns.push((name, arg)),
+                _ => tracing::warn!(pattern = %name, "Invalid allowed-tools pattern."),
+            }
+        };
+        for name in SKILL_SCOPED_TOOLS {
+            push(&glob::Pattern::escape(name), None);
+        }
+        for skill in skills {
+            for tool in &skill.metadata.tools {
+                push(&glob::Pattern::escape(&tool.name), None);
+            }
+            for entry in skill.metadata.allowed_tools.iter().flatten() {
+                match entry.split_once('(') {
+                    Some((name, rest)) => push(name, Some(rest.trim_end_matches(')'))),
+                    None => push(entry, None),
+                }
+            }
+        }
+        Self { patterns: Some(patterns) }
+    }
+
+    /// Whether the tool may be offered to the model at all.
+    pub fn allows_tool(&self, name: &str) -> bool {
+        match &self.patterns {
+            None => true,
+            Some(patterns) => patterns.iter().any(|(tool, _)| tool.matches(name)),
+        }
+    }
+
+    /// Whether a specific call, including its arguments, is permitted.
+    pub fn allows_call(&self, name: &str, arguments: &str) -> bool {
+        let Some(patterns) = &self.patterns else { return true };
+        let args: serde_json::Value = serde_json::from_str(arguments).unwrap_or_default();
+        let subjects = call_subjects(&args);
+        let options = glob::MatchOptions { require_literal_separator: true, ..Default::default() };
+        let matches = |arg: &glob::Pattern| {
+            !subjects.is_empty() && subjects.iter().all(|&(value, is_path)| match is_path {
+                true => normalize_argument(value).is_some_and(|v| arg.matches_with(&v, options)),
+                false => arg.matches(value),
+            })
+        };
+        patterns.iter().any(|(tool, arg)| tool.matches(name) && arg.as_ref().is_none_or(matches))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Skill {
     pub path: PathBuf,
@@ -22,17 +210,123 @@ pub struct Skill {
     pub instructions: String,
 }
 
+impl Skill {
+    /// Text embedded for RAG selection.
+    fn embedding_text(&self) -> String {
+        format!("{}: {}", self.metadata.name, self.metadata.description)
+    }
+
+    /// Hash of the embedded text, used to key cached embeddings so edits to a
+    /// skill's name or description invalidate them.
+    pub fn content_hash(&self) -> String {
+        let digest = Sha256::digest(self.embedding_text().as_bytes());
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+/// On-disk form of the embedding cache.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct EmbeddingCacheFile {
+    entries: Vec<CachedEmbedding>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedEmbedding {
+    model: String,
+    hash: String,
+    embedding: Vec<f32>,
+}
+
+/// How `select_skills` ranks skills against a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RagMode {
+    /// Weighted fusion of BM25 and embedding similarity
+    Hybrid,
+    /// Embedding similarity only, the scale `rag_threshold` was tuned for
+    #[default]
+    Embedding,
+    /// BM25 only, for setups without an embedding model
+    Keyword,
+}
+
+/// Retrieval settings, taken from the `rag_*` config keys.
+#[derive(Debug, Clone)]
+pub struct RetrievalOptions {
+    pub mode: RagMode,
+    pub model: String,
+    pub top_n: usize,
+    pub threshold: f32,
+    pub bm25_weight: f32,
+    pub embedding_weight: f32,
+    pub index_instructions: bool,
+}
+
+impl RetrievalOptions {
+    pub fn from_config(config: &crate::config::Config) -> Self {
+        Self {
+            mode: config.rag_mode,
+            model: config.rag_model.clone(),
+            top_n: config.rag_top_n,
+            threshold: config.rag_threshold,
+            bm25_weight: config.rag_bm25_weight,
+            embedding_weight: config.rag_embedding_weight,
+            index_instructions: config.rag_index_instructions,
+        }
+    }
+}
+
+/// A change to the set of loaded skills caused by edits on disk.
+#[derive(Debug, Clone)]
+pub enum SkillChange {
+    Loaded(String, PathBuf),
+    Updated(String, PathBuf),
+    Removed(String, PathBuf),
+}
+
+impl SkillChange {
+    pub fn to_event(&self) -> serde_json::Value {
+        let (event, name, path) = match self {
+            SkillChange::Loaded(name, path) => ("skill_loaded", name, path),
+            SkillChange::Updated(name, path) => ("skill_updated", name, path),
+            SkillChange::Removed(name, path) => ("skill_removed", name, path),
+        };
+        json!({
+            "event": event,
+            "skill": name,
+            "path": path.to_string_lossy()
+        })
+    }
+}
+
 #[derive(Clone)]
 pub struct SkillsManager {
     skills: Vec<Skill>,
+    /// Skills hidden by a same-named skill from a higher precedence directory
+    shadowed: Vec<Skill>,
+    /// Skill directories, highest precedence first
+    search_path: Vec<PathBuf>,
+    /// Embeddings keyed by (model, skill content hash)
     embedding_cache: HashMap<(String, String), Vec<f32>>,
+    cache_path: Option<PathBuf>,
+}
+
+/// Where a skill name resolves to, as reported by `skill which`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SkillResolution {
+    pub skill: String,
+    pub path: PathBuf,
+    pub shadowed: Vec<PathBuf>,
 }
 
 impl SkillsManager {
     pub fn new() -> Self {
         Self { 
             skills: Vec::new(),
+            shadowed: Vec::new(),
+            search_path: Vec::new(),
             embedding_cache: HashMap::new(),
+            cache_path: None,
         }
     }
 
@@ -120,8 +414,11 @@ If you need a specific capability, you can ask the user to 'add' the relevant sk
         Ok(())
     }
 
+    /// Loads the skills in `dirs`. Earlier directories take precedence when
+    /// several provide the same skill name.
     pub fn load_from_dirs(&mut self, dirs: &[String]) -> Result<()> {
         tracing::debug!(dirs = ?dirs, "Scanning directories for skills.");
+        self.search_path = dirs.iter().map(|d| crate::config::expand_path(d)).collect();
         for dir_str in dirs {
             let expanded = crate::config::expand_path(dir_str);
             let pattern = expanded.join("*").join("SKILL.md");
@@ -139,10 +436,62 @@ If you need a specific capability, you can ask the user to 'add' the relevant sk
                 }
             }
         }
+        self.resolve_precedence();
         Ok(())
     }
 
+    /// Precedence rank of a skill: the index of the directory holding it.
+    fn rank(&self, skill: &Skill) -> usize {
+        skill.path.parent()
+            .and_then(|parent| self.search_path.iter().position(|d| d == parent))
+            .unwrap_or(usize::MAX)
+    }
+
+    /// Keeps the highest precedence skill of every name in `skills` and moves
+    /// the rest to `shadowed`. Ties in the same directory go to the first path.
+    fn resolve_precedence(&mut self) {
+        let mut all = std::mem::take(&mut self.skills);
+        all.append(&mut self.shadowed);
+        all.sort_by_cached_key(|s| (self.rank(s), s.path.clone()));
+
+        for skill in all {
+            match self.skills.iter().find(|s| s.metadata.name == skill.metadata.name) {
+                Some(winner) => {
+                    tracing::warn!(skill = %skill.metadata.name, path = %skill.path.display(), shadowed_by = %winner.path.display(), "Skill is shadowed by a higher precedence skill.");
+                    self.shadowed.push(skill);
+                },
+                None => self.skills.push(skill),
+            }
+        }
+    }
+
+    /// Shadowed skills paired with the path of the skill hiding them.
+    pub fn shadowed_skills(&self) -> Vec<(&Skill, &Path)> {
+        self.shadowed.iter().filter_map(|s| {
+            self.get_skill(&s.metadata.name).map(|winner| (s, winner.path.as_path()))
+        }).collect()
+    }
+
+    pub fn which(&self, name: &str) -> Option<SkillResolution> {
+        let skill = self.get_skill(name)?;
+        Some(SkillResolution {
+            skill: name.to_string(),
+            path: skill.path.clone(),
+            shadowed: self.shadowed.iter()
+                .filter(|s| s.metadata.name == name)
+                .map(|s| s.path.clone())
+                .collect(),
+        })
+    }
+
     fn load_skill(&mut self, path: &Path) -> Result<()> {
+        let skill = Self::parse_skill(path)?;
+        tracing::info!(skill = %skill.metadata.name, "Loaded skill.");
+        self.skills.push(skill);
+        Ok(())
+    }
+
+    pub(crate) fn parse_skill(path: &Path) -> Result<Skill> {
         let content = fs::read_to_string(path)?;
         
         if content.starts_with("---") {
@@ -167,15 +516,11 @@ If you need a specific capability, you can ask the user to 'add' the relevant sk
                     }
                 }
 
-                tracing::info!(skill = %metadata.name, "Loaded skill.");
-
-                self.skills.push(Skill {
+                return Ok(Skill {
                     path: skill_path,
                     metadata,
                     instructions: body.trim().to_string(),
                 });
-
-                return Ok(());
             }
         }
         
@@ -183,6 +528,127 @@ If you need a specific capability, you can ask the user to 'add' the relevant sk
         Err(anyhow::anyhow!("Invalid SKILL.md format: missing YAML frontmatter"))
     }
 
+    /// Re-reads the skill stored in `dir`, registering, replacing or dropping
+    /// it as needed. Only the embeddings of the affected skill are invalidated.
+    pub fn reload_skill_dir(&mut self, dir: &Path) -> Option<SkillChange> {
+        self.skills.append(&mut self.shadowed);
+        let change = self.reload_skill_dir_unresolved(dir);
+        self.resolve_precedence();
+        change
+    }
+
+    fn reload_skill_dir_unresolved(&mut self, dir: &Path) -> Option<SkillChange> {
+        let existing = self.skills.iter().position(|s| s.path == dir);
+        let skill_md = dir.join("SKILL.md");
+        let parsed = if skill_md.is_file() {
+            match Self::parse_skill(&skill_md) {
+                Ok(skill) => Some(skill),
+                Err(e) => {
+                    // Keep the previous version while the file is being edited
+                    tracing::error!(path = %skill_md.display(), error = %e, "Failed to reload skill.");
+                    return None;
+                }
+            }
+        } else {
+            None
+        };
+
+        match (existing, parsed) {
+            (Some(idx), Some(skill)) => {
+                let old = std::mem::replace(&mut self.skills[idx], skill.clone());
+                let unchanged = old.metadata.name == skill.metadata.name
+                    && old.instructions == skill.instructions
+                    && serde_json::to_value(&old.metadata).ok() == serde_json::to_value(&skill.metadata).ok();
+                if unchanged {
+                    return None;
+                }
+                if old.content_hash() != skill.content_hash() {
+                    self.invalidate_embeddings(&old);
+                }
+                tracing::info!(skill = %skill.metadata.name, "Reloaded skill.");
+                Some(SkillChange::Updated(skill.metadata.name, skill.path))
+            },
+            (None, Some(skill)) => {
+                tracing::info!(skill = %skill.metadata.name, "Loaded skill.");
+                let change = SkillChange::Loaded(skill.metadata.name.clone(), skill.path.clone());
+                self.skills.push(skill);
+                Some(change)
+            },
+            (Some(idx), None) => {
+                let old = self.skills.remove(idx);
+                self.invalidate_embeddings(&old);
+                tracing::info!(skill = %old.metadata.name, "Unloaded skill.");
+                Some(SkillChange::Removed(old.metadata.name, old.path))
+            },
+            (None, None) => None,
+        }
+    }
+
+    /// Rescans `dirs`, reloading every known or newly found skill directory.
+    pub fn reload_all(&mut self, dirs: &[String]) -> Result<Vec<SkillChange>> {
+        self.search_path = dirs.iter().map(|d| crate::config::expand_path(d)).collect();
+        let mut skill_dirs: Vec<PathBuf> = self.skills.iter().chain(&self.shadowed).map(|s| s.path.clone()).collect();
+        for dir_str in dirs {
+            let pattern = crate::config::expand_path(dir_str).join("*").join("SKILL.md");
+            if let Some(p_str) = pattern.to_str() {
+                for path in glob(p_str)?.flatten() {
+                    if let Some(parent) = path.parent()
+                        && !skill_dirs.iter().any(|d| d == parent) {
+                        skill_dirs.push(parent.to_path_buf());
+                    }
+                }
+            }
+        }
+        Ok(skill_dirs.iter().filter_map(|d| self.reload_skill_dir(d)).collect())
+    }
+
+    fn invalidate_embeddings(&mut self, skill: &Skill) {
+        let hash = skill.content_hash();
+        self.embedding_cache.retain(|(_, h), _| *h != hash);
+    }
+
+    pub fn embedding_cache_path() -> Result<PathBuf> {
+        Ok(crate::logging::get_log_dir()?.parent().unwrap().join("cache").join("embeddings.json"))
+    }
+
+    /// Loads persisted embeddings from `path` and keeps it as the place to
+    /// save newly computed ones.
+    pub fn load_embedding_cache(&mut self, path: PathBuf) -> Result<()> {
+        // Keep the path even if loading fails so a corrupt cache gets rewritten
+        self.cache_path = Some(path.clone());
+        if path.exists() {
+            let content = fs::read_to_string(&path)?;
+            let file: EmbeddingCacheFile = serde_json::from_str(&content)?;
+            for entry in file.entries {
+                self.embedding_cache.insert((entry.model, entry.hash), entry.embedding);
+            }
+            tracing::info!(entries = %self.embedding_cache.len(), path = %path.display(), "Loaded skill embedding cache.");
+        }
+        Ok(())
+    }
+
+    /// Writes the cache to disk, dropping embeddings of skills no longer loaded.
+    fn save_embedding_cache(&self) -> Result<()> {
+        let Some(path) = &self.cache_path else { return Ok(()) };
+        let hashes: Vec<String> = self.skills.iter().map(|s| s.content_hash()).collect();
+        let entries = self.embedding_cache.iter()
+            .filter(|((_, hash), _)| hashes.contains(hash))
+            .map(|((model, hash), embedding)| CachedEmbedding {
+                model: model.clone(),
+                hash: hash.clone(),
+                embedding: embedding.clone(),
+            })
+            .collect();
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string(&EmbeddingCacheFile { entries })?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
     pub fn get_skill(&self, name: &str) -> Option<&Skill> {
         self.skills.iter().find(|s| s.metadata.name == name)
     }
@@ -194,63 +660,62 @@ If you need a specific capability, you can ask the user to 'add' the relevant sk
         }).collect()
     }
 
-    pub async fn search_skills(&mut self, query: &str, llm: &LlmClient, rag_model: &str, top_n: usize, threshold: f32) -> Result<Vec<Skill>> {
-        self.select_skills(query, llm, rag_model, top_n, threshold).await
+    pub async fn search_skills(&mut self, query: &str, llm: &LlmClient, opts: &RetrievalOptions) -> Result<Vec<Skill>> {
+        self.select_skills(query, llm, opts).await
     }
 
-    pub async fn select_skills(&mut self, message: &str, llm: &LlmClient, rag_model: &str, top_n: usize, threshold: f32) -> Result<Vec<Skill>> {
+    pub async fn select_skills(&mut self, message: &str, llm: &LlmClient, opts: &RetrievalOptions) -> Result<Vec<Skill>> {
         if self.skills.is_empty() {
             tracing::debug!("No skills available to select from.");
             return Ok(Vec::new());
         }
 
-        tracing::info!(rag_model = %rag_model, message_len = %message.len(), top_n = %top_n, threshold = %threshold, "Starting RAG skill selection");
+        tracing::info!(mode = ?opts.mode, rag_model = %opts.model, message_len = %message.len(), top_n = %opts.top_n, threshold = %opts.threshold, "Starting RAG skill selection");
 
-        // 1. Get embedding for the message
-        let query_embedding = match llm.embeddings(rag_model, message).await {
-            Ok(emb) => {
-                tracing::debug!("Successfully obtained message embedding");
-                emb
-            },
-            Err(e) => {
-                tracing::warn!(error = %e, "Failed to get query embedding. Falling back to keyword search.");
-                let mut relevant = Vec::new();
-                for skill in &self.skills {
-                    if message.to_lowercase().contains(&skill.metadata.name.to_lowercase()) {
-                        tracing::info!(skill = %skill.metadata.name, "Skill selected via keyword fallback.");
-                        relevant.push(skill.clone());
-                    }
+        // 1. Lexical scores
+        let lexical = if opts.mode == RagMode::Embedding {
+            vec![0.0; self.skills.len()]
+        } else {
+            let documents: Vec<String> = self.skills.iter().map(|s| {
+                if opts.index_instructions {
+                    format!("{}\n{}", s.embedding_text(), s.instructions)
+                } else {
+                    s.embedding_text()
                 }
-                return Ok(relevant);
-            }
+            }).collect();
+            Bm25Index::new(&documents).normalized_scores(message)
         };
 
-        // 2. Ensure all skills have embeddings cached for this model
-        for skill in &self.skills {
-            let key = (rag_model.to_string(), skill.metadata.name.clone());
-            if !self.embedding_cache.contains_key(&key) {
-                let text = format!("{}: {}", skill.metadata.name, skill.metadata.description);
-                tracing::debug!(skill = %skill.metadata.name, "Generating embedding for skill...");
-                match llm.embeddings(rag_model, &text).await {
-                    Ok(emb) => {
-                        self.embedding_cache.insert(key, emb);
-                    },
-                    Err(e) => {
-                        tracing::error!(error = %e, skill = %skill.metadata.name, "Failed to get embedding for skill. Skill will be skipped in RAG search.");
-                    }
+        // 2. Semantic scores; lexical only if the embedding model is unavailable
+        let semantic = if opts.mode == RagMode::Keyword {
+            None
+        } else {
+            match self.semantic_scores(message, llm, &opts.model).await {
+                Ok(scores) => Some(scores),
+                Err(e) => {
+                    tracing::warn!(error = %e, "Failed to get query embedding. Falling back to keyword search.");
+                    None
                 }
             }
-        }
+        };
 
-        // 3. Compute cosine similarity
+        // 3. Fuse scores
         let mut scores: Vec<(&Skill, f32)> = Vec::new();
-        for skill in &self.skills {
-            let key = (rag_model.to_string(), skill.metadata.name.clone());
-            if let Some(emb) = self.embedding_cache.get(&key) {
-                let score = cosine_similarity(&query_embedding, emb);
-                tracing::debug!(skill = %skill.metadata.name, score = %score, "Computed similarity score.");
-                scores.push((skill, score));
-            }
+        for (i, skill) in self.skills.iter().enumerate() {
+            let score = match &semantic {
+                Some(semantic) if opts.mode == RagMode::Embedding => match semantic[i] {
+                    Some(cos) => cos,
+                    None => continue,
+                },
+                Some(semantic) => {
+                    let total = opts.embedding_weight + opts.bm25_weight;
+                    let fused = opts.embedding_weight * semantic[i].unwrap_or(0.0) + opts.bm25_weight * lexical[i];
+                    if total > 0.0 { fused / total } else { 0.0 }
+                },
+                None => lexical[i],
+            };
+            tracing::debug!(skill = %skill.metadata.name, score = %score, lexical = %lexical[i], "Computed relevance score.");
+            scores.push((skill, score));
         }
 
         // 4. Sort and select top skills
@@ -259,7 +724,7 @@ If you need a specific capability, you can ask the user to 'add' the relevant sk
         let mut relevant = Vec::new();
         for (skill, score) in scores {
             // Threshold for relevance
-            if score > threshold {
+            if score > opts.threshold {
                  tracing::info!(skill = %skill.metadata.name, score = %score, "Skill selected via RAG.");
                  relevant.push(skill.clone());
             } else {
@@ -268,13 +733,170 @@ If you need a specific capability, you can ask the user to 'add' the relevant sk
         }
 
         // Limit to top_n to keep context manageable
-        if relevant.len() > top_n {
-            tracing::debug!(top_n = %top_n, "Truncating relevant skills list.");
-            relevant.truncate(top_n);
+        if relevant.len() > opts.top_n {
+            tracing::debug!(top_n = %opts.top_n, "Truncating relevant skills list.");
+            relevant.truncate(opts.top_n);
         }
 
         Ok(relevant)
     }
+
+    /// Cosine similarity between `message` and every skill, in skill order.
+    /// Skills whose embedding could not be computed score `None`.
+    async fn semantic_scores(&mut self, message: &str, llm: &LlmClient, rag_model: &str) -> Result<Vec<Option<f32>>> {
+        let query_embedding = llm.embeddings(rag_model, message).await?;
+        tracing::debug!("Successfully obtained message embedding");
+
+        // Ensure all skills have embeddings cached for this model
+        let mut computed = 0;
+        for skill in &self.skills {
+            let key = (rag_model.to_string(), skill.content_hash());
+            if !self.embedding_cache.contains_key(&key) {
+                tracing::debug!(skill = %skill.metadata.name, "Generating embedding for skill...");
+                match llm.embeddings(rag_model, &skill.embedding_text()).await {
+                    Ok(emb) => {
+                        self.embedding_cache.insert(key, emb);
+                        computed += 1;
+                    },
+                    Err(e) => {
+                        tracing::error!(error = %e, skill = %skill.metadata.name, "Failed to get embedding for skill. Skill will be scored lexically only.");
+                    }
+                }
+            }
+        }
+        if computed > 0 {
+            tracing::info!(computed = %computed, "Computed new skill embeddings.");
+            if let Err(e) = self.save_embedding_cache() {
+                tracing::warn!(error = %e, "Failed to save skill embedding cache.");
+            }
+        }
+
+        Ok(self.skills.iter().map(|skill| {
+            let key = (rag_model.to_string(), skill.content_hash());
+            self.embedding_cache.get(&key).map(|emb| cosine_similarity(&query_embedding, emb))
+        }).collect())
+    }
+}
+
+const ROOT_WATCH_MASK: u32 = inotify::IN_CREATE | inotify::IN_DELETE | inotify::IN_MOVED_FROM | inotify::IN_MOVED_TO;
+const SKILL_WATCH_MASK: u32 = inotify::IN_CLOSE_WRITE | inotify::IN_CREATE | inotify::IN_DELETE | inotify::IN_MOVED_FROM | inotify::IN_MOVED_TO;
+/// How often skills directories that do not exist yet are checked for
+const ROOT_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Watches every skills directory and hot-reloads skills whose `SKILL.md`
+/// changes, broadcasting `skill_loaded`, `skill_updated` and `skill_removed`.
+/// Directories that are missing, or get removed, are watched once they exist.
+pub async fn start_watch_loop(skills: Arc<RwLock<SkillsManager>>, dirs: Vec<String>, events: broadcast::Sender<serde_json::Value>) {
+    let watcher = match Inotify::new() {
+        Ok(w) => w,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to start skills watcher. Hot reload disabled.");
+            return;
+        }
+    };
+
+    // Watch descriptor -> (watched directory, is a skills root)
+    let mut watches: HashMap<i32, (PathBuf, bool)> = HashMap::new();
+    let roots: Vec<PathBuf> = dirs.iter().map(|d| crate::config::expand_path(d)).collect();
+    for root in &roots {
+        if root.is_dir() {
+            watch_root(&watcher, &mut watches, root);
+        }
+    }
+    tracing::info!(watches = %watches.len(), "Watching skills directories for changes.");
+
+    let mut deadline = tokio::time::Instant::now() + ROOT_POLL_INTERVAL;
+    loop {
+        let mut batch = match tokio::time::timeout_at(deadline, watcher.read_events()).await {
+            Ok(Ok(batch)) => batch,
+            Ok(Err(e)) => {
+                tracing::error!(error = %e, "Skills watcher failed. Hot reload disabled.");
+                return;
+            }
+            Err(_) => Vec::new(),
+        };
+        // Editors tend to write in several steps; wait for things to settle
+        if !batch.is_empty() {
+            while let Ok(Ok(more)) = tokio::time::timeout(Duration::from_millis(200), watcher.read_events()).await {
+                batch.extend(more);
+            }
+        }
+
+        let mut changed: Vec<PathBuf> = Vec::new();
+        for event in batch {
+            if event.mask & inotify::IN_IGNORED != 0 {
+                watches.remove(&event.wd);
+                continue;
+            }
+            let Some((dir, is_root)) = watches.get(&event.wd).cloned() else { continue };
+            let skill_dir = if is_root {
+                // Hidden entries, like install staging dirs, are not skills
+                let Some(name) = event.name.filter(|n| !n.to_string_lossy().starts_with('.')) else { continue };
+                let skill_dir = dir.join(name);
+                if event.mask & (inotify::IN_CREATE | inotify::IN_MOVED_TO) != 0 && skill_dir.is_dir() {
+                    watch_skill_dir(&watcher, &mut watches, skill_dir.clone());
+                }
+                skill_dir
+            } else {
+                dir
+            };
+            if !changed.contains(&skill_dir) {
+                changed.push(skill_dir);
+            }
+        }
+
+        if tokio::time::Instant::now() >= deadline {
+            deadline = tokio::time::Instant::now() + ROOT_POLL_INTERVAL;
+            for root in &roots {
+                let watched = watches.values().any(|(dir, is_root)| *is_root && dir == root);
+                if watched || !root.is_dir() {
+                    continue;
+                }
+                tracing::info!(dir = %root.display(), "Skills directory appeared.");
+                for skill_dir in watch_root(&watcher, &mut watches, root) {
+                    if !changed.contains(&skill_dir) {
+                        changed.push(skill_dir);
+                    }
+                }
+            }
+        }
+
+        let changes: Vec<SkillChange> = {
+            let mut mgr = skills.write().await;
+            changed.iter().filter_map(|dir| mgr.reload_skill_dir(dir)).collect()
+        };
+        for change in changes {
+            let _ = events.send(change.to_event());
+        }
+    }
+}
+
+/// Watches a skills root and the skill directories in it, returning them.
+fn watch_root(watcher: &Inotify, watches: &mut HashMap<i32, (PathBuf, bool)>, root: &Path) -> Vec<PathBuf> {
+    match watcher.add_watch(root, ROOT_WATCH_MASK) {
+        Ok(wd) => { watches.insert(wd, (root.to_path_buf(), true)); },
+        Err(e) => {
+            tracing::warn!(error = %e, "Failed to watch skills directory.");
+            return Vec::new();
+        }
+    }
+    let Ok(entries) = fs::read_dir(root) else { return Vec::new() };
+    let mut skill_dirs = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() && !entry.file_name().to_string_lossy().starts_with('.') {
+            watch_skill_dir(watcher, watches, path.clone());
+            skill_dirs.push(path);
+        }
+    }
+    skill_dirs
+}
+
+fn watch_skill_dir(watcher: &Inotify, watches: &mut HashMap<i32, (PathBuf, bool)>, dir: PathBuf) {
+    match watcher.add_watch(&dir, SKILL_WATCH_MASK) {
+        Ok(wd) => { watches.insert(wd, (dir, false)); },
+        Err(e) => tracing::warn!(error = %e, "Failed to watch skill directory."),
+    }
 }
 
 fn cosine_similarity(v1: &[f32], v2: &[f32]) -> f32 {
@@ -295,6 +917,7 @@ fn cosine_similarity(v1: &[f32], v2: &[f32]) -> f32 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::{write_skill, TempDir};
 
     #[test]
     fn test_cosine_similarity() {
@@ -308,4 +931,136 @@ mod tests {
         let v4 = vec![-1.0, 0.0];
         assert!((cosine_similarity(&v1, &v4) - (-1.0)).abs() < 1e-6);
     }
+
+    #[test]
+    fn test_reload_skill_dir_invalidates_only_changed_skill() {
+        let base = TempDir::new("skills");
+        let alpha = write_skill(&base, "alpha", "first");
+        write_skill(&base, "beta", "second");
+
+        let mut mgr = SkillsManager::new();
+        mgr.load_from_dirs(&[base.to_string_lossy().to_string()]).unwrap();
+        let alpha_key = ("m".to_string(), mgr.get_skill("alpha").unwrap().content_hash());
+        let beta_key = ("m".to_string(), mgr.get_skill("beta").unwrap().content_hash());
+        mgr.embedding_cache.insert(alpha_key.clone(), vec![1.0]);
+        mgr.embedding_cache.insert(beta_key.clone(), vec![1.0]);
+
+        assert!(mgr.reload_skill_dir(&alpha).is_none());
+        write_skill(&base, "alpha", "changed");
+        assert!(matches!(mgr.reload_skill_dir(&alpha), Some(SkillChange::Updated(..))));
+        assert_eq!(mgr.get_skill("alpha").unwrap().metadata.description, "changed");
+        assert!(!mgr.embedding_cache.contains_key(&alpha_key));
+        assert!(mgr.embedding_cache.contains_key(&beta_key));
+
+        fs::remove_dir_all(&alpha).unwrap();
+        assert!(matches!(mgr.reload_skill_dir(&alpha), Some(SkillChange::Removed(..))));
+        assert!(mgr.get_skill("alpha").is_none());
+    }
+
+    #[test]
+    fn test_embedding_cache_roundtrip_drops_stale_entries() {
+        let base = TempDir::new("skills");
+        write_skill(&base, "alpha", "first");
+        let cache_path = base.join("cache").join("embeddings.json");
+
+        let mut mgr = SkillsManager::new();
+        mgr.load_from_dirs(&[base.to_string_lossy().to_string()]).unwrap();
+        mgr.load_embedding_cache(cache_path.clone()).unwrap();
+        let key = ("m".to_string(), mgr.get_skill("alpha").unwrap().content_hash());
+        mgr.embedding_cache.insert(key.clone(), vec![0.5, 0.25]);
+        mgr.embedding_cache.insert(("m".into(), "stale".into()), vec![1.0]);
+        mgr.save_embedding_cache().unwrap();
+
+        let mut reloaded = SkillsManager::new();
+        reloaded.load_embedding_cache(cache_path).unwrap();
+        assert_eq!(reloaded.embedding_cache.get(&key), Some(&vec![0.5, 0.25]));
+        assert_eq!(reloaded.embedding_cache.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_keyword_mode_selects_without_embeddings() {
+        let base = TempDir::new("skills");
+        write_skill(&base, "clock", "Fetches the current date and time");
+        write_skill(&base, "joke-teller", "Tells funny programming jokes");
+        let mut mgr = SkillsManager::new();
+        mgr.load_from_dirs(&[base.to_string_lossy().to_string()]).unwrap();
+
+        let mut opts = RetrievalOptions::from_config(&crate::config::Config::default());
+        opts.mode = RagMode::Keyword;
+        let llm = LlmClient::new("http://127.0.0.1:9".to_string());
+        let selected = mgr.select_skills("tell me a joke about programming", &llm, &opts).await.unwrap();
+        let names: Vec<_> = selected.iter().map(|s| s.metadata.name.as_str()).collect();
+        assert_eq!(names, vec!["joke-teller"]);
+
+        // Sharing only a stopword with a description is not a match
+        let selected = mgr.select_skills("is the printer on?", &llm, &opts).await.unwrap();
+        assert!(selected.is_empty());
+    }
+
+    #[test]
+    fn test_full_frontmatter_and_allowed_tools() {
+        let base = TempDir::new("skills");
+        let dir = base.join("docs");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("SKILL.md"), "---
+name: docs
+description: Reads documentation
+version: 1.2
+license: MIT
+compatibility: Requires bash
+allowed-tools: read_file(docs/*), write_file(docs/*), github__* Bash(git *)
+metadata:
+  author: someone
+---
+Body").unwrap();
+
+        let skill = SkillsManager::parse_skill(&dir.join("SKILL.md")).unwrap();
+        assert_eq!(skill.metadata.version.as_deref(), Some("1.2"));
+        assert_eq!(skill.metadata.license.as_deref(), Some("MIT"));
+        assert_eq!(skill.metadata.compatibility.as_deref(), Some("Requires bash"));
+        assert_eq!(skill.metadata.metadata["author"], "someone");
+
+        let policy = ToolPolicy::for_skills(std::slice::from_ref(&skill));
+        assert!(policy.allows_tool("read_file"));
+        assert!(policy.allows_tool("github__search"));
+        assert!(policy.allows_tool("run_skill_script"));
+        assert!(!policy.allows_tool("apply_patch"));
+        assert!(!policy.allows_tool("activate_skill"));
+        assert!(policy.allows_call("read_file", r#"{"path": "docs/intro.md"}"#));
+        assert!(policy.allows_call("read_file", r#"{"path": "./docs/intro.md"}"#));
+        assert!(!policy.allows_call("read_file", r#"{"path": "/etc/passwd"}"#));
+        assert!(!policy.allows_call("read_file", r#"{"path": "docs/../secrets.txt"}"#));
+        assert!(!policy.allows_call("read_file", r#"{"path": "docs/private/key.pem"}"#));
+
+        // Only the path argument is checked, and every path must match
+        assert!(policy.allows_call("write_file", r#"{"path": "docs/a.md", "content": "/etc/x"}"#));
+        assert!(!policy.allows_call("write_file", r#"{"path": "/etc/x", "content": "docs/a"}"#));
+        assert!(!policy.allows_call("read_file", r#"{"paths": ["docs/a.md", "src/main.rs"]}"#));
+
+        assert_eq!(skill.metadata.allowed_tools.as_deref().unwrap(), ["read_file(docs/*)", "write_file(docs/*)", "github__*", "Bash(git *)"]);
+        assert!(policy.allows_call("Bash", r#"{"command": "git commit -m a/b", "description": "commit"}"#));
+        assert!(!policy.allows_call("Bash", r#"{"command": "rm -rf x"}"#));
+
+        assert!(ToolPolicy::for_skills(&[]).allows_call("write_file", "{}"));
+    }
+
+    #[test]
+    fn test_precedence_follows_search_path() {
+        let base = TempDir::new("skills");
+        let user = write_skill(&base.join("user"), "dup", "from user");
+        let system = write_skill(&base.join("system"), "dup", "from system");
+
+        let mut mgr = SkillsManager::new();
+        let dirs = [base.join("user"), base.join("system")].map(|d| d.to_string_lossy().to_string());
+        mgr.load_from_dirs(&dirs).unwrap();
+        assert_eq!(mgr.get_skill("dup").unwrap().path, user);
+        let which = mgr.which("dup").unwrap();
+        assert_eq!(which.shadowed, vec![system.clone()]);
+
+        // Removing the winner exposes the shadowed skill
+        fs::remove_dir_all(&user).unwrap();
+        mgr.reload_skill_dir(&user);
+        assert_eq!(mgr.get_skill("dup").unwrap().path, system);
+        assert!(mgr.shadowed_skills().is_empty());
+    }
 }
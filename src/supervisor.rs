@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use tokio::process::Command;
+use tokio::sync::{broadcast, oneshot, Mutex};
+use tokio::task::JoinHandle;
+use tokio::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use anyhow::{Result, anyhow};
+
+/// A run at least this long resets the restart backoff.
+const STABLE_AFTER: Duration = Duration::from_secs(30);
+
+/// How long a stopped server may take to exit after SIGTERM before it is killed.
+const STOP_GRACE: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    Always,
+    #[default]
+    OnFailure,
+    Never,
+}
+
+/// A `[[servers]]` entry: a Server process Ruster launches and supervises.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerConfig {
+    pub name: String,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    #[serde(default)]
+    pub restart: RestartPolicy,
+    // Start with the daemon; otherwise only on `server start`
+    #[serde(default = "default_autostart")]
+    pub autostart: bool,
+    // First restart delay, doubled after each quick failure up to the maximum
+    #[serde(default = "default_restart_delay_secs")]
+    pub restart_delay_secs: u64,
+    #[serde(default = "default_max_restart_delay_secs")]
+    pub max_restart_delay_secs: u64,
+}
+
+fn default_autostart() -> bool {
+    true
+}
+
+fn default_restart_delay_secs() -> u64 {
+    1
+}
+
+fn default_max_restart_delay_secs() -> u64 {
+    60
+}
+
+impl ServerConfig {
+    /// Rejects names that could leave the log or socket directory.
+    pub fn validate(&self) -> Result<()> {
+        let valid = !self.name.is_empty()
+            && self.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid {
+            return Err(anyhow!("Invalid server name '{}': use only letters, digits, '_' and '-'", self.name));
+        }
+        Ok(())
+    }
+
+    /// Delay before the next restart after `failures` runs in a row ended early.
+    pub fn backoff(&self, failures: u32) -> Duration {
+        let secs = self.restart_delay_secs.saturating_mul(1u64 << failures.min(32));
+        Duration::from_secs(secs.min(self.max_restart_delay_secs))
+    }
+
+    fn should_restart(&self, success: bool) -> bool {
+        match self.restart {
+            RestartPolicy::Always => true,
+            RestartPolicy::OnFailure => !success,
+            RestartPolicy::Never => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProcessState {
+    Running,
+    Backoff,
+    Exited,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessStatus {
+    pub name: String,
+    pub state: ProcessState,
+    pub pid: Option<u32>,
+    pub restarts: u32,
+    pub last_exit_code: Option<i32>,
+    pub log_path: PathBuf,
+}
+
+struct Supervised {
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+    status: Arc<Mutex<ProcessStatus>>,
+}
+
+/// Launches configured Servers and restarts them according to their policy.
+pub struct Supervisor {
+    log_dir: PathBuf,
+    /// Directory the Servers create their sockets in
+    socket_dir: PathBuf,
+    events: broadcast::Sender<Value>,
+    processes: Mutex<HashMap<String, Supervised>>,
+}
+
+impl Supervisor {
+    pub fn new(log_dir: PathBuf, socket_dir: PathBuf, events: broadcast::Sender<Value>) -> Self {
+        Self { log_dir, socket_dir, events, processes: Mutex::new(HashMap::new()) }
+    }
+
+    /// Starts supervising a server. Fails if it is already running or its
+    /// first launch fails. Returns the pid.
+    pub async fn start(&self, config: &ServerConfig) -> Result<u32> {
+        config.validate()?;
+        let mut processes = self.processes.lock().await;
+        if let Some(p) = processes.get(&config.name)
+            && !p.task.is_finished()
+        {
+            return Err(anyhow!("Server {} is already running", config.name));
+        }
+
+        fs::create_dir_all(&self.log_dir)?;
+        let log_path = self.log_dir.join(format!("{}.log", config.name));
+        let socket = crate::servers::socket_path(&self.socket_dir, &config.name);
+        let mut child = spawn(config, &log_path, &socket)?;
+        let pid = child.id().unwrap_or_default();
+        tracing::info!(server = %config.name, pid = %pid, "Started server process.");
+
+        let status = Arc::new(Mutex::new(ProcessStatus {
+            name: config.name.clone(),
+            state: ProcessState::Running,
+            pid: Some(pid),
+            restarts: 0,
+            last_exit_code: None,
+            log_path: log_path.clone(),
+        }));
+        let (stop_tx, mut stop_rx) = oneshot::channel::<()>();
+        let name = config.name.clone();
+        let (config, events, task_status) = (config.clone(), self.events.clone(), status.clone());
+        let task = tokio::spawn(async move {
+            let mut failures: u32 = 0;
+            loop {
+                let started = Instant::now();
+                let exit = tokio::select! {
+                    res = child.wait() => res,
+                    _ = &mut stop_rx => {
+                        terminate(&mut child).await;
+                        let _ = fs::remove_file(&socket);
+                        return;
+                    }
+                };
+                // A server that died cannot remove its socket, and a stale one looks like a live server
+                let _ = fs::remove_file(&socket);
+                let (success, code) = match &exit {
+                    Ok(s) => (s.success(), s.code()),
+                    Err(_) => (false, None),
+                };
+                failures = if started.elapsed() >= STABLE_AFTER { 0 } else { failures + 1 };
+                let restart = config.should_restart(success);
+                let delay = config.backoff(failures.saturating_sub(1));
+                tracing::warn!(server = %config.name, code = ?code, restart = %restart, "Server process exited.");
+                {
+                    let mut st = task_status.lock().await;
+                    st.pid = None;
+                    st.last_exit_code = code;
+                    st.state = if restart { ProcessState::Backoff } else { ProcessState::Exited };
+                }
+                let _ = events.send(json!({
+                    "event": "server_exited",
+                    "server_name": config.name,
+                    "exit_code": code,
+                    "restart_in_secs": if restart { Some(delay.as_secs_f64()) } else { None }
+                }));
+                if !restart {
+                    return;
+                }
+
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {},
+                    _ = &mut stop_rx => return,
+                }
+                match spawn(&config, &log_path, &socket) {
+                    Ok(c) => {
+                        child = c;
+                        let mut st = task_status.lock().await;
+                        st.pid = child.id();
+                        st.restarts += 1;
+                        st.state = ProcessState::Running;
+                        tracing::info!(server = %config.name, pid = ?st.pid, restarts = %st.restarts, "Restarted server process.");
+                        let _ = events.send(json!({"event": "server_started", "server_name": config.name, "pid": st.pid}));
+                    }
+                    Err(e) => {
+                        tracing::error!(server = %config.name, error = %e, "Failed to restart server process.");
+                        task_status.lock().await.state = ProcessState::Exited;
+                        return;
+                    }
+                }
+            }
+        });
+
+        let _ = self.events.send(json!({"event": "server_started", "server_name": name, "pid": pid}));
+        processes.insert(name, Supervised { stop: stop_tx, task, status });
+        Ok(pid)
+    }
+
+    /// Stops supervising a server and terminates its process.
+    pub async fn stop(&self, name: &str) -> Result<()> {
+        let p = self.processes.lock().await.remove(name)
+            .ok_or_else(|| anyhow!("Server {} is not managed by Ruster", name))?;
+        let _ = p.stop.send(());
+        let _ = p.task.await;
+        tracing::info!(server = %name, "Stopped server process.");
+        Ok(())
+    }
+
+    /// Stops every server at once, so their grace periods overlap.
+    pub async fn stop_all(&self) {
+        let names: Vec<String> = self.processes.lock().await.keys().cloned().collect();
+        futures_util::future::join_all(names.iter().map(|name| self.stop(name))).await;
+    }
+
+    pub async fn status(&self) -> Vec<ProcessStatus> {
+        let processes = self.processes.lock().await;
+        let mut list = Vec::new();
+        for p in processes.values() {
+            list.push(p.status.lock().await.clone());
+        }
+        list.sort_by(|a, b| a.name.cmp(&b.name));
+        list
+    }
+}
+
+/// Sends SIGTERM so the server can clean up, and kills it if it has not
+/// exited within `STOP_GRACE`.
+async fn terminate(child: &mut tokio::process::Child) {
+    if let Some(pid) = child.id() {
+        // SAFETY: `pid` is our child, which has not been reaped yet.
+        unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) };
+        if tokio::time::timeout(STOP_GRACE, child.wait()).await.is_ok() {
+            return;
+        }
+    }
+    let _ = child.kill().await;
+}
+
+fn spawn(config: &ServerConfig, log_path: &Path, socket: &Path) -> Result<tokio::process::Child> {
+    let mut log = OpenOptions::new().create(true).append(true).open(log_path)?;
+    writeln!(log, "--- {} starting {} {:?}", chrono::Local::now().to_rfc3339(), config.command, config.args)?;
+    Command::new(&config.command)
+        .args(&config.args)
+        .envs(&config.env)
+        .env("RUSTER_SERVER_NAME", &config.name)
+        .env("RUSTER_SERVER_SOCKET", socket)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::from(log))
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| anyhow!("Failed to launch server {} ({}): {}", config.name, config.command, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(script: &str, restart: RestartPolicy) -> ServerConfig {
+        ServerConfig {
+            name: "flaky".to_string(),
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), script.to_string()],
+            env: HashMap::from([("GREETING".to_string(), "hello".to_string())]),
+            restart,
+            autostart: true,
+            restart_delay_secs: 0,
+            max_restart_delay_secs: 0,
+        }
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let mut cfg = config("", RestartPolicy::Always);
+        cfg.restart_delay_secs = 1;
+        cfg.max_restart_delay_secs = 10;
+        let delays: Vec<u64> = (0..6).map(|n| cfg.backoff(n).as_secs()).collect();
+        assert_eq!(delays, [1, 2, 4, 8, 10, 10]);
+    }
+
+    #[tokio::test]
+    async fn test_supervised_restarts_and_stderr_log() {
+        let dir = crate::test_util::TempDir::new("sup");
+        let (events, mut rx) = broadcast::channel(100);
+        let sup = Supervisor::new(dir.to_path_buf(), dir.to_path_buf(), events);
+
+        let cfg = config("echo \"$GREETING from $RUSTER_SERVER_NAME\" >&2; exit 3", RestartPolicy::OnFailure);
+        sup.start(&cfg).await.unwrap();
+        assert!(sup.start(&cfg).await.is_err());
+
+        // Failing runs are restarted until stopped
+        let mut exits = 0;
+        while exits < 3 {
+            let ev = rx.recv().await.unwrap();
+            if ev["event"] == "server_exited" {
+                assert_eq!(ev["exit_code"], 3);
+                exits += 1;
+            }
+        }
+        sup.stop("flaky").await.unwrap();
+        assert!(sup.status().await.is_empty());
+        let log = fs::read_to_string(dir.join("flaky.log")).unwrap();
+        assert!(log.matches("hello from flaky").count() >= 3, "{}", log);
+
+        // A clean exit is not restarted under on-failure
+        sup.start(&config("exit 0", RestartPolicy::OnFailure)).await.unwrap();
+        loop {
+            let ev = rx.recv().await.unwrap();
+            if ev["event"] == "server_exited" {
+                assert!(ev["restart_in_secs"].is_null());
+                break;
+            }
+        }
+        let status = sup.status().await;
+        assert_eq!(status[0].state, ProcessState::Exited);
+        assert_eq!(status[0].restarts, 0);
+    }
+
+    #[tokio::test]
+    async fn test_stop_sends_sigterm_and_removes_socket() {
+        let dir = crate::test_util::TempDir::new("sup");
+        let (events, mut rx) = broadcast::channel(100);
+        let sup = Supervisor::new(dir.to_path_buf(), dir.to_path_buf(), events);
+
+        // The server only logs the signal; the socket it leaves behind is removed for it
+        let script = "trap 'echo terminated >&2; exit 0' TERM; touch \"$RUSTER_SERVER_SOCKET\"; while :; do sleep 0.05; done";
+        sup.start(&config(script, RestartPolicy::Always)).await.unwrap();
+        let socket = crate::servers::socket_path(&dir, "flaky");
+        while !socket.exists() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        sup.stop("flaky").await.unwrap();
+        assert!(!socket.exists());
+        assert!(fs::read_to_string(dir.join("flaky.log")).unwrap().contains("terminated"));
+        assert!(rx.try_recv().is_ok_and(|ev| ev["event"] == "server_started"));
+
+        let mut bad = config("exit 0", RestartPolicy::Never);
+        bad.name = "../../x".to_string();
+        assert!(sup.start(&bad).await.is_err());
+    }
+}
@@ -0,0 +1,46 @@
+//! Fixtures shared by the unit tests.
+
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// A fresh directory under the system temp dir, removed on drop so a failing
+/// assertion does not leave it behind.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(prefix: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("ruster-{}-{}", prefix, uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Writes `<dir>/<name>/SKILL.md` with just a name and description, and
+/// returns the skill's directory.
+pub fn write_skill(dir: &Path, name: &str, description: &str) -> PathBuf {
+    let skill_dir = dir.join(name);
+    fs::create_dir_all(&skill_dir).unwrap();
+    fs::write(skill_dir.join("SKILL.md"), format!("---\nname: {}\ndescription: {}\n---\nBody", name, description)).unwrap();
+    skill_dir
+}
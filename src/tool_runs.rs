@@ -0,0 +1,317 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::RwLock;
+use serde::{Deserialize, Serialize};
+use anyhow::{Result, anyhow};
+use uuid::Uuid;
+use crate::config::Config;
+
+/// Metadata recorded for every tool run, stored as `meta.json` next to the
+/// `call`, `stdout` and `stderr` files of the run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolRun {
+    pub id: String,
+    pub session_id: String,
+    pub tool: String,
+    pub arguments: String,
+    pub started_at: String,
+    pub duration_ms: u64,
+    pub exit_code: Option<i32>,
+    #[serde(default)]
+    pub artifacts: Vec<Artifact>,
+}
+
+/// Binary output of a tool run, stored under `artifacts/<id>` in the run dir.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Artifact {
+    pub id: String,
+    pub mime_type: String,
+    pub size: u64,
+}
+
+pub struct OutputPage {
+    pub content: String,
+    pub start: usize,
+    pub end: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct PruneStats {
+    pub removed: usize,
+    pub freed_bytes: u64,
+}
+
+pub fn runs_dir(config: &Config) -> PathBuf {
+    crate::config::expand_path(&config.tool_run_dir).join("tools")
+}
+
+/// Resolves the directory of a run, rejecting anything that is not a UUID so
+/// client supplied ids cannot escape the tool run directory.
+pub fn run_path(base: &Path, id: &str) -> Result<PathBuf> {
+    Uuid::parse_str(id).map_err(|_| anyhow!("Invalid tool run id: {}", id))?;
+    Ok(base.join(id))
+}
+
+impl ToolRun {
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        fs::write(dir.join("meta.json"), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+pub fn store_artifact(run_dir: &Path, data: &[u8]) -> Result<Artifact> {
+    let artifacts_dir = run_dir.join("artifacts");
+    fs::create_dir_all(&artifacts_dir)?;
+    let artifact = Artifact {
+        id: Uuid::new_v4().to_string(),
+        mime_type: sniff_mime(data).to_string(),
+        size: data.len() as u64,
+    };
+    fs::write(artifacts_dir.join(&artifact.id), data)?;
+    Ok(artifact)
+}
+
+/// Returns the artifact metadata and its on-disk path.
+pub fn get_artifact(base: &Path, call_id: &str, artifact_id: &str) -> Result<(Artifact, PathBuf)> {
+    let run = get_run(base, call_id)?;
+    let artifact = run.artifacts.into_iter()
+        .find(|a| a.id == artifact_id)
+        .ok_or_else(|| anyhow!("Artifact {} not found in tool run {}", artifact_id, call_id))?;
+    let path = run_path(base, call_id)?.join("artifacts").join(&artifact.id);
+    Ok((artifact, path))
+}
+
+/// Reads at most `limit` bytes of an artifact starting at `offset`.
+pub fn read_artifact(path: &Path, offset: u64, limit: u64) -> Result<Vec<u8>> {
+    use std::io::{Read, Seek, SeekFrom};
+    let mut file = fs::File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut data = Vec::new();
+    file.take(limit).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// Guesses a MIME type from the leading bytes of `data`.
+pub fn sniff_mime(data: &[u8]) -> &'static str {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"BZh", "application/x-bzip2"),
+        (b"\xfd7zXZ\x00", "application/x-xz"),
+        (b"\x28\xb5\x2f\xfd", "application/zstd"),
+    ];
+    for (magic, mime) in SIGNATURES {
+        if data.starts_with(magic) {
+            return mime;
+        }
+    }
+    if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        return "image/webp";
+    }
+    if data.len() > 262 && &data[257..262] == b"ustar" {
+        return "application/x-tar";
+    }
+    match std::str::from_utf8(data) {
+        Ok(text) if text.trim_start().starts_with('<') && text.contains("<svg") => "image/svg+xml",
+        Ok(text) if serde_json::from_str::<serde_json::Value>(text).is_ok() => "application/json",
+        Ok(_) => "text/plain",
+        Err(_) => "application/octet-stream",
+    }
+}
+
+pub fn get_run(base: &Path, id: &str) -> Result<ToolRun> {
+    let meta_path = run_path(base, id)?.join("meta.json");
+    if !meta_path.exists() {
+        return Err(anyhow!("Tool run {} not found", id));
+    }
+    let content = fs::read_to_string(meta_path)?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// Lists recorded runs, newest first. Runs without metadata are skipped.
+pub fn list_runs(base: &Path, session_id: Option<&str>) -> Result<Vec<ToolRun>> {
+    let mut runs = Vec::new();
+    if !base.exists() {
+        return Ok(runs);
+    }
+    for entry in fs::read_dir(base)? {
+        let entry = entry?;
+        let meta_path = entry.path().join("meta.json");
+        let Ok(content) = fs::read_to_string(&meta_path) else { continue };
+        match serde_json::from_str::<ToolRun>(&content) {
+            Ok(run) => {
+                if session_id.is_none_or(|id| run.session_id == id) {
+                    runs.push(run);
+                }
+            },
+            Err(e) => tracing::warn!(path = %meta_path.display(), error = %e, "Invalid tool run metadata."),
+        }
+    }
+    runs.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+    Ok(runs)
+}
+
+/// Reads a line range of a run's `stdout`, `stderr` or `diff`, optionally
+/// keeping only the lines containing `search`.
+pub fn read_output(base: &Path, id: &str, stream: &str, offset: usize, limit: usize, search: Option<&str>) -> Result<OutputPage> {
+    if !["stdout", "stderr", "diff"].contains(&stream) {
+        return Err(anyhow!("Invalid stream: {}. Expected 'stdout', 'stderr' or 'diff'", stream));
+    }
+    let path = run_path(base, id)?.join(stream);
+    if !path.exists() {
+        if stream == "diff" {
+            return Err(anyhow!("Tool run {} made no file changes", id));
+        }
+        return Err(anyhow!("Tool run {} not found", id));
+    }
+
+    let content = fs::read_to_string(path)?;
+    let mut lines: Vec<_> = content.lines().collect();
+    if let Some(term) = search {
+        lines.retain(|l| l.contains(term));
+    }
+
+    let total = lines.len();
+    let start = offset.min(total);
+    let end = offset.saturating_add(limit).min(total);
+    Ok(OutputPage {
+        content: lines[start..end].join("\n"),
+        start,
+        end,
+        total,
+    })
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else { return 0 };
+    entries.filter_map(|e| e.ok()).map(|e| {
+        match e.file_type() {
+            Ok(ft) if ft.is_dir() => dir_size(&e.path()),
+            _ => e.metadata().map(|m| m.len()).unwrap_or(0),
+        }
+    }).sum()
+}
+
+/// Removes runs older than `max_age`, then the oldest remaining runs until
+/// the total size fits in `max_bytes`.
+pub fn prune(base: &Path, max_age: Option<Duration>, max_bytes: Option<u64>) -> Result<PruneStats> {
+    let mut stats = PruneStats::default();
+    if !base.exists() {
+        return Ok(stats);
+    }
+
+    let now = SystemTime::now();
+    let mut runs = Vec::new();
+    for entry in fs::read_dir(base)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let modified = entry.metadata()?.modified().unwrap_or(now);
+        runs.push((entry.path(), modified, dir_size(&entry.path())));
+    }
+    // Oldest first
+    runs.sort_by_key(|(_, modified, _)| *modified);
+
+    let mut total: u64 = runs.iter().map(|(_, _, size)| size).sum();
+    for (path, modified, size) in runs {
+        let expired = max_age.is_some_and(|age| now.duration_since(modified).unwrap_or_default() > age);
+        let over_budget = max_bytes.is_some_and(|max| total > max);
+        if !expired && !over_budget {
+            continue;
+        }
+        match fs::remove_dir_all(&path) {
+            Ok(()) => {
+                tracing::debug!(path = %path.display(), expired = %expired, "Removed tool run.");
+                total -= size;
+                stats.removed += 1;
+                stats.freed_bytes += size;
+            },
+            Err(e) => tracing::warn!(path = %path.display(), error = %e, "Failed to remove tool run."),
+        }
+    }
+    Ok(stats)
+}
+
+pub async fn start_retention_loop(config: Arc<RwLock<Config>>) {
+    let mut timer = tokio::time::interval(Duration::from_secs(3600));
+    loop {
+        timer.tick().await;
+
+        let (base, max_age, max_bytes) = {
+            let cfg = config.read().await;
+            (
+                runs_dir(&cfg),
+                (cfg.tool_run_retention_secs > 0).then(|| Duration::from_secs(cfg.tool_run_retention_secs)),
+                (cfg.tool_run_max_bytes > 0).then_some(cfg.tool_run_max_bytes),
+            )
+        };
+
+        match prune(&base, max_age, max_bytes) {
+            Ok(stats) if stats.removed > 0 => {
+                tracing::info!(removed = %stats.removed, freed_bytes = %stats.freed_bytes, "Pruned tool runs.");
+            },
+            Ok(_) => tracing::debug!("Tool run retention check: nothing to prune"),
+            Err(e) => tracing::error!("Tool run retention error: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_run(base: &Path, bytes: usize) -> String {
+        let id = Uuid::new_v4().to_string();
+        let dir = base.join(&id);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("stdout"), vec![b'x'; bytes]).unwrap();
+        id
+    }
+
+    #[test]
+    fn test_prune_by_size_removes_oldest() {
+        let base = crate::test_util::TempDir::new("runs");
+        let oldest = make_run(&base, 100);
+        std::thread::sleep(Duration::from_millis(20));
+        let newest = make_run(&base, 100);
+
+        let stats = prune(&base, None, Some(150)).unwrap();
+        assert_eq!(stats.removed, 1);
+        assert!(!base.join(&oldest).exists());
+        assert!(base.join(&newest).exists());
+    }
+
+    #[test]
+    fn test_read_artifact_in_chunks() {
+        let base = crate::test_util::TempDir::new("artifacts");
+        let artifact = store_artifact(&base, b"0123456789").unwrap();
+        let path = base.join("artifacts").join(&artifact.id);
+        assert_eq!(read_artifact(&path, 0, 4).unwrap(), b"0123");
+        assert_eq!(read_artifact(&path, 8, 4).unwrap(), b"89");
+        assert!(read_artifact(&path, 20, 4).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sniff_mime() {
+        assert_eq!(sniff_mime(b"\x89PNG\r\n\x1a\n\0\0"), "image/png");
+        assert_eq!(sniff_mime(br#"{"a": 1}"#), "application/json");
+        assert_eq!(sniff_mime(b"hello"), "text/plain");
+        assert_eq!(sniff_mime(&[0xff, 0x00, 0xfe]), "application/octet-stream");
+    }
+
+    #[test]
+    fn test_run_path_rejects_traversal() {
+        let base = Path::new("/tmp/ruster.run/tools");
+        assert!(run_path(base, "../../etc").is_err());
+        assert!(run_path(base, &Uuid::new_v4().to_string()).is_ok());
+    }
+}